@@ -0,0 +1,429 @@
+use crate::{
+    match_objects, DatasetKind, ImageAnnotation, ImageDataset, MatchConfig, MediaAnnotation,
+    Object, Project, Result, Tag, TagValue,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// The options for comparing two annotators.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgreementConfig {
+    pub match_config: MatchConfig,
+    /// The number of equal-width IoU histogram bins over `[0, 1]`.
+    pub histogram_bins: usize,
+}
+
+impl Default for AgreementConfig {
+    fn default() -> Self {
+        Self {
+            match_config: MatchConfig::default(),
+            histogram_bins: 10,
+        }
+    }
+}
+
+/// The agreement between a reference annotator (left) and another
+/// annotator (right). Objects without area, such as points and
+/// polylines, are not compared.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgreementReport {
+    /// The number of images labeled by both annotators.
+    pub num_images: usize,
+    /// The agreement over all classes.
+    pub overall: ClassAgreement,
+    pub classes: IndexMap<String, ClassAgreement>,
+}
+
+/// The agreement of the objects of a class.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClassAgreement {
+    pub num_left: usize,
+    pub num_right: usize,
+    pub num_matched: usize,
+    /// The number of left objects without a counterpart on the right.
+    pub missed: usize,
+    /// The number of right objects without a counterpart on the left.
+    pub extra: usize,
+    /// The F1 score of the matching, or `None` if there is no object.
+    pub f1: Option<f64>,
+    pub iou: IouStats,
+    /// The number of matched pairs disagreeing on each tag, where the tag
+    /// is either missing on one side or has a different value.
+    pub tag_disagreements: IndexMap<String, usize>,
+}
+
+/// The distribution of the IoU of matched pairs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IouStats {
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// The number of pairs in each equal-width bin over `[0, 1]`.
+    pub histogram: Vec<usize>,
+}
+
+/// Compare two projects labeling the same images.
+///
+/// Images are paired by their dataset and image names. Images present
+/// in only one project are skipped.
+pub fn project_agreement(
+    left: &Project,
+    right: &Project,
+    config: &AgreementConfig,
+) -> Result<AgreementReport> {
+    let mut builder = ReportBuilder::new(config);
+
+    for (dataset_name, left_dataset, right_dataset) in image_dataset_pairs(left, right) {
+        for image_name in &left_dataset.image_names {
+            let (Some(left_image), Some(right_image)) = (
+                left_dataset.get_image(image_name),
+                right_dataset.get_image(image_name),
+            ) else {
+                warn!("image '{image_name}' in dataset '{dataset_name}' is labeled only once");
+                continue;
+            };
+            let left_ann = left_image.ann()?;
+            let right_ann = right_image.ann()?;
+            builder.add(&left_ann, &right_ann)?;
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+/// Compare the objects of two labelers within one project.
+///
+/// Objects are attributed to labelers by their `labeler_login`. Images
+/// where only one of the labelers created objects count those objects
+/// as missed or extra, and images labeled by neither are skipped.
+pub fn labeler_agreement(
+    project: &Project,
+    left_login: &str,
+    right_login: &str,
+    config: &AgreementConfig,
+) -> Result<AgreementReport> {
+    let mut builder = ReportBuilder::new(config);
+
+    for item in project.iter_annotations() {
+        let MediaAnnotation::Image(ann) = item?.annotation else {
+            continue;
+        };
+        builder.add_labelers(&ann, left_login, right_login)?;
+    }
+
+    Ok(builder.finish())
+}
+
+/// Compare two annotations of the same image.
+pub fn annotation_agreement(
+    left: &ImageAnnotation,
+    right: &ImageAnnotation,
+    config: &AgreementConfig,
+) -> Result<AgreementReport> {
+    let mut builder = ReportBuilder::new(config);
+    builder.add(left, right)?;
+    Ok(builder.finish())
+}
+
+fn image_dataset_pairs<'a>(
+    left: &'a Project,
+    right: &'a Project,
+) -> Vec<(&'a str, &'a ImageDataset, &'a ImageDataset)> {
+    let mut dataset_names: Vec<&String> = left.datasets.keys().collect();
+    dataset_names.sort_unstable();
+
+    dataset_names
+        .into_iter()
+        .filter_map(|name| {
+            let DatasetKind::Image(left_dataset) = &left.datasets[name].kind else {
+                return None;
+            };
+            let Some(DatasetKind::Image(right_dataset)) =
+                right.datasets.get(name).map(|dataset| &dataset.kind)
+            else {
+                warn!("dataset '{name}' is missing in the compared project");
+                return None;
+            };
+            Some((name.as_str(), left_dataset, right_dataset))
+        })
+        .collect()
+}
+
+fn filter_labeler(ann: &ImageAnnotation, login: &str) -> ImageAnnotation {
+    ImageAnnotation {
+        objects: ann
+            .objects
+            .iter()
+            .filter(|obj| obj.labeler_login.as_deref() == Some(login))
+            .cloned()
+            .collect(),
+        ..ann.clone()
+    }
+}
+
+/// Accumulates the statistics of compared annotations.
+struct ReportBuilder<'a> {
+    config: &'a AgreementConfig,
+    num_images: usize,
+    classes: IndexMap<String, ClassAccumulator>,
+}
+
+#[derive(Default)]
+struct ClassAccumulator {
+    num_left: usize,
+    num_right: usize,
+    ious: Vec<f64>,
+    tag_disagreements: IndexMap<String, usize>,
+}
+
+impl<'a> ReportBuilder<'a> {
+    fn new(config: &'a AgreementConfig) -> Self {
+        Self {
+            config,
+            num_images: 0,
+            classes: IndexMap::new(),
+        }
+    }
+
+    fn add(&mut self, left: &ImageAnnotation, right: &ImageAnnotation) -> Result<()> {
+        let result = match_objects(left, right, &self.config.match_config)?;
+        self.num_images += 1;
+
+        for &index in result
+            .matches
+            .iter()
+            .map(|m| &m.left)
+            .chain(&result.unmatched_left)
+        {
+            if let Some(class) = class_of(&mut self.classes, &left.objects[index]) {
+                class.num_left += 1;
+            }
+        }
+        for &index in result
+            .matches
+            .iter()
+            .map(|m| &m.right)
+            .chain(&result.unmatched_right)
+        {
+            if let Some(class) = class_of(&mut self.classes, &right.objects[index]) {
+                class.num_right += 1;
+            }
+        }
+
+        for m in &result.matches {
+            let (lobj, robj) = (&left.objects[m.left], &right.objects[m.right]);
+            let Some(class) = class_of(&mut self.classes, lobj) else {
+                continue;
+            };
+            class.ious.push(m.iou);
+            for name in disagreeing_tags(lobj, robj) {
+                *class.tag_disagreements.entry(name).or_default() += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compare the objects of two labelers in one annotation.
+    fn add_labelers(&mut self, ann: &ImageAnnotation, left: &str, right: &str) -> Result<()> {
+        let left_ann = filter_labeler(ann, left);
+        let right_ann = filter_labeler(ann, right);
+        if left_ann.objects.is_empty() && right_ann.objects.is_empty() {
+            return Ok(());
+        }
+        self.add(&left_ann, &right_ann)
+    }
+
+    fn finish(self) -> AgreementReport {
+        let bins = self.config.histogram_bins;
+        let mut overall = ClassAccumulator::default();
+
+        let classes = self
+            .classes
+            .into_iter()
+            .map(|(name, class)| {
+                overall.num_left += class.num_left;
+                overall.num_right += class.num_right;
+                overall.ious.extend(&class.ious);
+                for (tag, count) in &class.tag_disagreements {
+                    *overall.tag_disagreements.entry(tag.clone()).or_default() += count;
+                }
+                (name, class.summarize(bins))
+            })
+            .collect();
+
+        AgreementReport {
+            num_images: self.num_images,
+            overall: overall.summarize(bins),
+            classes,
+        }
+    }
+}
+
+fn class_of<'a>(
+    classes: &'a mut IndexMap<String, ClassAccumulator>,
+    obj: &Object,
+) -> Option<&'a mut ClassAccumulator> {
+    Some(classes.entry(obj.class_name()?).or_default())
+}
+
+impl ClassAccumulator {
+    fn summarize(mut self, bins: usize) -> ClassAgreement {
+        let num_matched = self.ious.len();
+        let num_objects = self.num_left + self.num_right;
+        let f1 = (num_objects > 0).then(|| 2.0 * num_matched as f64 / num_objects as f64);
+
+        self.ious.sort_by(f64::total_cmp);
+        let mut histogram = vec![0; bins];
+        if bins > 0 {
+            for &iou in &self.ious {
+                let bin = ((iou * bins as f64) as usize).min(bins - 1);
+                histogram[bin] += 1;
+            }
+        }
+        let median = match num_matched {
+            0 => None,
+            count if count % 2 == 1 => Some(self.ious[count / 2]),
+            count => Some((self.ious[count / 2 - 1] + self.ious[count / 2]) / 2.0),
+        };
+
+        ClassAgreement {
+            num_left: self.num_left,
+            num_right: self.num_right,
+            num_matched,
+            missed: self.num_left.saturating_sub(num_matched),
+            extra: self.num_right.saturating_sub(num_matched),
+            f1,
+            iou: IouStats {
+                mean: (num_matched > 0).then(|| self.ious.iter().sum::<f64>() / num_matched as f64),
+                median,
+                min: self.ious.first().copied(),
+                max: self.ious.last().copied(),
+                histogram,
+            },
+            tag_disagreements: self.tag_disagreements,
+        }
+    }
+}
+
+/// List the tag names whose presence or values differ between two
+/// objects.
+fn disagreeing_tags(left: &Object, right: &Object) -> Vec<String> {
+    let collect = |obj: &Object| -> IndexMap<String, Vec<Option<TagValue>>> {
+        let mut tags: IndexMap<String, Vec<Option<TagValue>>> = IndexMap::new();
+        for Tag { name, value, .. } in obj.geometry.tags().into_iter().flatten() {
+            tags.entry(name.clone()).or_default().push(value.clone());
+        }
+        tags
+    };
+    let left = collect(left);
+    let right = collect(right);
+
+    let mut names: Vec<String> = left
+        .iter()
+        .filter(|(name, values)| right.get(*name) != Some(values))
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.extend(
+        right
+            .keys()
+            .filter(|name| !left.contains_key(*name))
+            .cloned(),
+    );
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Geometry, Points, RectangleGeometry, Size};
+    use noisy_float::types::r64;
+
+    fn object(id: usize, login: &str, class: &str, x: f64) -> Object {
+        Object {
+            id,
+            class_id: None,
+            class_title: Some(class.to_string()),
+            labeler_login: Some(login.to_string()),
+            created_at: None,
+            updated_at: None,
+            geometry: Geometry::Rectangle(RectangleGeometry {
+                tags: None,
+                points: Points {
+                    exterior: vec![(r64(x), r64(0.0)), (r64(x + 10.0), r64(10.0))],
+                    interior: vec![],
+                },
+            }),
+        }
+    }
+
+    fn annotation(objects: Vec<Object>) -> ImageAnnotation {
+        ImageAnnotation {
+            name: "image.png".to_string(),
+            description: None,
+            size: Size {
+                width: 100,
+                height: 100,
+            },
+            tags: None,
+            objects,
+        }
+    }
+
+    #[test]
+    fn identical_annotations_agree() {
+        let ann = annotation(vec![
+            object(1, "a", "car", 0.0),
+            object(2, "a", "dog", 40.0),
+        ]);
+        let report = annotation_agreement(&ann, &ann, &AgreementConfig::default()).unwrap();
+
+        assert_eq!(report.num_images, 1);
+        assert_eq!(report.overall.num_matched, 2);
+        assert_eq!((report.overall.missed, report.overall.extra), (0, 0));
+        assert_eq!(report.overall.f1, Some(1.0));
+        assert_eq!(report.overall.iou.mean, Some(1.0));
+        assert_eq!(report.overall.iou.histogram[9], 2);
+        assert_eq!(report.classes["car"].num_matched, 1);
+    }
+
+    #[test]
+    fn shifted_object_lowers_iou() {
+        let left = annotation(vec![object(1, "a", "car", 0.0)]);
+        let right = annotation(vec![object(1, "b", "car", 2.0)]);
+        let report = annotation_agreement(&left, &right, &AgreementConfig::default()).unwrap();
+
+        // The boxes overlap by 80 pixels, giving an IoU of 80 / 120.
+        let iou = report.overall.iou.mean.unwrap();
+        assert!((iou - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(report.overall.iou.histogram[6], 1);
+    }
+
+    #[test]
+    fn labeler_without_objects_counts_as_missed() {
+        let config = AgreementConfig::default();
+        let mut builder = ReportBuilder::new(&config);
+
+        // Only the left labeler annotated the first image, and neither
+        // annotated the second one.
+        let first = annotation(vec![
+            object(1, "a", "car", 0.0),
+            object(2, "a", "car", 40.0),
+        ]);
+        let second = annotation(vec![object(1, "c", "car", 0.0)]);
+        let third = annotation(vec![object(1, "a", "car", 0.0), object(2, "b", "car", 0.0)]);
+        for ann in [&first, &second, &third] {
+            builder.add_labelers(ann, "a", "b").unwrap();
+        }
+        let report = builder.finish();
+
+        assert_eq!(report.num_images, 2);
+        assert_eq!(report.overall.num_left, 3);
+        assert_eq!(report.overall.num_right, 1);
+        assert_eq!(report.overall.num_matched, 1);
+        assert_eq!((report.overall.missed, report.overall.extra), (2, 0));
+        assert_eq!(report.overall.f1, Some(0.5));
+    }
+}
@@ -1,6 +1,74 @@
 use crate::{geometry::Geometry, objects::Object, tags::Tag, Shape};
 use serde::{Deserialize, Serialize};
 
+/// The annotation of a media item of any kind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaAnnotation {
+    Image(ImageAnnotation),
+    Video(VideoAnnotation),
+    PointCloud(PointCloudAnnotation),
+    PointCloudEpisode(PointCloudEpisodeAnnotation),
+}
+
+impl MediaAnnotation {
+    /// Get the number of objects.
+    pub fn num_objects(&self) -> usize {
+        match self {
+            MediaAnnotation::Image(ann) => ann.objects.len(),
+            MediaAnnotation::Video(ann) => ann.objects.len(),
+            MediaAnnotation::PointCloud(ann) => ann.objects.len(),
+            MediaAnnotation::PointCloudEpisode(ann) => ann.objects.len(),
+        }
+    }
+
+    /// Get the class name of each object, falling back to the class id
+    /// for image objects without a class title.
+    pub fn object_classes(&self) -> Vec<Option<String>> {
+        match self {
+            MediaAnnotation::Image(ann) => ann.objects.iter().map(|obj| obj.class_name()).collect(),
+            MediaAnnotation::Video(ann) => ann
+                .objects
+                .iter()
+                .map(|obj| obj.class_title.clone())
+                .collect(),
+            MediaAnnotation::PointCloud(ann) => ann
+                .objects
+                .iter()
+                .map(|obj| Some(obj.class_title.clone()))
+                .collect(),
+            MediaAnnotation::PointCloudEpisode(ann) => ann
+                .objects
+                .iter()
+                .map(|obj| Some(obj.class_title.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl From<PointCloudEpisodeAnnotation> for MediaAnnotation {
+    fn from(v: PointCloudEpisodeAnnotation) -> Self {
+        Self::PointCloudEpisode(v)
+    }
+}
+
+impl From<PointCloudAnnotation> for MediaAnnotation {
+    fn from(v: PointCloudAnnotation) -> Self {
+        Self::PointCloud(v)
+    }
+}
+
+impl From<VideoAnnotation> for MediaAnnotation {
+    fn from(v: VideoAnnotation) -> Self {
+        Self::Video(v)
+    }
+}
+
+impl From<ImageAnnotation> for MediaAnnotation {
+    fn from(v: ImageAnnotation) -> Self {
+        Self::Image(v)
+    }
+}
+
 /// The image annotation data.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ImageAnnotation {
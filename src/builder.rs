@@ -0,0 +1,509 @@
+use crate::{
+    utils::write_json, ClassMeta, Error, FsStorage, Geometry, ImageAnnotation, Object, Project,
+    ProjectMeta, Result, Shape, Size, Storage, Tag, TagMeta, TagValue, ValueType,
+};
+use indexmap::IndexMap;
+use std::{collections::HashSet, io::Write, path::Path, sync::Arc};
+use tracing::warn;
+
+/// Allocates increasing ids, skipping the reserved ones.
+#[derive(Debug, Clone)]
+pub struct IdAllocator {
+    next: usize,
+}
+
+impl Default for IdAllocator {
+    fn default() -> Self {
+        Self { next: 1 }
+    }
+}
+
+impl IdAllocator {
+    /// Create an allocator starting from 1.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the next free id.
+    pub fn next_id(&mut self) -> usize {
+        let id = self.next;
+        self.next += 1;
+        id
+    }
+
+    /// Make sure that the id is never allocated.
+    pub fn reserve(&mut self, id: usize) {
+        self.next = self.next.max(id + 1);
+    }
+}
+
+/// Builds a project in memory and writes it out.
+///
+/// Class and object ids are allocated by the builder, and annotations
+/// are checked against the project meta as they are added.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectBuilder {
+    meta: ProjectMeta,
+    class_ids: IdAllocator,
+    object_ids: IdAllocator,
+    /// The object ids used across all images.
+    used_object_ids: HashSet<usize>,
+    /// The images keyed by dataset and image names.
+    datasets: IndexMap<String, IndexMap<String, BuiltImage>>,
+}
+
+#[derive(Debug, Clone)]
+struct BuiltImage {
+    annotation: ImageAnnotation,
+    /// The image content.
+    data: Vec<u8>,
+}
+
+impl ProjectBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from an existing project meta.
+    pub fn with_meta(meta: ProjectMeta) -> Result<Self> {
+        meta.validate()?;
+
+        let mut class_ids = IdAllocator::new();
+        for id in meta.classes.iter().filter_map(|class| class.id) {
+            class_ids.reserve(id);
+        }
+
+        Ok(Self {
+            meta,
+            class_ids,
+            ..Self::default()
+        })
+    }
+
+    /// Define a class of a shape.
+    pub fn class<T>(self, title: T, shape: Shape) -> Result<Self>
+    where
+        T: Into<String>,
+    {
+        self.class_meta(ClassMeta::new(title.into(), shape))
+    }
+
+    /// Define a class. An id is allocated if the class has none, and an
+    /// explicit id must not be taken by another class.
+    pub fn class_meta(mut self, mut class: ClassMeta) -> Result<Self> {
+        let is_taken_id = |id| self.meta.classes.iter().any(|other| other.id == Some(id));
+        if self.meta.class(&class.title).is_some() || class.id.is_some_and(is_taken_id) {
+            return Err(Error::DuplicateClass(class.title));
+        }
+        match class.id {
+            Some(id) => self.class_ids.reserve(id),
+            None => class.id = Some(self.class_ids.next_id()),
+        }
+        self.meta.classes.push(class);
+        Ok(self)
+    }
+
+    /// Define a tag.
+    pub fn tag(mut self, tag: TagMeta) -> Result<Self> {
+        if self.meta.tag(&tag.name).is_some() {
+            return Err(Error::DuplicateTag(tag.name));
+        }
+        self.meta.tags.push(tag);
+        Ok(self)
+    }
+
+    pub fn meta(&self) -> &ProjectMeta {
+        &self.meta
+    }
+
+    /// Start building the annotation of an image, allocating object ids
+    /// from the project.
+    pub fn annotate<N>(&mut self, name: N, width: u64, height: u64) -> ImageAnnotationBuilder<'_>
+    where
+        N: Into<String>,
+    {
+        ImageAnnotationBuilder::new(&self.meta, &mut self.object_ids, name, width, height)
+    }
+
+    /// Add an image along with its content to a dataset.
+    ///
+    /// Object ids must be unique across the project. An image of the
+    /// same name in the dataset is replaced.
+    pub fn add_image(
+        &mut self,
+        dataset: &str,
+        ann: ImageAnnotation,
+        data: Vec<u8>,
+    ) -> Result<&mut Self> {
+        self.meta.validate_image(&ann)?;
+
+        let replaced = self
+            .datasets
+            .get(dataset)
+            .and_then(|images| images.get(&ann.name));
+        let replaced_ids: HashSet<usize> = replaced
+            .into_iter()
+            .flat_map(|image| &image.annotation.objects)
+            .map(|obj| obj.id)
+            .collect();
+        for obj in &ann.objects {
+            if self.used_object_ids.contains(&obj.id) && !replaced_ids.contains(&obj.id) {
+                return Err(Error::DuplicateObjectId(obj.id));
+            }
+        }
+
+        for id in &replaced_ids {
+            self.used_object_ids.remove(id);
+        }
+        for obj in &ann.objects {
+            self.object_ids.reserve(obj.id);
+            self.used_object_ids.insert(obj.id);
+        }
+
+        let images = self.datasets.entry(dataset.to_string()).or_default();
+        if images.contains_key(&ann.name) {
+            warn!("image '{}' in dataset '{dataset}' is replaced", ann.name);
+        }
+        let image = BuiltImage {
+            annotation: ann,
+            data,
+        };
+        images.insert(image.annotation.name.clone(), image);
+        Ok(self)
+    }
+
+    /// Write the project to a directory.
+    pub fn write<P>(&self, dir: P) -> Result<Project>
+    where
+        P: AsRef<Path>,
+    {
+        self.write_with(Arc::new(FsStorage), dir.as_ref())
+    }
+
+    /// Write the project to a directory of a storage.
+    pub fn write_with(&self, storage: Arc<dyn Storage>, dir: &Path) -> Result<Project> {
+        write_json(&*storage, &dir.join("meta.json"), &self.meta)?;
+
+        for (dataset_name, images) in &self.datasets {
+            let dataset_dir = dir.join(dataset_name);
+
+            for (image_name, image) in images {
+                let ann_path = dataset_dir.join("ann").join(format!("{image_name}.json"));
+                write_json(&*storage, &ann_path, &image.annotation)?;
+
+                let image_path = dataset_dir.join("img").join(image_name);
+                let mut writer = storage.open_write(&image_path)?;
+                writer
+                    .write_all(&image.data)
+                    .and_then(|()| writer.flush())
+                    .map_err(|error| Error::write_file_error(&image_path, error))?;
+            }
+        }
+
+        Project::open_with(storage, dir)
+    }
+}
+
+/// Builds an image annotation, checking classes and tags against the
+/// project meta.
+#[derive(Debug)]
+pub struct ImageAnnotationBuilder<'a> {
+    meta: &'a ProjectMeta,
+    object_ids: &'a mut IdAllocator,
+    annotation: ImageAnnotation,
+}
+
+impl<'a> ImageAnnotationBuilder<'a> {
+    pub fn new<N>(
+        meta: &'a ProjectMeta,
+        object_ids: &'a mut IdAllocator,
+        name: N,
+        width: u64,
+        height: u64,
+    ) -> Self
+    where
+        N: Into<String>,
+    {
+        Self {
+            meta,
+            object_ids,
+            annotation: ImageAnnotation {
+                name: name.into(),
+                description: None,
+                size: Size { width, height },
+                tags: None,
+                objects: vec![],
+            },
+        }
+    }
+
+    pub fn description<D>(mut self, description: D) -> Self
+    where
+        D: Into<String>,
+    {
+        self.annotation.description = Some(description.into());
+        self
+    }
+
+    /// Attach a tag with a value to the image.
+    pub fn tag<V>(mut self, name: &str, value: V) -> Result<Self>
+    where
+        V: Into<TagValue>,
+    {
+        let tag = new_tag(name, Some(value.into()));
+        self.meta.validate_tag(&tag)?;
+        self.annotation.tags.get_or_insert_with(Vec::new).push(tag);
+        Ok(self)
+    }
+
+    /// Attach a tag without value to the image.
+    pub fn flag(mut self, name: &str) -> Result<Self> {
+        let tag = new_tag(name, None);
+        self.meta.validate_tag(&tag)?;
+        self.annotation.tags.get_or_insert_with(Vec::new).push(tag);
+        Ok(self)
+    }
+
+    /// Add an object of a class with a newly allocated id.
+    pub fn object(self, class_title: &str, geometry: Geometry) -> Result<Self> {
+        let class = self
+            .meta
+            .class(class_title)
+            .ok_or_else(|| Error::UnknownClass(class_title.to_string()))?;
+        let obj = Object {
+            id: 0,
+            class_id: class.id,
+            class_title: Some(class.title.clone()),
+            labeler_login: None,
+            created_at: None,
+            updated_at: None,
+            geometry,
+        };
+        self.push_object(obj)
+    }
+
+    /// Add an object, replacing its id with a newly allocated one.
+    pub fn push_object(mut self, mut obj: Object) -> Result<Self> {
+        self.meta.validate_object(&obj)?;
+        obj.id = self.object_ids.next_id();
+        self.annotation.objects.push(obj);
+        Ok(self)
+    }
+
+    pub fn build(self) -> ImageAnnotation {
+        self.annotation
+    }
+}
+
+impl ProjectMeta {
+    /// Find a class by its title.
+    pub fn class(&self, title: &str) -> Option<&ClassMeta> {
+        self.classes.iter().find(|class| class.title == title)
+    }
+
+    /// Find a tag by its name.
+    pub fn tag(&self, name: &str) -> Option<&TagMeta> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Check that class titles, class ids and tag names are unique.
+    pub fn validate(&self) -> Result<()> {
+        let mut titles = HashSet::new();
+        let mut ids = HashSet::new();
+        for class in &self.classes {
+            let is_unique_id = class.id.is_none_or(|id| ids.insert(id));
+            if !titles.insert(&class.title) || !is_unique_id {
+                return Err(Error::DuplicateClass(class.title.clone()));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for tag in &self.tags {
+            if !names.insert(&tag.name) {
+                return Err(Error::DuplicateTag(tag.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a tag is defined and its value agrees with the value
+    /// type.
+    pub fn validate_tag(&self, tag: &Tag) -> Result<()> {
+        let meta = self
+            .tag(&tag.name)
+            .ok_or_else(|| Error::UnknownTag(tag.name.clone()))?;
+        let invalid = |reason| Err(Error::invalid_tag_value(&tag.name, reason));
+
+        match (meta.value_type, &tag.value) {
+            (ValueType::None, None) => Ok(()),
+            (ValueType::None, Some(_)) => invalid("expect no value"),
+            (_, None) => invalid("missing value"),
+            (ValueType::AnyNumber, Some(TagValue::Number(_) | TagValue::Float(_))) => Ok(()),
+            (ValueType::AnyNumber, Some(_)) => invalid("expect a number"),
+            (ValueType::AnyString, Some(TagValue::Text(_) | TagValue::OneOf(_))) => Ok(()),
+            (ValueType::AnyString, Some(_)) => invalid("expect a string"),
+            (ValueType::OneOfString, Some(TagValue::Text(text) | TagValue::OneOf(text))) => {
+                match &meta.values {
+                    Some(values) if !values.contains(text) => {
+                        invalid("the value is not one of the allowed values")
+                    }
+                    _ => Ok(()),
+                }
+            }
+            (ValueType::OneOfString, Some(_)) => invalid("expect a string"),
+        }
+    }
+
+    /// Check that the class of an object is defined with the shape of
+    /// its geometry, and that its geometry tags are valid.
+    pub fn validate_object(&self, obj: &Object) -> Result<()> {
+        let class = match (&obj.class_title, obj.class_id) {
+            (Some(title), _) => self.class(title),
+            (None, Some(id)) => self.classes.iter().find(|class| class.id == Some(id)),
+            (None, None) => None,
+        };
+        let Some(class) = class else {
+            let name = obj.class_name().unwrap_or_default();
+            return Err(Error::UnknownClass(name));
+        };
+
+        let shape = obj.geometry.shape();
+        if class.shape != shape {
+            return Err(Error::ClassShapeMismatch {
+                title: class.title.clone(),
+                expected: class.shape,
+                found: shape,
+            });
+        }
+
+        for tag in obj.geometry.tags().unwrap_or_default() {
+            self.validate_tag(tag)?;
+        }
+        Ok(())
+    }
+
+    /// Check the tags and objects of an image annotation, and that
+    /// object ids are unique.
+    pub fn validate_image(&self, ann: &ImageAnnotation) -> Result<()> {
+        for tag in ann.tags.iter().flatten() {
+            self.validate_tag(tag)?;
+        }
+
+        let mut ids = HashSet::new();
+        for obj in &ann.objects {
+            if !ids.insert(obj.id) {
+                return Err(Error::DuplicateObjectId(obj.id));
+            }
+            self.validate_object(obj)?;
+        }
+        Ok(())
+    }
+}
+
+fn new_tag(name: &str, value: Option<TagValue>) -> Tag {
+    Tag {
+        name: name.to_string(),
+        value,
+        id: None,
+        key: None,
+        tag_id: None,
+        labeler_login: None,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DatasetKind, MemoryStorage};
+
+    fn builder() -> ProjectBuilder {
+        ProjectBuilder::new()
+            .class("car", Shape::Rectangle)
+            .unwrap()
+    }
+
+    fn image(builder: &mut ProjectBuilder, name: &str) -> ImageAnnotation {
+        builder
+            .annotate(name, 10, 10)
+            .object("car", Geometry::rectangle(1.0, 1.0, 4.0, 4.0))
+            .unwrap()
+            .build()
+    }
+
+    #[test]
+    fn class_meta_rejects_duplicate_titles_and_ids() {
+        let with_id = |title: &str, id| ClassMeta {
+            id: Some(id),
+            ..ClassMeta::new(title.to_string(), Shape::Polygon)
+        };
+
+        let builder = builder().class_meta(with_id("dog", 5)).unwrap();
+        assert!(matches!(
+            builder.clone().class("car", Shape::Polygon),
+            Err(Error::DuplicateClass(title)) if title == "car"
+        ));
+        assert!(matches!(
+            builder.clone().class_meta(with_id("cat", 5)),
+            Err(Error::DuplicateClass(title)) if title == "cat"
+        ));
+        assert!(matches!(
+            builder.clone().class_meta(with_id("cat", 1)),
+            Err(Error::DuplicateClass(title)) if title == "cat"
+        ));
+
+        let builder = builder.class("cat", Shape::Bitmap).unwrap();
+        let ids: Vec<Option<usize>> = builder
+            .meta()
+            .classes
+            .iter()
+            .map(|class| class.id)
+            .collect();
+        assert_eq!(ids, [Some(1), Some(5), Some(6)]);
+    }
+
+    #[test]
+    fn add_image_rejects_object_ids_used_by_other_images() {
+        let mut builder = builder();
+        let first = image(&mut builder, "a.png");
+        let second = ImageAnnotation {
+            name: "b.png".to_string(),
+            ..first.clone()
+        };
+
+        builder.add_image("ds", first.clone(), vec![1]).unwrap();
+        assert!(matches!(
+            builder.add_image("other", second, vec![1]),
+            Err(Error::DuplicateObjectId(_))
+        ));
+
+        // Replacing an image releases its object ids.
+        builder.add_image("ds", first, vec![2]).unwrap();
+    }
+
+    #[test]
+    fn write_with_stores_image_content() {
+        let mut builder = builder();
+        let ann = image(&mut builder, "a.png");
+        builder.add_image("ds", ann, b"image".to_vec()).unwrap();
+
+        let storage = Arc::new(MemoryStorage::new());
+        let project = builder.write_with(storage.clone(), Path::new("")).unwrap();
+        assert_eq!(storage.get("ds/img/a.png"), Some(b"image".to_vec()));
+
+        let DatasetKind::Image(dataset) = &project.datasets["ds"].kind else {
+            panic!("expect an image dataset");
+        };
+        assert_eq!(
+            dataset
+                .get_image("a.png")
+                .unwrap()
+                .ann()
+                .unwrap()
+                .objects
+                .len(),
+            1
+        );
+    }
+}
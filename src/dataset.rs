@@ -1,27 +1,97 @@
+mod classical;
 mod image;
 mod point_cloud;
 mod point_cloud_episode;
 mod video;
 
+pub use classical::{ClassicalDataset, ClassicalMedia, MediaKind};
 pub use image::ImageDataset;
 pub use point_cloud::PointCloudDataset;
 pub use point_cloud_episode::PointCloudEpisodeDataset;
 pub use video::VideoDataset;
 
-use crate::Result;
-use std::path::Path;
+use crate::{Error, FsStorage, MediaAnnotation, Result, Storage};
+use std::{path::Path, sync::Arc};
 use tracing::warn;
 
 /// Represent a dataset contained in a Supervisely project.
 #[derive(Debug, Clone)]
 pub struct Dataset {
-    /// The name of the dataset.
+    /// The name of the dataset. Nested datasets opened with a project
+    /// are named by their path from the top-level dataset, such as
+    /// `parent/child`.
     pub name: String,
 
+    /// The name of the parent dataset if the dataset is nested. The
+    /// parent may merely group datasets without media of its own, in
+    /// which case it is not a dataset of the project.
+    pub parent: Option<String>,
+
     /// The dataset instance classified by its type.
     pub kind: DatasetKind,
 }
 
+/// An annotation yielded by annotation iterators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationItem<'a> {
+    /// The name of the dataset.
+    pub dataset: &'a str,
+    /// The media name, or the dataset name for a point cloud episode.
+    pub media_name: &'a str,
+    pub annotation: MediaAnnotation,
+}
+
+/// The directory layout of a dataset, detected from its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DatasetLayout {
+    Image,
+    Video,
+    PointCloud,
+    PointCloudEpisode,
+}
+
+impl DatasetLayout {
+    /// The layouts in the order of detection.
+    const DETECTION_ORDER: [Self; 4] = [
+        Self::PointCloudEpisode,
+        Self::Image,
+        Self::Video,
+        Self::PointCloud,
+    ];
+
+    /// Get the file or folder that marks the layout.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            DatasetLayout::Image => "img",
+            DatasetLayout::Video => "video",
+            DatasetLayout::PointCloud => "pointcloud",
+            DatasetLayout::PointCloudEpisode => "frame_pointcloud_map.json",
+        }
+    }
+
+    /// Detect the layout of a dataset directory in a storage.
+    pub fn detect(storage: &dyn Storage, dir: &Path) -> Result<Self> {
+        Self::DETECTION_ORDER
+            .into_iter()
+            .find(|layout| storage.exists(&dir.join(layout.marker())))
+            .ok_or_else(|| Error::UnknownDatasetLayout(dir.to_path_buf()))
+    }
+
+    /// The async counterpart of [`Self::detect`] on the file system.
+    #[cfg(feature = "async")]
+    pub async fn detect_async(dir: &Path) -> Result<Self> {
+        for layout in Self::DETECTION_ORDER {
+            if tokio::fs::try_exists(dir.join(layout.marker()))
+                .await
+                .unwrap_or(false)
+            {
+                return Ok(layout);
+            }
+        }
+        Err(Error::UnknownDatasetLayout(dir.to_path_buf()))
+    }
+}
+
 /// The Supervisely dataset classified by its type.
 #[derive(Debug, Clone)]
 pub enum DatasetKind {
@@ -55,9 +125,76 @@ impl From<ImageDataset> for DatasetKind {
     }
 }
 
+impl DatasetKind {
+    /// Get the directory of the dataset.
+    pub fn dataset_dir(&self) -> &Path {
+        match self {
+            DatasetKind::Image(dataset) => &dataset.dataset_dir,
+            DatasetKind::Video(dataset) => &dataset.dataset_dir,
+            DatasetKind::PointCloud(dataset) => &dataset.dataset_dir,
+            DatasetKind::PointCloudEpisode(dataset) => &dataset.dataset_dir,
+        }
+    }
+
+    /// Get the layout of the dataset.
+    pub fn layout(&self) -> DatasetLayout {
+        match self {
+            DatasetKind::Image(_) => DatasetLayout::Image,
+            DatasetKind::Video(_) => DatasetLayout::Video,
+            DatasetKind::PointCloud(_) => DatasetLayout::PointCloud,
+            DatasetKind::PointCloudEpisode(_) => DatasetLayout::PointCloudEpisode,
+        }
+    }
+
+    /// Get the storage of the dataset.
+    pub fn storage(&self) -> &Arc<dyn Storage> {
+        match self {
+            DatasetKind::Image(dataset) => &dataset.storage,
+            DatasetKind::Video(dataset) => &dataset.storage,
+            DatasetKind::PointCloud(dataset) => &dataset.storage,
+            DatasetKind::PointCloudEpisode(dataset) => &dataset.storage,
+        }
+    }
+}
+
 impl Dataset {
     /// Open a Supervisely dataset in a directory.
     pub fn open<P>(dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with(Arc::new(FsStorage), dir.as_ref())
+    }
+
+    /// Open a Supervisely dataset in a directory of a storage.
+    pub fn open_with(storage: Arc<dyn Storage>, dir: &Path) -> Result<Self> {
+        let name = get_dir_name(dir)
+            .unwrap_or_else(|| {
+                warn!(
+                    "unable to determine the directory name of dataset {}",
+                    dir.display()
+                );
+                ""
+            })
+            .to_string();
+        let kind = match DatasetLayout::detect(&*storage, dir)? {
+            DatasetLayout::PointCloudEpisode => {
+                PointCloudEpisodeDataset::open_with(storage, dir)?.into()
+            }
+            DatasetLayout::Image => ImageDataset::open_with(storage, dir)?.into(),
+            DatasetLayout::Video => VideoDataset::open_with(storage, dir)?.into(),
+            DatasetLayout::PointCloud => PointCloudDataset::open_with(storage, dir)?.into(),
+        };
+        Ok(Self {
+            name,
+            parent: None,
+            kind,
+        })
+    }
+
+    /// The async counterpart of [`Self::open`].
+    #[cfg(feature = "async")]
+    pub async fn open_async<P>(dir: P) -> Result<Self>
     where
         P: AsRef<Path>,
     {
@@ -71,18 +208,55 @@ impl Dataset {
                 ""
             })
             .to_string();
-        let kind = if dir.join("frame_pointcloud_map.json").exists() {
-            PointCloudEpisodeDataset::open(dir)?.into()
-        } else if dir.join("img").exists() {
-            ImageDataset::open(dir)?.into()
-        } else if dir.join("video").exists() {
-            VideoDataset::open(dir)?.into()
-        } else if dir.join("pointcloud").exists() {
-            PointCloudDataset::open(dir)?.into()
-        } else {
-            todo!();
+        let kind = match DatasetLayout::detect_async(dir).await? {
+            DatasetLayout::PointCloudEpisode => {
+                PointCloudEpisodeDataset::open_async(dir).await?.into()
+            }
+            DatasetLayout::Image => ImageDataset::open_async(dir).await?.into(),
+            DatasetLayout::Video => VideoDataset::open_async(dir).await?.into(),
+            DatasetLayout::PointCloud => PointCloudDataset::open_async(dir).await?.into(),
         };
-        Ok(Self { name, kind })
+        Ok(Self {
+            name,
+            parent: None,
+            kind,
+        })
+    }
+}
+
+impl Dataset {
+    /// Iterate over the annotations of all media in the dataset.
+    ///
+    /// Annotation files are parsed one at a time as the iterator
+    /// advances. A point cloud episode yields a single item, cloned from
+    /// the annotation loaded when the dataset was opened.
+    pub fn iter_annotations(&self) -> Box<dyn Iterator<Item = Result<AnnotationItem<'_>>> + '_> {
+        let dataset_name = self.name.as_str();
+        let item = move |media_name, annotation| AnnotationItem {
+            dataset: dataset_name,
+            media_name,
+            annotation,
+        };
+
+        match &self.kind {
+            DatasetKind::Image(dataset) => Box::new(dataset.image_names.iter().map(move |name| {
+                let ann = dataset.get_image(name).unwrap().ann()?;
+                Ok(item(name, ann.into()))
+            })),
+            DatasetKind::Video(dataset) => Box::new(dataset.video_names.iter().map(move |name| {
+                let ann = dataset.get_video(name).unwrap().ann()?;
+                Ok(item(name, ann.into()))
+            })),
+            DatasetKind::PointCloud(dataset) => {
+                Box::new(dataset.point_cloud_names.iter().map(move |name| {
+                    let ann = dataset.get_point_cloud(name).unwrap().ann()?;
+                    Ok(item(name, ann.into()))
+                }))
+            }
+            DatasetKind::PointCloudEpisode(dataset) => Box::new(std::iter::once_with(move || {
+                Ok(item(dataset_name, dataset.annotation.clone().into()))
+            })),
+        }
     }
 }
 
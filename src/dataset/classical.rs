@@ -1,127 +1,291 @@
 use super::DatasetKind;
 use crate::{
-    utils::load_json, Error, ImageAnnotation, MediaAnnotation, MediaKind, PointCloudAnnotation,
-    Result, VideoAnnotation,
+    utils::{read_json, read_json_if_exists, write_json},
+    Error, FileMetadata, ImageAnnotation, ImageDataset, ItemInfo, ItemMeta, MediaAnnotation,
+    PointCloudAnnotation, PointCloudDataset, Result, Storage, VideoAnnotation, VideoDataset,
+    ITEM_META_DIR,
 };
 use indexmap::IndexSet;
-use itertools::Itertools;
 use std::{
-    fs,
+    fmt::Debug,
+    io::Read,
     path::{Path, PathBuf},
+    sync::Arc,
 };
-use strum::IntoEnumIterator;
 
-/// The classical Supervisely dataset.
-#[derive(Debug, Clone)]
-pub struct ClassicalDataset {
-    pub dataset_dir: PathBuf,
-    pub media_kind: MediaKind,
-    pub media_names: IndexSet<String>,
+/// The kind of media in a classical dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    Image,
+    Video,
+    PointCloud,
 }
 
-/// The reference to a media data.
-#[derive(Debug, Clone)]
-pub struct ClassicalMedia<'a> {
-    media_name: &'a str,
-    dataset: &'a ClassicalDataset,
+impl MediaKind {
+    /// Get the name of the media folder.
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            MediaKind::Image => "img",
+            MediaKind::Video => "video",
+            MediaKind::PointCloud => "pointcloud",
+        }
+    }
+
+    /// Get the name of the item info folder.
+    pub fn info_dir_name(&self) -> &'static str {
+        match self {
+            MediaKind::Image => "img_info",
+            MediaKind::Video => "video_info",
+            MediaKind::PointCloud => "pointcloud_info",
+        }
+    }
 }
 
-impl From<ClassicalDataset> for DatasetKind {
-    fn from(v: ClassicalDataset) -> Self {
-        Self::Classical(v)
+/// The classical Supervisely dataset, which keeps media files in a
+/// media folder and an annotation file per media in `ann`.
+///
+/// Image, video and point cloud datasets share the layout, so that
+/// tooling can be written once over this trait.
+pub trait ClassicalDataset: Debug + Send + Sync {
+    fn media_kind(&self) -> MediaKind;
+
+    fn dataset_dir(&self) -> &Path;
+
+    fn storage(&self) -> &Arc<dyn Storage>;
+
+    /// Get the media names in sorted order.
+    fn media_names(&self) -> &IndexSet<String>;
+
+    /// Query the media data by its name.
+    fn get_media(&self, media_name: &str) -> Option<ClassicalMedia<'_>> {
+        let media_name = self.media_names().get(media_name)?;
+        Some(ClassicalMedia::new(self, media_name))
+    }
+
+    /// Iterate over the media in the order of names.
+    fn iter_media(&self) -> Box<dyn Iterator<Item = ClassicalMedia<'_>> + '_> {
+        Box::new(
+            self.media_names()
+                .iter()
+                .map(|media_name| ClassicalMedia::new(self, media_name)),
+        )
     }
 }
 
-impl ClassicalDataset {
-    /// Open a classical Supervisely dataset in a directory.
-    pub fn open<P>(dir: P) -> Result<Self>
+/// The reference to a media data in a classical dataset.
+#[derive(Debug, Clone)]
+pub struct ClassicalMedia<'a> {
+    media_name: &'a str,
+    media_kind: MediaKind,
+    dataset_dir: &'a Path,
+    storage: &'a Arc<dyn Storage>,
+}
+
+impl<'a> ClassicalMedia<'a> {
+    pub(super) fn new<D>(dataset: &'a D, media_name: &'a str) -> Self
     where
-        P: AsRef<Path>,
+        D: ClassicalDataset + ?Sized,
     {
-        let dir = dir.as_ref();
-
-        let media_kind = {
-            let kinds: Vec<MediaKind> = MediaKind::iter()
-                .filter(|kind| dir.join(kind.dir_name()).exists())
-                .collect();
-
-            match kinds.as_slice() {
-                [] => return Err(crate::Error::expect_single_media_folder(dir)),
-                &[kind] => kind,
-                [..] => {
-                    return Err(crate::Error::expect_single_media_folder(dir));
-                }
-            }
-        };
+        Self {
+            media_name,
+            media_kind: dataset.media_kind(),
+            dataset_dir: dataset.dataset_dir(),
+            storage: dataset.storage(),
+        }
+    }
 
-        let media_dir = dir.join(media_kind.dir_name());
+    pub fn media_name(&self) -> &'a str {
+        self.media_name
+    }
 
-        let entries =
-            fs::read_dir(&media_dir).map_err(|error| Error::read_dir_error(&media_dir, error))?;
+    pub fn media_kind(&self) -> MediaKind {
+        self.media_kind
+    }
 
-        let mut media_names: IndexSet<_> = entries
-            .map(|entry| -> Result<_> {
-                let entry = entry.map_err(|error| Error::read_dir_error(&media_dir, error))?;
+    /// Get the path of the media file within the dataset storage.
+    pub fn media_path(&self) -> PathBuf {
+        self.dataset_dir
+            .join(self.media_kind.dir_name())
+            .join(self.media_name)
+    }
 
-                let file_name = entry.file_name();
-                let file_name = file_name
-                    .to_str()
-                    .ok_or_else(|| Error::expect_utf8_file_name(entry.path()))?
-                    .to_string();
-                Ok(file_name)
-            })
-            .try_collect()?;
-        media_names.sort_unstable();
+    /// Get the path of the annotation file within the dataset storage.
+    pub fn ann_path(&self) -> PathBuf {
+        self.dataset_dir
+            .join("ann")
+            .join(format!("{}.json", self.media_name))
+    }
 
-        Ok(Self {
-            media_names,
-            dataset_dir: dir.to_owned(),
-            media_kind,
-        })
+    /// Get the path of the item info file within the dataset storage.
+    pub fn item_info_path(&self) -> PathBuf {
+        self.dataset_dir
+            .join(self.media_kind.info_dir_name())
+            .join(format!("{}.json", self.media_name))
     }
 
-    /// Query the media data by its name.
-    pub fn get_media(&self, media_name: &str) -> Option<ClassicalMedia<'_>> {
-        let media_name = self.media_names.get(media_name)?;
-        Some(ClassicalMedia {
-            media_name,
-            dataset: self,
-        })
+    /// Get the path of the user metadata file within the dataset storage.
+    pub fn item_meta_path(&self) -> PathBuf {
+        self.dataset_dir
+            .join(ITEM_META_DIR)
+            .join(format!("{}.json", self.media_name))
     }
-}
 
-impl<'a> ClassicalMedia<'a> {
-    /// Get the media folder path.
-    pub fn media_path(&self) -> PathBuf {
-        let Self {
-            media_name,
-            dataset,
-        } = *self;
+    /// Open the media file for reading.
+    pub fn open_media(&self) -> Result<Box<dyn Read + Send + 'a>> {
+        self.storage.open_read(&self.media_path())
+    }
 
-        dataset
-            .dataset_dir
-            .join(dataset.media_kind.dir_name())
-            .join(media_name)
+    /// Read the size and checksum of the media file.
+    pub fn metadata(&self) -> Result<FileMetadata> {
+        let path = self.media_path();
+        FileMetadata::from_reader(self.open_media()?)
+            .map_err(|error| Error::open_file_error(&path, error))
     }
 
     /// Get the annotation data.
     pub fn ann(&self) -> Result<MediaAnnotation> {
-        let Self {
-            media_name,
-            dataset,
-        } = *self;
+        let storage = &**self.storage;
+        let path = self.ann_path();
 
-        let path = dataset
-            .dataset_dir
-            .join("ann")
-            .join(format!("{media_name}.json"));
-
-        let ann = match dataset.media_kind {
-            MediaKind::Image => load_json::<ImageAnnotation, _>(path)?.into(),
-            MediaKind::Video => load_json::<VideoAnnotation, _>(path)?.into(),
-            MediaKind::PointCloud => load_json::<PointCloudAnnotation, _>(path)?.into(),
+        let ann = match self.media_kind {
+            MediaKind::Image => read_json::<ImageAnnotation>(storage, &path)?.into(),
+            MediaKind::Video => read_json::<VideoAnnotation>(storage, &path)?.into(),
+            MediaKind::PointCloud => read_json::<PointCloudAnnotation>(storage, &path)?.into(),
         };
 
         Ok(ann)
     }
+
+    /// Replace the annotation data, which must be of the same kind as
+    /// the media.
+    pub fn write_ann(&self, ann: &MediaAnnotation) -> Result<()> {
+        let storage = &**self.storage;
+        let path = self.ann_path();
+
+        match (self.media_kind, ann) {
+            (MediaKind::Image, MediaAnnotation::Image(ann)) => write_json(storage, &path, ann),
+            (MediaKind::Video, MediaAnnotation::Video(ann)) => write_json(storage, &path, ann),
+            (MediaKind::PointCloud, MediaAnnotation::PointCloud(ann)) => {
+                write_json(storage, &path, ann)
+            }
+            (expected, _) => Err(Error::AnnotationKindMismatch { path, expected }),
+        }
+    }
+
+    /// Get the item info of the media, if exported.
+    pub fn item_info(&self) -> Result<Option<ItemInfo>> {
+        read_json_if_exists(&**self.storage, &self.item_info_path())
+    }
+
+    /// Get the user metadata of the media, if exported.
+    pub fn item_meta(&self) -> Result<Option<ItemMeta>> {
+        read_json_if_exists(&**self.storage, &self.item_meta_path())
+    }
+}
+
+impl ClassicalDataset for ImageDataset {
+    fn media_kind(&self) -> MediaKind {
+        MediaKind::Image
+    }
+
+    fn dataset_dir(&self) -> &Path {
+        &self.dataset_dir
+    }
+
+    fn storage(&self) -> &Arc<dyn Storage> {
+        &self.storage
+    }
+
+    fn media_names(&self) -> &IndexSet<String> {
+        &self.image_names
+    }
+}
+
+impl ClassicalDataset for VideoDataset {
+    fn media_kind(&self) -> MediaKind {
+        MediaKind::Video
+    }
+
+    fn dataset_dir(&self) -> &Path {
+        &self.dataset_dir
+    }
+
+    fn storage(&self) -> &Arc<dyn Storage> {
+        &self.storage
+    }
+
+    fn media_names(&self) -> &IndexSet<String> {
+        &self.video_names
+    }
+}
+
+impl ClassicalDataset for PointCloudDataset {
+    fn media_kind(&self) -> MediaKind {
+        MediaKind::PointCloud
+    }
+
+    fn dataset_dir(&self) -> &Path {
+        &self.dataset_dir
+    }
+
+    fn storage(&self) -> &Arc<dyn Storage> {
+        &self.storage
+    }
+
+    fn media_names(&self) -> &IndexSet<String> {
+        &self.point_cloud_names
+    }
+}
+
+impl DatasetKind {
+    /// View the dataset as a classical dataset. Point cloud episodes
+    /// are not classical.
+    pub fn as_classical(&self) -> Option<&dyn ClassicalDataset> {
+        match self {
+            DatasetKind::Image(dataset) => Some(dataset),
+            DatasetKind::Video(dataset) => Some(dataset),
+            DatasetKind::PointCloud(dataset) => Some(dataset),
+            DatasetKind::PointCloudEpisode(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryStorage, Project};
+
+    #[test]
+    fn write_ann_checks_the_media_kind() {
+        let storage = Arc::new(MemoryStorage::new());
+        storage
+            .insert("meta.json", r#"{"classes": [], "tags": []}"#)
+            .unwrap();
+        storage.insert("ds/img/a.png", []).unwrap();
+        storage
+            .insert(
+                "ds/ann/a.png.json",
+                r#"{"name": "a.png", "size": {"width": 10, "height": 10}, "tags": [], "objects": []}"#,
+            )
+            .unwrap();
+        let project = Project::open_with(storage.clone(), Path::new("")).unwrap();
+        let dataset = project.datasets["ds"].kind.as_classical().unwrap();
+        let media = dataset.get_media("a.png").unwrap();
+
+        let result = media.write_ann(&VideoAnnotation::default().into());
+        assert!(matches!(
+            result,
+            Err(Error::AnnotationKindMismatch {
+                expected: MediaKind::Image,
+                ..
+            })
+        ));
+
+        let MediaAnnotation::Image(mut ann) = media.ann().unwrap() else {
+            unreachable!();
+        };
+        ann.size.width = 20;
+        media.write_ann(&ann.clone().into()).unwrap();
+        assert_eq!(media.ann().unwrap(), ann.into());
+    }
 }
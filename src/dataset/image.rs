@@ -1,9 +1,16 @@
-use crate::{utils::load_json, Error, ImageAnnotation, Result};
+#[cfg(feature = "async")]
+use crate::utils::{list_file_names_async, read_json_async};
+use crate::{
+    image_dimensions,
+    utils::{list_file_names, read_json, write_json},
+    ClassicalMedia, Error, FsStorage, ImageAnnotation, ImageMetadata, ItemInfo, ItemMeta, Result,
+    Storage,
+};
 use indexmap::IndexSet;
-use itertools::Itertools;
 use std::{
-    fs,
+    io::Read,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 /// The classical Supervisely dataset.
@@ -11,6 +18,7 @@ use std::{
 pub struct ImageDataset {
     pub dataset_dir: PathBuf,
     pub image_names: IndexSet<String>,
+    pub storage: Arc<dyn Storage>,
 }
 
 /// The reference to a media data.
@@ -23,31 +31,37 @@ pub struct ImageData<'a> {
 impl ImageDataset {
     /// Open a classical Supervisely dataset in a directory.
     pub fn open<P>(dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with(Arc::new(FsStorage), dir.as_ref())
+    }
+
+    /// Open the dataset in a directory of a storage.
+    pub fn open_with(storage: Arc<dyn Storage>, dir: &Path) -> Result<Self> {
+        let image_names = list_file_names(&*storage, &dir.join("img"))?;
+
+        Ok(Self {
+            image_names,
+            dataset_dir: dir.to_owned(),
+            storage,
+        })
+    }
+
+    /// The async counterpart of [`Self::open`]. The dataset is read
+    /// from the file system.
+    #[cfg(feature = "async")]
+    pub async fn open_async<P>(dir: P) -> Result<Self>
     where
         P: AsRef<Path>,
     {
         let dir = dir.as_ref();
-        let data_dir = dir.join("img");
-
-        let entries =
-            fs::read_dir(&data_dir).map_err(|error| Error::read_dir_error(&data_dir, error))?;
-
-        let mut image_names: IndexSet<_> = entries
-            .map(|entry| -> Result<_> {
-                let entry = entry.map_err(|error| Error::read_dir_error(&data_dir, error))?;
-                let file_name = entry.file_name();
-                let file_name = file_name
-                    .to_str()
-                    .ok_or_else(|| Error::expect_utf8_file_name(entry.path()))?
-                    .to_string();
-                Ok(file_name)
-            })
-            .try_collect()?;
-        image_names.sort_unstable();
+        let image_names = list_file_names_async(dir.join("img")).await?;
 
         Ok(Self {
             image_names,
             dataset_dir: dir.to_owned(),
+            storage: Arc::new(FsStorage),
         })
     }
 
@@ -59,21 +73,103 @@ impl ImageDataset {
             dataset: self,
         })
     }
+
+    /// Load the annotations of all images in parallel, yielding each
+    /// image name with its annotation or error.
+    #[cfg(feature = "parallel")]
+    pub fn par_iter(
+        &self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = (&str, Result<ImageAnnotation>)> + '_
+    {
+        use rayon::prelude::*;
+
+        self.image_names.par_iter().map(|name| {
+            let ann = self.get_image(name).unwrap().ann();
+            (name.as_str(), ann)
+        })
+    }
 }
 
 impl<'a> ImageData<'a> {
     /// Get the annotation data.
     pub fn ann(&self) -> Result<ImageAnnotation> {
-        let Self {
-            image_name,
-            dataset,
-        } = *self;
-
-        let path = dataset
-            .dataset_dir
-            .join("ann")
-            .join(format!("{image_name}.json"));
-        let ann: ImageAnnotation = load_json(path)?;
-        Ok(ann)
+        read_json(&*self.dataset.storage, &self.ann_path())
+    }
+
+    /// Overwrite the annotation data.
+    pub fn write_ann(&self, ann: &ImageAnnotation) -> Result<()> {
+        write_json(&*self.dataset.storage, &self.ann_path(), ann)
+    }
+
+    /// The async counterpart of [`Self::ann`]. The annotation is read
+    /// through the dataset storage on a blocking thread.
+    #[cfg(feature = "async")]
+    pub async fn ann_async(&self) -> Result<ImageAnnotation> {
+        read_json_async(self.dataset.storage.clone(), self.ann_path()).await
+    }
+
+    /// Open the image file for reading.
+    pub fn open_media(&self) -> Result<Box<dyn Read + Send + 'a>> {
+        self.media().open_media()
+    }
+
+    /// Read the size, checksum and dimensions of the image file.
+    pub fn metadata(&self) -> Result<ImageMetadata> {
+        let path = self.media_path();
+        ImageMetadata::from_reader(self.open_media()?)
+            .map_err(|error| Error::open_file_error(&path, error))
+    }
+
+    /// Check that the size in the annotation agrees with the image
+    /// dimensions. Only the image header is read.
+    pub fn verify_size(&self) -> Result<()> {
+        let path = self.media_path();
+        let found = image_dimensions(&mut self.open_media()?)
+            .map_err(|error| Error::open_file_error(&path, error))?
+            .ok_or_else(|| Error::UnknownImageFormat(path.clone()))?;
+
+        let expected = self.ann()?.size;
+        if expected != found {
+            return Err(Error::ImageSizeMismatch {
+                path,
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the path of the image file within the dataset storage.
+    pub fn media_path(&self) -> PathBuf {
+        self.media().media_path()
+    }
+
+    /// Get the path of the annotation file within the dataset storage.
+    pub fn ann_path(&self) -> PathBuf {
+        self.media().ann_path()
+    }
+
+    /// Get the path of the item info file within the dataset storage.
+    pub fn item_info_path(&self) -> PathBuf {
+        self.media().item_info_path()
+    }
+
+    /// Get the path of the user metadata file within the dataset storage.
+    pub fn item_meta_path(&self) -> PathBuf {
+        self.media().item_meta_path()
+    }
+
+    /// Get the item info of the image, if exported.
+    pub fn item_info(&self) -> Result<Option<ItemInfo>> {
+        self.media().item_info()
+    }
+
+    /// Get the user metadata of the image, if exported.
+    pub fn item_meta(&self) -> Result<Option<ItemMeta>> {
+        self.media().item_meta()
+    }
+
+    fn media(&self) -> ClassicalMedia<'a> {
+        ClassicalMedia::new(self.dataset, self.image_name)
     }
 }
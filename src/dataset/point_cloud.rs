@@ -1,9 +1,15 @@
-use crate::{utils::load_json, Error, PointCloudAnnotation, Result};
+#[cfg(feature = "async")]
+use crate::utils::{list_file_names_async, read_json_async};
+use crate::{
+    utils::{list_file_names, read_json, write_json},
+    ClassicalMedia, FileMetadata, FsStorage, ItemInfo, ItemMeta, PointCloudAnnotation, Result,
+    Storage,
+};
 use indexmap::IndexSet;
-use itertools::Itertools;
 use std::{
-    fs,
+    io::Read,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 /// The classical Supervisely dataset.
@@ -11,6 +17,7 @@ use std::{
 pub struct PointCloudDataset {
     pub dataset_dir: PathBuf,
     pub point_cloud_names: IndexSet<String>,
+    pub storage: Arc<dyn Storage>,
 }
 
 /// The reference to point cloud data.
@@ -23,33 +30,37 @@ pub struct PointCloudData<'a> {
 impl PointCloudDataset {
     /// Open a classical Supervisely dataset in a directory.
     pub fn open<P>(dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with(Arc::new(FsStorage), dir.as_ref())
+    }
+
+    /// Open the dataset in a directory of a storage.
+    pub fn open_with(storage: Arc<dyn Storage>, dir: &Path) -> Result<Self> {
+        let point_cloud_names = list_file_names(&*storage, &dir.join("pointcloud"))?;
+
+        Ok(Self {
+            point_cloud_names,
+            dataset_dir: dir.to_owned(),
+            storage,
+        })
+    }
+
+    /// The async counterpart of [`Self::open`]. The dataset is read
+    /// from the file system.
+    #[cfg(feature = "async")]
+    pub async fn open_async<P>(dir: P) -> Result<Self>
     where
         P: AsRef<Path>,
     {
         let dir = dir.as_ref();
-        let point_cloud_dir = dir.join("pointcloud");
-
-        let entries = fs::read_dir(&point_cloud_dir)
-            .map_err(|error| Error::read_dir_error(&point_cloud_dir, error))?;
-
-        let mut point_cloud_names: IndexSet<_> = entries
-            .map(|entry| -> Result<_> {
-                let entry =
-                    entry.map_err(|error| Error::read_dir_error(&point_cloud_dir, error))?;
-
-                let file_name = entry.file_name();
-                let file_name = file_name
-                    .to_str()
-                    .ok_or_else(|| Error::expect_utf8_file_name(entry.path()))?
-                    .to_string();
-                Ok(file_name)
-            })
-            .try_collect()?;
-        point_cloud_names.sort_unstable();
+        let point_cloud_names = list_file_names_async(dir.join("pointcloud")).await?;
 
         Ok(Self {
             point_cloud_names,
             dataset_dir: dir.to_owned(),
+            storage: Arc::new(FsStorage),
         })
     }
 
@@ -61,21 +72,82 @@ impl PointCloudDataset {
             dataset: self,
         })
     }
+
+    /// Load the annotations of all point clouds in parallel, yielding each
+    /// point cloud name with its annotation or error.
+    #[cfg(feature = "parallel")]
+    pub fn par_iter(
+        &self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = (&str, Result<PointCloudAnnotation>)> + '_
+    {
+        use rayon::prelude::*;
+
+        self.point_cloud_names.par_iter().map(|name| {
+            let ann = self.get_point_cloud(name).unwrap().ann();
+            (name.as_str(), ann)
+        })
+    }
 }
 
 impl<'a> PointCloudData<'a> {
     /// Get the annotation data.
     pub fn ann(&self) -> Result<PointCloudAnnotation> {
-        let Self {
-            point_cloud_name: media_name,
-            dataset,
-        } = *self;
-
-        let path = dataset
-            .dataset_dir
-            .join("ann")
-            .join(format!("{media_name}.json"));
-        let ann: PointCloudAnnotation = load_json(path)?;
-        Ok(ann)
+        read_json(&*self.dataset.storage, &self.ann_path())
+    }
+
+    /// Overwrite the annotation data.
+    pub fn write_ann(&self, ann: &PointCloudAnnotation) -> Result<()> {
+        write_json(&*self.dataset.storage, &self.ann_path(), ann)
+    }
+
+    /// The async counterpart of [`Self::ann`]. The annotation is read
+    /// through the dataset storage on a blocking thread.
+    #[cfg(feature = "async")]
+    pub async fn ann_async(&self) -> Result<PointCloudAnnotation> {
+        read_json_async(self.dataset.storage.clone(), self.ann_path()).await
+    }
+
+    /// Open the point cloud file for reading.
+    pub fn open_media(&self) -> Result<Box<dyn Read + Send + 'a>> {
+        self.media().open_media()
+    }
+
+    /// Read the size and checksum of the point cloud file.
+    pub fn metadata(&self) -> Result<FileMetadata> {
+        self.media().metadata()
+    }
+
+    /// Get the path of the point cloud file within the dataset storage.
+    pub fn media_path(&self) -> PathBuf {
+        self.media().media_path()
+    }
+
+    /// Get the path of the annotation file within the dataset storage.
+    pub fn ann_path(&self) -> PathBuf {
+        self.media().ann_path()
+    }
+
+    /// Get the path of the item info file within the dataset storage.
+    pub fn item_info_path(&self) -> PathBuf {
+        self.media().item_info_path()
+    }
+
+    /// Get the path of the user metadata file within the dataset storage.
+    pub fn item_meta_path(&self) -> PathBuf {
+        self.media().item_meta_path()
+    }
+
+    /// Get the item info of the point cloud, if exported.
+    pub fn item_info(&self) -> Result<Option<ItemInfo>> {
+        self.media().item_info()
+    }
+
+    /// Get the user metadata of the point cloud, if exported.
+    pub fn item_meta(&self) -> Result<Option<ItemMeta>> {
+        self.media().item_meta()
+    }
+
+    fn media(&self) -> ClassicalMedia<'a> {
+        ClassicalMedia::new(self.dataset, self.point_cloud_name)
     }
 }
@@ -1,9 +1,15 @@
 use indexmap::IndexMap;
 
-use crate::{utils::load_json, Frame, PointCloudEpisodeAnnotation, Result};
+#[cfg(feature = "async")]
+use crate::utils::load_json_async;
+use crate::{
+    utils::{read_json, write_json},
+    Frame, FsStorage, PointCloudEpisodeAnnotation, Result, Storage,
+};
 use std::{
     fmt::Debug,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 /// The point cloud episode Supervisely dataset.
@@ -12,29 +18,63 @@ pub struct PointCloudEpisodeDataset {
     pub dataset_dir: PathBuf,
     pub frame_point_map: IndexMap<u64, String>,
     pub annotation: PointCloudEpisodeAnnotation,
+    pub storage: Arc<dyn Storage>,
 }
 
 impl PointCloudEpisodeDataset {
     /// Open a point cloud episode Supervisely dataset in a directory.
     pub fn open<P>(dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with(Arc::new(FsStorage), dir.as_ref())
+    }
+
+    /// Open the dataset in a directory of a storage.
+    pub fn open_with(storage: Arc<dyn Storage>, dir: &Path) -> Result<Self> {
+        let frame_point_map_file = dir.join("frame_pointcloud_map.json");
+        let frame_point_map: IndexMap<u64, String> = read_json(&*storage, &frame_point_map_file)?;
+
+        let annotation_file = dir.join("annotation.json");
+        let annotation: PointCloudEpisodeAnnotation = read_json(&*storage, &annotation_file)?;
+
+        Ok(Self {
+            dataset_dir: dir.to_path_buf(),
+            frame_point_map,
+            annotation,
+            storage,
+        })
+    }
+
+    /// The async counterpart of [`Self::open`]. The dataset is read
+    /// from the file system.
+    #[cfg(feature = "async")]
+    pub async fn open_async<P>(dir: P) -> Result<Self>
     where
         P: AsRef<Path>,
     {
         let dir = dir.as_ref();
 
         let frame_point_map_file = dir.join("frame_pointcloud_map.json");
-        let frame_point_map: IndexMap<u64, String> = load_json(&frame_point_map_file)?;
+        let frame_point_map: IndexMap<u64, String> = load_json_async(&frame_point_map_file).await?;
 
         let annotation_file = dir.join("annotation.json");
-        let annotation: PointCloudEpisodeAnnotation = load_json(annotation_file)?;
+        let annotation: PointCloudEpisodeAnnotation = load_json_async(annotation_file).await?;
 
         Ok(Self {
             dataset_dir: dir.to_path_buf(),
             frame_point_map,
             annotation,
+            storage: Arc::new(FsStorage),
         })
     }
 
+    /// Overwrite the annotation file with the annotation of the dataset.
+    pub fn save_annotation(&self) -> Result<()> {
+        let path = self.dataset_dir.join("annotation.json");
+        write_json(&*self.storage, &path, &self.annotation)
+    }
+
     pub fn frame_id_iter(
         &self,
     ) -> impl ExactSizeIterator<Item = u64> + Debug + Clone + Sync + Send + '_ {
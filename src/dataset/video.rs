@@ -1,9 +1,14 @@
-use crate::{utils::load_json, Error, Result, VideoAnnotation};
+#[cfg(feature = "async")]
+use crate::utils::{list_file_names_async, read_json_async};
+use crate::{
+    utils::{list_file_names, read_json, write_json},
+    ClassicalMedia, FileMetadata, FsStorage, ItemInfo, ItemMeta, Result, Storage, VideoAnnotation,
+};
 use indexmap::IndexSet;
-use itertools::Itertools;
 use std::{
-    fs,
+    io::Read,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 /// The classical Supervisely dataset.
@@ -11,6 +16,7 @@ use std::{
 pub struct VideoDataset {
     pub dataset_dir: PathBuf,
     pub video_names: IndexSet<String>,
+    pub storage: Arc<dyn Storage>,
 }
 
 /// The reference to a media data.
@@ -26,29 +32,34 @@ impl VideoDataset {
     where
         P: AsRef<Path>,
     {
-        let dir = dir.as_ref();
-        let video_dir = dir.join("video");
+        Self::open_with(Arc::new(FsStorage), dir.as_ref())
+    }
 
-        let entries =
-            fs::read_dir(&video_dir).map_err(|error| Error::read_dir_error(&video_dir, error))?;
+    /// Open the dataset in a directory of a storage.
+    pub fn open_with(storage: Arc<dyn Storage>, dir: &Path) -> Result<Self> {
+        let video_names = list_file_names(&*storage, &dir.join("video"))?;
 
-        let mut video_names: IndexSet<_> = entries
-            .map(|entry| -> Result<_> {
-                let entry = entry.map_err(|error| Error::read_dir_error(&video_dir, error))?;
+        Ok(Self {
+            video_names,
+            dataset_dir: dir.to_owned(),
+            storage,
+        })
+    }
 
-                let file_name = entry.file_name();
-                let file_name = file_name
-                    .to_str()
-                    .ok_or_else(|| Error::expect_utf8_file_name(entry.path()))?
-                    .to_string();
-                Ok(file_name)
-            })
-            .try_collect()?;
-        video_names.sort_unstable();
+    /// The async counterpart of [`Self::open`]. The dataset is read
+    /// from the file system.
+    #[cfg(feature = "async")]
+    pub async fn open_async<P>(dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        let video_names = list_file_names_async(dir.join("video")).await?;
 
         Ok(Self {
             video_names,
             dataset_dir: dir.to_owned(),
+            storage: Arc::new(FsStorage),
         })
     }
 
@@ -60,22 +71,82 @@ impl VideoDataset {
             dataset: self,
         })
     }
+
+    /// Load the annotations of all videos in parallel, yielding each
+    /// video name with its annotation or error.
+    #[cfg(feature = "parallel")]
+    pub fn par_iter(
+        &self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = (&str, Result<VideoAnnotation>)> + '_
+    {
+        use rayon::prelude::*;
+
+        self.video_names.par_iter().map(|name| {
+            let ann = self.get_video(name).unwrap().ann();
+            (name.as_str(), ann)
+        })
+    }
 }
 
 impl<'a> VideoData<'a> {
     /// Get the annotation data.
     pub fn ann(&self) -> Result<VideoAnnotation> {
-        let Self {
-            video_name: media_name,
-            dataset,
-        } = *self;
-
-        let path = dataset
-            .dataset_dir
-            .join("ann")
-            .join(format!("{media_name}.json"));
-        let ann: VideoAnnotation = load_json(path)?;
-
-        Ok(ann)
+        read_json(&*self.dataset.storage, &self.ann_path())
+    }
+
+    /// Overwrite the annotation data.
+    pub fn write_ann(&self, ann: &VideoAnnotation) -> Result<()> {
+        write_json(&*self.dataset.storage, &self.ann_path(), ann)
+    }
+
+    /// The async counterpart of [`Self::ann`]. The annotation is read
+    /// through the dataset storage on a blocking thread.
+    #[cfg(feature = "async")]
+    pub async fn ann_async(&self) -> Result<VideoAnnotation> {
+        read_json_async(self.dataset.storage.clone(), self.ann_path()).await
+    }
+
+    /// Open the video file for reading.
+    pub fn open_media(&self) -> Result<Box<dyn Read + Send + 'a>> {
+        self.media().open_media()
+    }
+
+    /// Read the size and checksum of the video file.
+    pub fn metadata(&self) -> Result<FileMetadata> {
+        self.media().metadata()
+    }
+
+    /// Get the path of the video file within the dataset storage.
+    pub fn media_path(&self) -> PathBuf {
+        self.media().media_path()
+    }
+
+    /// Get the path of the annotation file within the dataset storage.
+    pub fn ann_path(&self) -> PathBuf {
+        self.media().ann_path()
+    }
+
+    /// Get the path of the item info file within the dataset storage.
+    pub fn item_info_path(&self) -> PathBuf {
+        self.media().item_info_path()
+    }
+
+    /// Get the path of the user metadata file within the dataset storage.
+    pub fn item_meta_path(&self) -> PathBuf {
+        self.media().item_meta_path()
+    }
+
+    /// Get the item info of the video, if exported.
+    pub fn item_info(&self) -> Result<Option<ItemInfo>> {
+        self.media().item_info()
+    }
+
+    /// Get the user metadata of the video, if exported.
+    pub fn item_meta(&self) -> Result<Option<ItemMeta>> {
+        self.media().item_meta()
+    }
+
+    fn media(&self) -> ClassicalMedia<'a> {
+        ClassicalMedia::new(self.dataset, self.video_name)
     }
 }
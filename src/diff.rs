@@ -0,0 +1,971 @@
+use crate::{
+    iou, Cuboid3DGeometry, Dataset, Geometry, MediaAnnotation, PointCloudGeometry, Points, Project,
+    ProjectMeta, Result, Shape, Tag, TagValue, ValueType,
+};
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The options for comparing two project versions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffConfig {
+    /// The largest vertex or cuboid displacement regarded as unchanged.
+    pub geometry_tolerance: f64,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            geometry_tolerance: 0.5,
+        }
+    }
+}
+
+/// The changes between an old and a new version of a project.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectDiff {
+    pub meta: MetaDiff,
+    pub added_datasets: Vec<String>,
+    pub removed_datasets: Vec<String>,
+    /// The changed media items. Unchanged items are not listed.
+    pub media: Vec<MediaDiff>,
+}
+
+/// The changes of the classes and tags in the project meta.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetaDiff {
+    pub added_classes: Vec<String>,
+    pub removed_classes: Vec<String>,
+    pub modified_classes: Vec<ClassMetaChange>,
+    pub added_tags: Vec<String>,
+    pub removed_tags: Vec<String>,
+    pub modified_tags: Vec<TagMetaChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassMetaChange {
+    pub title: String,
+    pub shape: Option<ValueChange<Shape>>,
+    /// The colors in hex codes.
+    pub color: Option<ValueChange<Option<String>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagMetaChange {
+    pub name: String,
+    pub value_type: Option<ValueChange<ValueType>>,
+    pub added_values: Vec<String>,
+    pub removed_values: Vec<String>,
+}
+
+/// The old and new values of a property.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// The changes of a media item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MediaDiff {
+    pub dataset: String,
+    /// The media name, or the dataset name for an episode.
+    pub name: String,
+    pub status: ChangeStatus,
+    pub tags: Vec<TagChange>,
+    pub entries: Vec<EntryDiff>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeStatus {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Whether an entry is an object or a figure. Image objects are
+/// objects carrying their own geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Object,
+    Figure,
+}
+
+/// The change of an object or a figure, matched by its id or key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryDiff {
+    pub kind: EntryKind,
+    /// The object id for images, or the object or figure key otherwise.
+    pub key: String,
+    pub status: ChangeStatus,
+    pub class: Option<ValueChange<Option<String>>>,
+    pub geometry: Option<GeometryChange>,
+    pub tags: Vec<TagChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeometryChange {
+    /// The geometry type changed.
+    Shape(ValueChange<Shape>),
+    /// The vertices or the cuboid moved by the distance, measured by the
+    /// Hausdorff distance of vertices or the largest parameter change.
+    Moved { distance: f64 },
+    /// The bitmap changed, with the IoU of the old and new masks.
+    Bitmap { iou: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagChange {
+    Added {
+        name: String,
+        value: Option<TagValue>,
+    },
+    Removed {
+        name: String,
+        value: Option<TagValue>,
+    },
+    Changed {
+        name: String,
+        old: Option<TagValue>,
+        new: Option<TagValue>,
+    },
+}
+
+impl Project {
+    /// Compare the project against a newer version of it.
+    pub fn diff(&self, newer: &Project, config: &DiffConfig) -> Result<ProjectDiff> {
+        ProjectDiff::new(self, newer, config)
+    }
+}
+
+impl ProjectDiff {
+    /// Compare an old and a new version of a project.
+    ///
+    /// Datasets are paired by name, and media items by dataset and
+    /// media names.
+    pub fn new(old: &Project, new: &Project, config: &DiffConfig) -> Result<Self> {
+        let mut names: IndexSet<&String> = old.datasets.keys().chain(new.datasets.keys()).collect();
+        names.sort_unstable();
+
+        let mut diff = ProjectDiff {
+            meta: MetaDiff::new(&old.meta, &new.meta),
+            ..Self::default()
+        };
+
+        for name in names {
+            let old_items = old.datasets.get(name).map(load_items).transpose()?;
+            let new_items = new.datasets.get(name).map(load_items).transpose()?;
+
+            match (&old_items, &new_items) {
+                (None, Some(_)) => diff.added_datasets.push(name.clone()),
+                (Some(_), None) => diff.removed_datasets.push(name.clone()),
+                _ => {}
+            }
+
+            let old_items = old_items.unwrap_or_default();
+            let new_items = new_items.unwrap_or_default();
+            let mut media_names: IndexSet<&String> =
+                old_items.keys().chain(new_items.keys()).collect();
+            media_names.sort_unstable();
+
+            for media_name in media_names {
+                let media_diff = MediaDiff::new(
+                    name,
+                    media_name,
+                    old_items.get(media_name),
+                    new_items.get(media_name),
+                    config,
+                )?;
+                diff.media.extend(media_diff);
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Check if both versions are identical.
+    pub fn is_empty(&self) -> bool {
+        self.meta.is_empty()
+            && self.added_datasets.is_empty()
+            && self.removed_datasets.is_empty()
+            && self.media.is_empty()
+    }
+}
+
+impl MetaDiff {
+    pub fn new(old: &ProjectMeta, new: &ProjectMeta) -> Self {
+        let mut diff = Self::default();
+
+        for class in &old.classes {
+            let Some(other) = new.classes.iter().find(|other| other.title == class.title) else {
+                diff.removed_classes.push(class.title.clone());
+                continue;
+            };
+            let shape = value_change(class.shape, other.shape);
+            let color = value_change(class.color.map(hex_code), other.color.map(hex_code));
+            if shape.is_some() || color.is_some() {
+                diff.modified_classes.push(ClassMetaChange {
+                    title: class.title.clone(),
+                    shape,
+                    color,
+                });
+            }
+        }
+        diff.added_classes = new
+            .classes
+            .iter()
+            .filter(|class| !old.classes.iter().any(|other| other.title == class.title))
+            .map(|class| class.title.clone())
+            .collect();
+
+        for tag in &old.tags {
+            let Some(other) = new.tags.iter().find(|other| other.name == tag.name) else {
+                diff.removed_tags.push(tag.name.clone());
+                continue;
+            };
+            let old_values: IndexSet<&String> = tag.values.iter().flatten().collect();
+            let new_values: IndexSet<&String> = other.values.iter().flatten().collect();
+            let mut added_values: Vec<String> = new_values
+                .difference(&old_values)
+                .map(|&value| value.clone())
+                .collect();
+            let mut removed_values: Vec<String> = old_values
+                .difference(&new_values)
+                .map(|&value| value.clone())
+                .collect();
+            added_values.sort_unstable();
+            removed_values.sort_unstable();
+
+            let value_type = value_change(tag.value_type, other.value_type);
+            if value_type.is_some() || !added_values.is_empty() || !removed_values.is_empty() {
+                diff.modified_tags.push(TagMetaChange {
+                    name: tag.name.clone(),
+                    value_type,
+                    added_values,
+                    removed_values,
+                });
+            }
+        }
+        diff.added_tags = new
+            .tags
+            .iter()
+            .filter(|tag| !old.tags.iter().any(|other| other.name == tag.name))
+            .map(|tag| tag.name.clone())
+            .collect();
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_classes.is_empty()
+            && self.removed_classes.is_empty()
+            && self.modified_classes.is_empty()
+            && self.added_tags.is_empty()
+            && self.removed_tags.is_empty()
+            && self.modified_tags.is_empty()
+    }
+}
+
+impl MediaDiff {
+    /// Compare two versions of a media annotation. Returns `None` if
+    /// they are identical within the tolerance.
+    pub fn new(
+        dataset: &str,
+        name: &str,
+        old: Option<&MediaAnnotation>,
+        new: Option<&MediaAnnotation>,
+        config: &DiffConfig,
+    ) -> Result<Option<Self>> {
+        let status = match (old, new) {
+            (None, None) => return Ok(None),
+            (None, Some(_)) => ChangeStatus::Added,
+            (Some(_), None) => ChangeStatus::Removed,
+            (Some(_), Some(_)) => ChangeStatus::Modified,
+        };
+        let old_entries = old.map(entries).unwrap_or_default();
+        let new_entries = new.map(entries).unwrap_or_default();
+
+        let mut diffs = vec![];
+        for ((kind, key), entry) in &old_entries {
+            let other = new_entries.get(&(*kind, key.clone()));
+            diffs.extend(entry_diff(*kind, key, Some(entry), other, config)?);
+        }
+        for ((kind, key), entry) in &new_entries {
+            if !old_entries.contains_key(&(*kind, key.clone())) {
+                diffs.extend(entry_diff(*kind, key, None, Some(entry), config)?);
+            }
+        }
+
+        let tags = tag_changes(
+            old.map(media_tags).unwrap_or_default(),
+            new.map(media_tags).unwrap_or_default(),
+        );
+
+        if status == ChangeStatus::Modified && tags.is_empty() && diffs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            dataset: dataset.to_string(),
+            name: name.to_string(),
+            status,
+            tags,
+            entries: diffs,
+        }))
+    }
+}
+
+/// The comparable properties of an object or a figure.
+struct Entry<'a> {
+    class: Option<String>,
+    tags: Vec<&'a Tag>,
+    geometry: Option<GeometryRef<'a>>,
+}
+
+#[derive(Clone, Copy)]
+enum GeometryRef<'a> {
+    Planar(&'a Geometry),
+    PointCloud(&'a PointCloudGeometry),
+}
+
+fn load_items(dataset: &Dataset) -> Result<IndexMap<String, MediaAnnotation>> {
+    dataset
+        .iter_annotations()
+        .map(|item| {
+            let item = item?;
+            Ok((item.media_name.to_string(), item.annotation))
+        })
+        .collect()
+}
+
+fn media_tags(ann: &MediaAnnotation) -> Vec<&Tag> {
+    match ann {
+        MediaAnnotation::Image(ann) => ann.tags.iter().flatten().collect(),
+        MediaAnnotation::Video(ann) => ann.tags.iter().collect(),
+        MediaAnnotation::PointCloud(ann) => ann.tags.iter().collect(),
+        MediaAnnotation::PointCloudEpisode(ann) => ann.tags.iter().collect(),
+    }
+}
+
+fn entries(ann: &MediaAnnotation) -> IndexMap<(EntryKind, String), Entry<'_>> {
+    let mut entries = IndexMap::new();
+    let mut insert = |kind, key: &str, entry| {
+        entries.insert((kind, key.to_string()), entry);
+    };
+
+    match ann {
+        MediaAnnotation::Image(ann) => {
+            for obj in &ann.objects {
+                let entry = Entry {
+                    class: obj.class_name(),
+                    tags: obj.geometry.tags().into_iter().flatten().collect(),
+                    geometry: Some(GeometryRef::Planar(&obj.geometry)),
+                };
+                insert(EntryKind::Object, &obj.id.to_string(), entry);
+            }
+        }
+        MediaAnnotation::Video(ann) => {
+            for obj in &ann.objects {
+                let entry = Entry {
+                    class: obj.class_title.clone(),
+                    tags: obj.tags.iter().flatten().collect(),
+                    geometry: None,
+                };
+                insert(EntryKind::Object, &obj.key, entry);
+            }
+            for figure in ann.frames.iter().flat_map(|frame| &frame.figures) {
+                let entry = Entry {
+                    class: None,
+                    tags: figure.geometry.tags().into_iter().flatten().collect(),
+                    geometry: Some(GeometryRef::Planar(&figure.geometry)),
+                };
+                insert(EntryKind::Figure, &figure.key, entry);
+            }
+        }
+        MediaAnnotation::PointCloud(ann) => {
+            for obj in &ann.objects {
+                let entry = Entry {
+                    class: Some(obj.class_title.clone()),
+                    tags: obj.tags.iter().collect(),
+                    geometry: None,
+                };
+                insert(EntryKind::Object, &obj.key, entry);
+            }
+            for figure in &ann.figures {
+                let entry = Entry {
+                    class: None,
+                    tags: vec![],
+                    geometry: Some(GeometryRef::PointCloud(&figure.geometry)),
+                };
+                insert(EntryKind::Figure, &figure.key, entry);
+            }
+        }
+        MediaAnnotation::PointCloudEpisode(ann) => {
+            for obj in &ann.objects {
+                let entry = Entry {
+                    class: Some(obj.class_title.clone()),
+                    tags: obj.tags.iter().collect(),
+                    geometry: None,
+                };
+                insert(EntryKind::Object, &obj.key, entry);
+            }
+            for figure in ann.frames.iter().flat_map(|frame| &frame.figures) {
+                let entry = Entry {
+                    class: figure.class_title.clone(),
+                    tags: figure.geometry.tags().into_iter().flatten().collect(),
+                    geometry: Some(GeometryRef::Planar(&figure.geometry)),
+                };
+                insert(EntryKind::Figure, &figure.key, entry);
+            }
+        }
+    }
+
+    entries
+}
+
+fn entry_diff(
+    kind: EntryKind,
+    key: &str,
+    old: Option<&Entry<'_>>,
+    new: Option<&Entry<'_>>,
+    config: &DiffConfig,
+) -> Result<Option<EntryDiff>> {
+    let (status, class, geometry) = match (old, new) {
+        (None, None) => return Ok(None),
+        (None, Some(_)) => (ChangeStatus::Added, None, None),
+        (Some(_), None) => (ChangeStatus::Removed, None, None),
+        (Some(old), Some(new)) => {
+            let class = value_change(old.class.clone(), new.class.clone());
+            let geometry = match (old.geometry, new.geometry) {
+                (Some(lhs), Some(rhs)) => geometry_change(lhs, rhs, config.geometry_tolerance)?,
+                _ => None,
+            };
+            (ChangeStatus::Modified, class, geometry)
+        }
+    };
+    let tags = tag_changes(
+        old.map(|entry| entry.tags.clone()).unwrap_or_default(),
+        new.map(|entry| entry.tags.clone()).unwrap_or_default(),
+    );
+
+    if status == ChangeStatus::Modified && class.is_none() && geometry.is_none() && tags.is_empty()
+    {
+        return Ok(None);
+    }
+    Ok(Some(EntryDiff {
+        kind,
+        key: key.to_string(),
+        status,
+        class,
+        geometry,
+        tags,
+    }))
+}
+
+fn geometry_change(
+    old: GeometryRef<'_>,
+    new: GeometryRef<'_>,
+    tolerance: f64,
+) -> Result<Option<GeometryChange>> {
+    let distance = match (old, new) {
+        (GeometryRef::PointCloud(old), GeometryRef::PointCloud(new)) => {
+            let params = |geometry: &PointCloudGeometry| {
+                let PointCloudGeometry {
+                    position: p,
+                    rotation: r,
+                    dimensions: d,
+                } = geometry;
+                [p.x, p.y, p.z, r.x, r.y, r.z, d.x, d.y, d.z]
+            };
+            max_abs_diff(&params(old), &params(new))
+        }
+        (GeometryRef::Planar(old), GeometryRef::Planar(new)) => {
+            if old.shape() != new.shape() {
+                return Ok(Some(GeometryChange::Shape(ValueChange {
+                    old: old.shape(),
+                    new: new.shape(),
+                })));
+            }
+
+            match (old, new) {
+                (Geometry::Bitmap(lhs), Geometry::Bitmap(rhs)) => {
+                    if lhs.bitmap == rhs.bitmap {
+                        return Ok(None);
+                    }
+                    let iou = iou(old, new)?;
+                    if iou >= 1.0 {
+                        return Ok(None);
+                    }
+                    return Ok(Some(GeometryChange::Bitmap { iou }));
+                }
+                (Geometry::Cuboid3D(lhs), Geometry::Cuboid3D(rhs)) => {
+                    let params = |geometry: &Cuboid3DGeometry| {
+                        [&geometry.position, &geometry.rotation, &geometry.dimensions]
+                            .into_iter()
+                            .flat_map(|xyz| [xyz.x.raw(), xyz.y.raw(), xyz.z.raw()])
+                            .collect::<Vec<_>>()
+                    };
+                    max_abs_diff(&params(lhs), &params(rhs))
+                }
+                _ => match (points(old), points(new)) {
+                    (Some(lhs), Some(rhs)) => hausdorff(lhs, rhs),
+                    _ => 0.0,
+                },
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok((distance > tolerance).then_some(GeometryChange::Moved { distance }))
+}
+
+fn points(geometry: &Geometry) -> Option<&Points> {
+    let points = match geometry {
+        Geometry::Point(point) => &point.points,
+        Geometry::Rectangle(rect) => &rect.points,
+        Geometry::Polygon(polygon) => &polygon.points,
+        Geometry::Polyline(polyline) => &polyline.points,
+        Geometry::Bitmap(_) | Geometry::Cuboid3D(_) => return None,
+    };
+    Some(points)
+}
+
+/// The symmetric Hausdorff distance between the vertex sets.
+fn hausdorff(lhs: &Points, rhs: &Points) -> f64 {
+    let vertices = |points: &Points| -> Vec<(f64, f64)> {
+        points
+            .exterior
+            .iter()
+            .chain(points.interior.iter().flatten())
+            .map(|&(x, y)| (x.raw(), y.raw()))
+            .collect()
+    };
+    let (lhs, rhs) = (vertices(lhs), vertices(rhs));
+    if lhs.is_empty() || rhs.is_empty() {
+        return if lhs.len() == rhs.len() {
+            0.0
+        } else {
+            f64::INFINITY
+        };
+    }
+
+    let directed = |from: &[(f64, f64)], to: &[(f64, f64)]| {
+        from.iter()
+            .map(|&(x1, y1)| {
+                to.iter()
+                    .map(|&(x2, y2)| (x1 - x2).hypot(y1 - y2))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .fold(0.0, f64::max)
+    };
+    directed(&lhs, &rhs).max(directed(&rhs, &lhs))
+}
+
+fn max_abs_diff(lhs: &[f64], rhs: &[f64]) -> f64 {
+    lhs.iter()
+        .zip(rhs)
+        .map(|(lhs, rhs)| (lhs - rhs).abs())
+        .fold(0.0, f64::max)
+}
+
+fn tag_changes(old: Vec<&Tag>, new: Vec<&Tag>) -> Vec<TagChange> {
+    let group = |tags: Vec<&Tag>| {
+        let mut groups: IndexMap<String, Vec<Option<TagValue>>> = IndexMap::new();
+        for tag in tags {
+            groups
+                .entry(tag.name.clone())
+                .or_default()
+                .push(tag.value.clone());
+        }
+        groups
+    };
+    let (old, new) = (group(old), group(new));
+
+    let mut changes = vec![];
+    for (name, old_values) in &old {
+        match new.get(name) {
+            None => changes.extend(old_values.iter().map(|value| TagChange::Removed {
+                name: name.clone(),
+                value: value.clone(),
+            })),
+            Some(new_values) if new_values != old_values => {
+                changes.push(TagChange::Changed {
+                    name: name.clone(),
+                    old: old_values.first().cloned().flatten(),
+                    new: new_values.first().cloned().flatten(),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, new_values) in &new {
+        if !old.contains_key(name) {
+            changes.extend(new_values.iter().map(|value| TagChange::Added {
+                name: name.clone(),
+                value: value.clone(),
+            }));
+        }
+    }
+    changes
+}
+
+fn value_change<T: PartialEq>(old: T, new: T) -> Option<ValueChange<T>> {
+    (old != new).then_some(ValueChange { old, new })
+}
+
+fn hex_code(color: palette::Srgb<u8>) -> String {
+    let (r, g, b) = color.into_components();
+    hex_color::HexColor { r, g, b }.to_string()
+}
+
+impl fmt::Display for ProjectDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+
+        let meta = &self.meta;
+        for title in &meta.added_classes {
+            writeln!(f, "+ class '{title}'")?;
+        }
+        for title in &meta.removed_classes {
+            writeln!(f, "- class '{title}'")?;
+        }
+        for change in &meta.modified_classes {
+            write!(f, "~ class '{}':", change.title)?;
+            if let Some(ValueChange { old, new }) = &change.shape {
+                write!(f, " shape {old:?} -> {new:?}")?;
+            }
+            if let Some(ValueChange { old, new }) = &change.color {
+                write!(f, " color {} -> {}", or_none(old), or_none(new))?;
+            }
+            writeln!(f)?;
+        }
+        for name in &meta.added_tags {
+            writeln!(f, "+ tag '{name}'")?;
+        }
+        for name in &meta.removed_tags {
+            writeln!(f, "- tag '{name}'")?;
+        }
+        for change in &meta.modified_tags {
+            write!(f, "~ tag '{}':", change.name)?;
+            if let Some(ValueChange { old, new }) = &change.value_type {
+                write!(f, " value type {old:?} -> {new:?}")?;
+            }
+            if !change.added_values.is_empty() {
+                write!(f, " added values {:?}", change.added_values)?;
+            }
+            if !change.removed_values.is_empty() {
+                write!(f, " removed values {:?}", change.removed_values)?;
+            }
+            writeln!(f)?;
+        }
+
+        for name in &self.added_datasets {
+            writeln!(f, "+ dataset '{name}'")?;
+        }
+        for name in &self.removed_datasets {
+            writeln!(f, "- dataset '{name}'")?;
+        }
+
+        for media in &self.media {
+            writeln!(
+                f,
+                "{} {}/{}",
+                status_sign(media.status),
+                media.dataset,
+                media.name
+            )?;
+            if media.status != ChangeStatus::Modified {
+                continue;
+            }
+            for change in &media.tags {
+                writeln!(f, "    {change}")?;
+            }
+            for entry in &media.entries {
+                let kind = match entry.kind {
+                    EntryKind::Object => "object",
+                    EntryKind::Figure => "figure",
+                };
+                write!(f, "    {} {kind} {}", status_sign(entry.status), entry.key)?;
+
+                let mut details = vec![];
+                if let Some(ValueChange { old, new }) = &entry.class {
+                    details.push(format!("class {} -> {}", or_none(old), or_none(new)));
+                }
+                match &entry.geometry {
+                    Some(GeometryChange::Shape(ValueChange { old, new })) => {
+                        details.push(format!("shape {old:?} -> {new:?}"))
+                    }
+                    Some(GeometryChange::Moved { distance }) => {
+                        details.push(format!("moved by {distance:.2}"))
+                    }
+                    Some(GeometryChange::Bitmap { iou }) => {
+                        details.push(format!("bitmap changed (IoU {iou:.3})"))
+                    }
+                    None => {}
+                }
+                if entry.status == ChangeStatus::Modified {
+                    details.extend(entry.tags.iter().map(|change| change.to_string()));
+                }
+                if !details.is_empty() {
+                    write!(f, ": {}", details.join("; "))?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for TagChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagChange::Added { name, value } => write!(f, "+ tag '{name}'{}", value_text(value)),
+            TagChange::Removed { name, value } => {
+                write!(f, "- tag '{name}'{}", value_text(value))
+            }
+            TagChange::Changed { name, old, new } => {
+                write!(f, "~ tag '{name}'{} ->{}", value_text(old), value_text(new))
+            }
+        }
+    }
+}
+
+fn status_sign(status: ChangeStatus) -> char {
+    match status {
+        ChangeStatus::Added => '+',
+        ChangeStatus::Removed => '-',
+        ChangeStatus::Modified => '~',
+    }
+}
+
+fn or_none(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("(none)")
+}
+
+fn value_text(value: &Option<TagValue>) -> String {
+    match value {
+        Some(TagValue::Number(value)) => format!(" {value}"),
+        Some(TagValue::Float(value)) => format!(" {value}"),
+        Some(TagValue::Text(value) | TagValue::OneOf(value)) => format!(" '{value}'"),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ImageAnnotation, MemoryStorage};
+    use std::{path::Path, sync::Arc};
+
+    fn rectangle(id: usize, class_title: &str, x: i64) -> String {
+        format!(
+            r#"{{
+                "id": {id},
+                "classId": null,
+                "classTitle": "{class_title}",
+                "labelerLogin": null,
+                "createdAt": null,
+                "updatedAt": null,
+                "geometryType": "rectangle",
+                "tags": [],
+                "points": {{"exterior": [[{x}, 0], [{}, 10]], "interior": []}}
+            }}"#,
+            x + 10
+        )
+    }
+
+    fn image_ann(objects: &[String]) -> MediaAnnotation {
+        let ann: ImageAnnotation = serde_json::from_str(&format!(
+            r#"{{"name": "a.png", "size": {{"width": 100, "height": 100}}, "tags": [], "objects": [{}]}}"#,
+            objects.join(",")
+        ))
+        .unwrap();
+        MediaAnnotation::Image(ann)
+    }
+
+    fn media_diff(old: &[String], new: &[String], config: &DiffConfig) -> Option<MediaDiff> {
+        let (old, new) = (image_ann(old), image_ann(new));
+        MediaDiff::new("ds", "a.png", Some(&old), Some(&new), config).unwrap()
+    }
+
+    fn project(meta: &str, images: &[(&str, &[String])]) -> Project {
+        let storage = MemoryStorage::new();
+        storage.insert("meta.json", meta).unwrap();
+        for (name, objects) in images {
+            let ann = format!(
+                r#"{{"name": "{name}", "size": {{"width": 100, "height": 100}}, "tags": [], "objects": [{}]}}"#,
+                objects.join(",")
+            );
+            storage.insert(format!("ds/img/{name}"), []).unwrap();
+            storage.insert(format!("ds/ann/{name}.json"), ann).unwrap();
+        }
+        Project::open_with(Arc::new(storage), Path::new("")).unwrap()
+    }
+
+    const OLD_META: &str = r##"{
+        "classes": [
+            {"title": "car", "shape": "rectangle", "color": "#FF0000", "geometry_config": {}},
+            {"title": "dog", "shape": "polygon", "color": null, "geometry_config": {}}
+        ],
+        "tags": [
+            {"name": "weather", "color": null, "value_type": "oneof_string", "values": ["rainy", "sunny"]}
+        ]
+    }"##;
+
+    const NEW_META: &str = r##"{
+        "classes": [
+            {"title": "car", "shape": "rectangle", "color": "#0000FF", "geometry_config": {}},
+            {"title": "cat", "shape": "bitmap", "color": null, "geometry_config": {}}
+        ],
+        "tags": [
+            {"name": "weather", "color": null, "value_type": "oneof_string", "values": ["snowy", "sunny"]},
+            {"name": "night", "color": null, "value_type": "none", "values": null}
+        ]
+    }"##;
+
+    #[test]
+    fn identical_projects_have_no_diff() {
+        let objects = [rectangle(1, "car", 0)];
+        let old = project(OLD_META, &[("a.png", &objects)]);
+        let new = project(OLD_META, &[("a.png", &objects)]);
+
+        let diff = old.diff(&new, &DiffConfig::default()).unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "no changes\n");
+    }
+
+    #[test]
+    fn diff_media() {
+        let old = project(
+            OLD_META,
+            &[("a.png", &[rectangle(1, "car", 0)]), ("b.png", &[])],
+        );
+        let new = project(
+            NEW_META,
+            &[("a.png", &[rectangle(1, "car", 5)]), ("c.png", &[])],
+        );
+
+        let diff = old.diff(&new, &DiffConfig::default()).unwrap();
+        assert!(!diff.meta.is_empty());
+        let statuses: Vec<(&str, ChangeStatus)> = diff
+            .media
+            .iter()
+            .map(|media| (media.name.as_str(), media.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("a.png", ChangeStatus::Modified),
+                ("b.png", ChangeStatus::Removed),
+                ("c.png", ChangeStatus::Added)
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_meta() {
+        let old: ProjectMeta = serde_json::from_str(OLD_META).unwrap();
+        let new: ProjectMeta = serde_json::from_str(NEW_META).unwrap();
+        assert!(MetaDiff::new(&old, &old).is_empty());
+
+        let meta = MetaDiff::new(&old, &new);
+        assert_eq!(meta.added_classes, ["cat"]);
+        assert_eq!(meta.removed_classes, ["dog"]);
+        assert_eq!(
+            meta.modified_classes,
+            [ClassMetaChange {
+                title: "car".to_string(),
+                shape: None,
+                color: Some(ValueChange {
+                    old: Some("#FF0000".to_string()),
+                    new: Some("#0000FF".to_string()),
+                }),
+            }]
+        );
+        assert_eq!(meta.added_tags, ["night"]);
+        assert!(meta.removed_tags.is_empty());
+        assert_eq!(
+            meta.modified_tags,
+            [TagMetaChange {
+                name: "weather".to_string(),
+                value_type: None,
+                added_values: vec!["snowy".to_string()],
+                removed_values: vec!["rainy".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_objects() {
+        let old = [
+            rectangle(1, "car", 0),
+            rectangle(2, "car", 20),
+            rectangle(3, "car", 40),
+            rectangle(4, "car", 60),
+        ];
+        let new = [
+            rectangle(1, "car", 0),
+            rectangle(2, "car", 23),
+            rectangle(3, "dog", 40),
+            rectangle(5, "car", 80),
+        ];
+        let config = DiffConfig::default();
+        assert_eq!(media_diff(&old, &old, &config), None);
+
+        let diff = media_diff(&old, &new, &config).unwrap();
+        assert_eq!(diff.status, ChangeStatus::Modified);
+        let entries: Vec<(&str, ChangeStatus)> = diff
+            .entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("2", ChangeStatus::Modified),
+                ("3", ChangeStatus::Modified),
+                ("4", ChangeStatus::Removed),
+                ("5", ChangeStatus::Added)
+            ]
+        );
+
+        let moved = &diff.entries[0];
+        assert_eq!(moved.class, None);
+        assert_eq!(
+            moved.geometry,
+            Some(GeometryChange::Moved { distance: 3.0 })
+        );
+
+        let renamed = &diff.entries[1];
+        assert_eq!(
+            renamed.class,
+            Some(ValueChange {
+                old: Some("car".to_string()),
+                new: Some("dog".to_string()),
+            })
+        );
+        assert_eq!(renamed.geometry, None);
+    }
+
+    #[test]
+    fn small_moves_are_within_the_tolerance() {
+        let old = [rectangle(1, "car", 0)];
+        let new = [rectangle(1, "car", 1)];
+
+        let config = DiffConfig {
+            geometry_tolerance: 1.0,
+        };
+        assert_eq!(media_diff(&old, &new, &config), None);
+
+        let config = DiffConfig {
+            geometry_tolerance: 0.5,
+        };
+        assert!(media_diff(&old, &new, &config).is_some());
+    }
+}
@@ -0,0 +1,439 @@
+use crate::{Error, Geometry, ImageAnnotation, Object, Points, PolygonGeometry, Result, Size, Tag};
+use noisy_float::types::r64;
+use std::{fmt, fs, path::Path, str::FromStr};
+use tracing::warn;
+
+/// The name of the tag storing the DOTA difficulty flag on polygon objects.
+pub const DOTA_DIFFICULT_TAG: &str = "difficult";
+
+/// A DOTA label file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DotaAnnotation {
+    pub image_source: Option<String>,
+    pub gsd: Option<f64>,
+    pub objects: Vec<DotaObject>,
+}
+
+/// An oriented object in a DOTA label file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DotaObject {
+    /// The four vertices of the oriented box in clockwise order.
+    pub points: [(f64, f64); 4],
+    pub category: String,
+    pub difficult: bool,
+}
+
+/// A rotated rectangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotatedRect {
+    pub center: (f64, f64),
+    pub width: f64,
+    pub height: f64,
+    /// The angle of the width axis in radians.
+    pub angle: f64,
+}
+
+impl RotatedRect {
+    /// Get the four corners of the rectangle.
+    pub fn corners(&self) -> [(f64, f64); 4] {
+        let Self {
+            center: (cx, cy),
+            width,
+            height,
+            angle,
+        } = *self;
+        let (ux, uy) = (angle.cos() * width / 2.0, angle.sin() * width / 2.0);
+        let (vx, vy) = (-angle.sin() * height / 2.0, angle.cos() * height / 2.0);
+
+        [
+            (cx - ux - vx, cy - uy - vy),
+            (cx + ux - vx, cy + uy - vy),
+            (cx + ux + vx, cy + uy + vy),
+            (cx - ux + vx, cy - uy + vy),
+        ]
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width * self.height
+    }
+}
+
+impl PolygonGeometry {
+    /// Fit the minimum-area rotated rectangle enclosing the exterior of
+    /// the polygon. Returns `None` if the polygon is degenerate.
+    pub fn min_area_rect(&self) -> Option<RotatedRect> {
+        let points: Vec<_> = self
+            .points
+            .exterior
+            .iter()
+            .map(|&(x, y)| (x.raw(), y.raw()))
+            .collect();
+        let hull = convex_hull(points);
+        if hull.len() < 3 {
+            return None;
+        }
+
+        let rect = hull
+            .iter()
+            .zip(hull.iter().cycle().skip(1))
+            .filter_map(|(&(x1, y1), &(x2, y2))| {
+                let len = (x2 - x1).hypot(y2 - y1);
+                if len == 0.0 {
+                    return None;
+                }
+                let (ux, uy) = ((x2 - x1) / len, (y2 - y1) / len);
+
+                let (mut smin, mut smax) = (f64::INFINITY, f64::NEG_INFINITY);
+                let (mut tmin, mut tmax) = (f64::INFINITY, f64::NEG_INFINITY);
+                for &(x, y) in &hull {
+                    let s = x * ux + y * uy;
+                    let t = -x * uy + y * ux;
+                    smin = smin.min(s);
+                    smax = smax.max(s);
+                    tmin = tmin.min(t);
+                    tmax = tmax.max(t);
+                }
+
+                let (sc, tc) = ((smin + smax) / 2.0, (tmin + tmax) / 2.0);
+                Some(RotatedRect {
+                    center: (sc * ux - tc * uy, sc * uy + tc * ux),
+                    width: smax - smin,
+                    height: tmax - tmin,
+                    angle: uy.atan2(ux),
+                })
+            })
+            .min_by(|lhs, rhs| lhs.area().total_cmp(&rhs.area()))?;
+
+        Some(rect)
+    }
+}
+
+impl DotaAnnotation {
+    /// Load a DOTA label file.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| Error::open_file_error(path, error))?;
+        text.parse()
+    }
+
+    /// Convert to a Supervisely image annotation. Each oriented box
+    /// becomes a 4-vertex polygon tagged with its difficulty.
+    pub fn to_image_annotation(&self, name: String, size: Size) -> ImageAnnotation {
+        let objects = self
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(id, obj)| {
+                let exterior = obj.points.iter().map(|&(x, y)| (r64(x), r64(y))).collect();
+                let tag = Tag::new(DOTA_DIFFICULT_TAG.to_string(), obj.difficult as isize);
+
+                Object {
+                    id,
+                    class_id: None,
+                    class_title: Some(obj.category.clone()),
+                    labeler_login: None,
+                    created_at: None,
+                    updated_at: None,
+                    geometry: PolygonGeometry {
+                        tags: Some(vec![tag]),
+                        points: Points {
+                            exterior,
+                            interior: vec![],
+                        },
+                    }
+                    .into(),
+                }
+            })
+            .collect();
+
+        ImageAnnotation {
+            name,
+            description: None,
+            size,
+            tags: None,
+            objects,
+        }
+    }
+
+    /// Convert a Supervisely image annotation to DOTA labels.
+    ///
+    /// Rectangles and 4-vertex polygons are kept as is, while other
+    /// polygons are replaced by their minimum-area rotated rectangle.
+    /// Objects of other geometry types are skipped.
+    pub fn from_image_annotation(ann: &ImageAnnotation) -> Self {
+        let objects = ann
+            .objects
+            .iter()
+            .filter_map(|obj| {
+                let Some(category) = obj.class_title.clone() else {
+                    warn!("skip object {} without a class title", obj.id);
+                    return None;
+                };
+
+                let points = match &obj.geometry {
+                    Geometry::Rectangle(rect) => {
+                        let [(x1, y1), (x2, y2)] = rect.points.exterior.as_slice() else {
+                            warn!("skip rectangle object {} with invalid points", obj.id);
+                            return None;
+                        };
+                        let (x1, y1, x2, y2) = (x1.raw(), y1.raw(), x2.raw(), y2.raw());
+                        [(x1, y1), (x2, y1), (x2, y2), (x1, y2)]
+                    }
+                    Geometry::Polygon(polygon) => match polygon.points.exterior.as_slice() {
+                        &[p1, p2, p3, p4] => [p1, p2, p3, p4].map(|(x, y)| (x.raw(), y.raw())),
+                        _ => {
+                            let Some(rect) = polygon.min_area_rect() else {
+                                warn!("skip degenerate polygon object {}", obj.id);
+                                return None;
+                            };
+                            rect.corners()
+                        }
+                    },
+                    _ => {
+                        warn!(
+                            "skip object {} whose geometry is not supported by DOTA",
+                            obj.id
+                        );
+                        return None;
+                    }
+                };
+
+                let difficult = obj
+                    .geometry
+                    .tags()
+                    .into_iter()
+                    .flatten()
+                    .find(|tag| tag.name == DOTA_DIFFICULT_TAG)
+                    .and_then(|tag| tag.value.as_ref()?.as_f64())
+                    .is_some_and(|value| value != 0.0);
+
+                Some(DotaObject {
+                    points,
+                    category,
+                    difficult,
+                })
+            })
+            .collect();
+
+        Self {
+            image_source: None,
+            gsd: None,
+            objects,
+        }
+    }
+}
+
+impl FromStr for DotaAnnotation {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut image_source = None;
+        let mut gsd = None;
+        let mut objects = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            } else if let Some(source) = line.strip_prefix("imagesource:") {
+                image_source = Some(source.trim().to_string());
+                continue;
+            } else if let Some(value) = line.strip_prefix("gsd:") {
+                gsd = value.trim().parse().ok();
+                continue;
+            }
+
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let (coords, category, difficult) =
+                match tokens.as_slice() {
+                    [coords @ .., category] if coords.len() == 8 => (coords, category, false),
+                    [coords @ .., category, difficult] if coords.len() == 8 => {
+                        let difficult: u8 = difficult.parse().map_err(|_| {
+                            Error::invalid_dota_label(line_no, "invalid difficulty flag")
+                        })?;
+                        (coords, category, difficult != 0)
+                    }
+                    _ => return Err(Error::invalid_dota_label(
+                        line_no,
+                        "expect 8 coordinates followed by a category and an optional difficulty",
+                    )),
+                };
+
+            let coords: Vec<f64> = coords
+                .iter()
+                .map(|token| token.parse::<f64>().ok().filter(|value| value.is_finite()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| Error::invalid_dota_label(line_no, "invalid coordinate"))?;
+            let points = [0, 1, 2, 3].map(|index| (coords[index * 2], coords[index * 2 + 1]));
+
+            objects.push(DotaObject {
+                points,
+                category: category.to_string(),
+                difficult,
+            });
+        }
+
+        Ok(Self {
+            image_source,
+            gsd,
+            objects,
+        })
+    }
+}
+
+impl fmt::Display for DotaAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(source) = &self.image_source {
+            writeln!(f, "imagesource:{source}")?;
+        }
+        if let Some(gsd) = self.gsd {
+            writeln!(f, "gsd:{gsd}")?;
+        }
+
+        for obj in &self.objects {
+            for (x, y) in obj.points {
+                write!(f, "{x} {y} ")?;
+            }
+            writeln!(f, "{} {}", obj.category, obj.difficult as u8)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the convex hull in counter-clockwise order using the monotone
+/// chain algorithm.
+fn convex_hull(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_by(|lhs, rhs| lhs.0.total_cmp(&rhs.0).then(lhs.1.total_cmp(&rhs.1)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+
+    let mut lower: Vec<(f64, f64)> = vec![];
+    for &point in &points {
+        while lower.len() >= 2
+            && cross(lower[lower.len() - 2], lower[lower.len() - 1], point) <= 0.0
+        {
+            lower.pop();
+        }
+        lower.push(point);
+    }
+
+    let mut upper: Vec<(f64, f64)> = vec![];
+    for &point in points.iter().rev() {
+        while upper.len() >= 2
+            && cross(upper[upper.len() - 2], upper[upper.len() - 1], point) <= 0.0
+        {
+            upper.pop();
+        }
+        upper.push(point);
+    }
+
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+    lower
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABELS: &str = "\
+imagesource:GoogleEarth
+gsd:0.146
+10 20 50 20 50 40 10 40 plane 0
+1.5 2 8 2 8 6 1.5 6 small-vehicle 1
+";
+
+    fn polygon(exterior: &[(f64, f64)]) -> PolygonGeometry {
+        PolygonGeometry {
+            tags: None,
+            points: Points {
+                exterior: exterior.iter().map(|&(x, y)| (r64(x), r64(y))).collect(),
+                interior: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn parse_and_format_labels() {
+        let ann: DotaAnnotation = LABELS.parse().unwrap();
+        assert_eq!(ann.image_source.as_deref(), Some("GoogleEarth"));
+        assert_eq!(ann.gsd, Some(0.146));
+        assert_eq!(
+            ann.objects[1],
+            DotaObject {
+                points: [(1.5, 2.0), (8.0, 2.0), (8.0, 6.0), (1.5, 6.0)],
+                category: "small-vehicle".to_string(),
+                difficult: true,
+            }
+        );
+
+        assert_eq!(ann.to_string().parse::<DotaAnnotation>().unwrap(), ann);
+    }
+
+    #[test]
+    fn round_trip_through_image_annotation() {
+        let ann: DotaAnnotation = LABELS.parse().unwrap();
+        let size = Size {
+            width: 100,
+            height: 100,
+        };
+        let image_ann = ann.to_image_annotation("image.png".to_string(), size);
+        let restored = DotaAnnotation::from_image_annotation(&image_ann);
+        assert_eq!(restored.objects, ann.objects);
+    }
+
+    #[test]
+    fn reject_invalid_lines() {
+        let result = "imagesource:x\n1 2 3 4 5 6 7 plane\n".parse::<DotaAnnotation>();
+        assert!(matches!(
+            result,
+            Err(Error::InvalidDotaLabel { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn reject_non_finite_coordinates() {
+        for value in ["nan", "inf", "-inf"] {
+            let result = format!("1 2 3 4 5 6 7 {value} plane 0\n").parse::<DotaAnnotation>();
+            assert!(matches!(
+                result,
+                Err(Error::InvalidDotaLabel { line: 1, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn min_area_rect_of_rotated_square() {
+        let polygon = polygon(&[(1.0, 0.0), (2.0, 1.0), (1.0, 2.0), (0.0, 1.0), (1.0, 1.0)]);
+        let rect = polygon.min_area_rect().unwrap();
+
+        let close = |lhs: f64, rhs: f64| (lhs - rhs).abs() < 1e-9;
+        assert!(close(rect.area(), 2.0));
+        assert!(close(rect.center.0, 1.0) && close(rect.center.1, 1.0));
+        for (x, y) in rect.corners() {
+            let is_vertex = [(1.0, 0.0), (2.0, 1.0), (1.0, 2.0), (0.0, 1.0)]
+                .iter()
+                .any(|&(vx, vy)| close(x, vx) && close(y, vy));
+            assert!(is_vertex, "unexpected corner ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn min_area_rect_of_degenerate_polygon() {
+        let polygon = polygon(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]);
+        assert_eq!(polygon.min_area_rect(), None);
+    }
+}
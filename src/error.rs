@@ -1,3 +1,4 @@
+use crate::{MediaKind, Shape, Size, ValueType};
 use std::{
     io,
     path::{Path, PathBuf},
@@ -25,11 +26,134 @@ pub enum Error {
         path: PathBuf,
     },
 
+    #[error("Unable to write file '{path}': {error}")]
+    WriteFileError { path: PathBuf, error: io::Error },
+
+    #[error("Unable to create directory '{path}': {error}")]
+    CreateDirError { path: PathBuf, error: io::Error },
+
+    #[error("Unable to copy file '{from}' to '{to}': {error}")]
+    CopyFileError {
+        from: PathBuf,
+        to: PathBuf,
+        error: io::Error,
+    },
+
+    #[error("Fail to write JSON file '{path}': {error}")]
+    WriteJsonFileError {
+        error: serde_json::Error,
+        path: PathBuf,
+    },
+
+    #[error("Unable to read archive '{path}': {error}")]
+    ReadArchiveError { path: PathBuf, error: io::Error },
+
+    #[error("Unsupported archive format of '{0}', expect a .tar or .zip file with the corresponding feature enabled")]
+    UnsupportedArchive(PathBuf),
+
+    #[error("Unable to find meta.json at the root or a top-level directory of '{0}'")]
+    ProjectNotFoundInArchive(PathBuf),
+
+    #[error("Unable to write '{0}' to a read-only storage")]
+    ReadOnlyStorage(PathBuf),
+
+    #[error("Path '{0}' escapes the storage root")]
+    PathEscapesRoot(PathBuf),
+
+    #[error("Class '{0}' is not defined in the project meta")]
+    UnknownClass(String),
+
+    #[error("Class '{0}' is defined more than once")]
+    DuplicateClass(String),
+
+    #[error("Expect a {expected:?} geometry for class '{title}', but found {found:?}")]
+    ClassShapeMismatch {
+        title: String,
+        expected: Shape,
+        found: Shape,
+    },
+
+    #[error("Tag '{0}' is not defined in the project meta")]
+    UnknownTag(String),
+
+    #[error("Tag '{0}' is defined more than once")]
+    DuplicateTag(String),
+
+    #[error("Invalid value of tag '{name}': {reason}")]
+    InvalidTagValue { name: String, reason: String },
+
+    #[error("Object id {0} is used more than once")]
+    DuplicateObjectId(usize),
+
+    #[error("Unable to detect the layout of dataset '{0}', which has none of img, video, pointcloud or frame_pointcloud_map.json")]
+    UnknownDatasetLayout(PathBuf),
+
     #[error("Expect a single media folder within '{0}', but found zero or multiple directories.")]
     ExpectSingleMediaDirectory(PathBuf),
 
     #[error("unable to decode data")]
     DecodeDataError,
+
+    #[error("unable to encode data")]
+    EncodeDataError,
+
+    #[error("Expect a 2D geometry, but found a 3D cuboid")]
+    Expect2DGeometry,
+
+    #[error("Expect a rectangle, polygon or bitmap geometry")]
+    ExpectAreaGeometry,
+
+    #[error("Invalid LabelMe shape '{label}': {reason}")]
+    InvalidLabelMeShape { label: String, reason: String },
+
+    #[error("Invalid DOTA label at line {line}: {reason}")]
+    InvalidDotaLabel { line: usize, reason: String },
+
+    #[error("Unable to merge class '{title}' of shape {lhs:?} with shape {rhs:?}")]
+    IncompatibleClassShapes {
+        title: String,
+        lhs: Shape,
+        rhs: Shape,
+    },
+
+    #[error("Unable to merge tag '{name}' of value type {lhs:?} with value type {rhs:?}")]
+    IncompatibleTagTypes {
+        name: String,
+        lhs: ValueType,
+        rhs: ValueType,
+    },
+
+    #[error("Class '{title}' is mapped to '{target}', which is itself mapped to '{next}'")]
+    ChainedClassMapping {
+        title: String,
+        target: String,
+        next: String,
+    },
+
+    #[error("Media '{name}' is not found in dataset '{dataset}'")]
+    MediaNotFound { dataset: String, name: String },
+
+    #[error("Expect an annotation of {expected:?} media for '{path}'")]
+    AnnotationKindMismatch { path: PathBuf, expected: MediaKind },
+
+    #[error("Expect at least one split")]
+    NoSplits,
+
+    #[error("Invalid ratio {ratio} of split '{name}', expect a finite positive number")]
+    InvalidSplitRatio { name: String, ratio: f64 },
+
+    #[error("Unable to read the dimensions of image '{0}', which is not a PNG, JPEG, GIF, BMP or WebP image")]
+    UnknownImageFormat(PathBuf),
+
+    #[error("Image '{path}' is {found:?}, but its annotation says {expected:?}")]
+    ImageSizeMismatch {
+        path: PathBuf,
+        expected: Size,
+        found: Size,
+    },
+
+    #[error("Found {} errors, the first of which is: {first}", .rest.len() + 1)]
+    MultipleErrors { first: Box<Error>, rest: Vec<Error> },
 }
 
 impl Error {
@@ -73,6 +197,58 @@ impl Error {
         }
     }
 
+    pub fn write_file_error<P>(path: P, error: io::Error) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::WriteFileError {
+            path: path.as_ref().to_path_buf(),
+            error,
+        }
+    }
+
+    pub fn create_dir_error<P>(path: P, error: io::Error) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::CreateDirError {
+            path: path.as_ref().to_path_buf(),
+            error,
+        }
+    }
+
+    pub fn copy_file_error<P, Q>(from: P, to: Q, error: io::Error) -> Self
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        Self::CopyFileError {
+            from: from.as_ref().to_path_buf(),
+            to: to.as_ref().to_path_buf(),
+            error,
+        }
+    }
+
+    pub fn write_json_file_error<P>(path: P, error: serde_json::Error) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::WriteJsonFileError {
+            path: path.as_ref().to_path_buf(),
+            error,
+        }
+    }
+
+    pub fn read_archive_error<P>(path: P, error: io::Error) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::ReadArchiveError {
+            path: path.as_ref().to_path_buf(),
+            error,
+        }
+    }
+
     pub fn expect_single_media_folder<P>(dir: P) -> Self
     where
         P: AsRef<Path>,
@@ -80,6 +256,53 @@ impl Error {
         Self::ExpectSingleMediaDirectory(dir.as_ref().to_path_buf())
     }
 
+    pub fn invalid_labelme_shape<L, R>(label: L, reason: R) -> Self
+    where
+        L: ToString,
+        R: ToString,
+    {
+        Self::InvalidLabelMeShape {
+            label: label.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn invalid_dota_label<R>(line: usize, reason: R) -> Self
+    where
+        R: ToString,
+    {
+        Self::InvalidDotaLabel {
+            line,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Combine the errors into one, or return `None` if there is none.
+    pub fn multiple(errors: Vec<Error>) -> Option<Self> {
+        let mut errors = errors.into_iter();
+        let first = errors.next()?;
+        let rest: Vec<_> = errors.collect();
+
+        if rest.is_empty() {
+            return Some(first);
+        }
+        Some(Self::MultipleErrors {
+            first: Box::new(first),
+            rest,
+        })
+    }
+
+    pub fn invalid_tag_value<N, R>(name: N, reason: R) -> Self
+    where
+        N: ToString,
+        R: ToString,
+    {
+        Self::InvalidTagValue {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
     pub fn expect_utf8_file_name<P>(dir: P) -> Self
     where
         P: AsRef<Path>,
@@ -87,3 +310,41 @@ impl Error {
         Self::ExpectUtf8FileName(dir.as_ref().to_path_buf())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dota_error(line: usize) -> Error {
+        Error::InvalidDotaLabel {
+            line,
+            reason: "missing class".to_string(),
+        }
+    }
+
+    #[test]
+    fn multiple_collects_all_errors() {
+        assert!(Error::multiple(vec![]).is_none());
+
+        let error = Error::multiple(vec![dota_error(1)]).unwrap();
+        assert!(matches!(error, Error::InvalidDotaLabel { line: 1, .. }));
+
+        let error = Error::multiple(vec![dota_error(1), dota_error(2), dota_error(3)]).unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Found 3 errors, the first of which is: Invalid DOTA label at line 1: missing class"
+        );
+        let Error::MultipleErrors { first, rest } = error else {
+            panic!("expect multiple errors");
+        };
+        assert!(matches!(*first, Error::InvalidDotaLabel { line: 1, .. }));
+        let rest: Vec<String> = rest.iter().map(|error| error.to_string()).collect();
+        assert_eq!(
+            rest,
+            [
+                "Invalid DOTA label at line 2: missing class",
+                "Invalid DOTA label at line 3: missing class"
+            ]
+        );
+    }
+}
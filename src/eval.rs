@@ -0,0 +1,287 @@
+mod image;
+mod point_cloud;
+
+pub use image::{evaluate_images, ImageEvalConfig};
+pub use point_cloud::{
+    evaluate_point_clouds, DistanceBandReport, MatchCriterion, PointCloudEvalConfig,
+    PointCloudEvalReport,
+};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// The default name of the tag storing the confidence of a prediction.
+pub const CONFIDENCE_TAG: &str = "confidence";
+
+/// The evaluation metrics of a single class.
+///
+/// The metrics are `None` if the class has no ground truth objects.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClassMetrics {
+    pub num_ground_truths: usize,
+    pub num_predictions: usize,
+    /// The average precision over all thresholds.
+    pub ap: Option<f64>,
+    /// The average precision at each threshold.
+    pub ap_per_threshold: Vec<Option<f64>>,
+    /// The average recall over all thresholds.
+    pub ar: Option<f64>,
+}
+
+/// The evaluation report comparing predictions against ground truth.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalReport {
+    /// The matching thresholds used in the evaluation.
+    pub thresholds: Vec<f64>,
+    pub classes: IndexMap<String, ClassMetrics>,
+    /// The mean of the class average precisions.
+    pub map: Option<f64>,
+    /// The mean of the class average recalls.
+    pub mar: Option<f64>,
+}
+
+impl EvalReport {
+    /// Build the report from per-class metrics, averaging over the
+    /// classes that have ground truth objects.
+    pub(crate) fn new(thresholds: Vec<f64>, classes: IndexMap<String, ClassMetrics>) -> Self {
+        let map = mean(classes.values().filter_map(|metrics| metrics.ap));
+        let mar = mean(classes.values().filter_map(|metrics| metrics.ar));
+        Self {
+            thresholds,
+            classes,
+            map,
+            mar,
+        }
+    }
+}
+
+/// The COCO IoU thresholds 0.50:0.05:0.95.
+pub(crate) fn coco_iou_thresholds() -> Vec<f64> {
+    (0..10).map(|step| 0.5 + 0.05 * step as f64).collect()
+}
+
+/// Scored detections of a class at one threshold.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Detections {
+    /// The `(score, true positive)` pairs.
+    pub results: Vec<(f64, bool)>,
+    pub num_ground_truths: usize,
+}
+
+impl Detections {
+    /// Compute the 101-point interpolated average precision and the
+    /// final recall, or `None` if there is no ground truth.
+    pub fn ap_and_recall(mut self) -> Option<(f64, f64)> {
+        if self.num_ground_truths == 0 {
+            return None;
+        }
+        self.results
+            .sort_by(|(lhs, _), (rhs, _)| rhs.total_cmp(lhs));
+
+        let num_gt = self.num_ground_truths as f64;
+        let mut tp = 0.0;
+        let mut fp = 0.0;
+        let mut recalls = Vec::with_capacity(self.results.len());
+        let mut precisions = Vec::with_capacity(self.results.len());
+        for &(_, is_tp) in &self.results {
+            if is_tp {
+                tp += 1.0;
+            } else {
+                fp += 1.0;
+            }
+            recalls.push(tp / num_gt);
+            precisions.push(tp / (tp + fp));
+        }
+
+        // Make the precision monotonically decreasing.
+        for index in (1..precisions.len()).rev() {
+            precisions[index - 1] = f64::max(precisions[index - 1], precisions[index]);
+        }
+
+        let ap = (0..=100)
+            .map(|step| {
+                let recall = step as f64 / 100.0;
+                let index = recalls.partition_point(|&value| value < recall);
+                precisions.get(index).copied().unwrap_or(0.0)
+            })
+            .sum::<f64>()
+            / 101.0;
+        let recall = recalls.last().copied().unwrap_or(0.0);
+
+        Some((ap, recall))
+    }
+}
+
+/// The objects of a class across all samples.
+#[derive(Debug, Default)]
+pub(crate) struct ClassEntries {
+    samples: Vec<SampleEntry>,
+    num_gt: usize,
+    num_pred: usize,
+}
+
+/// The objects of a class in one sample.
+#[derive(Debug)]
+struct SampleEntry {
+    /// The prediction scores in descending order.
+    scores: Vec<f64>,
+    /// The similarity of each prediction to each ground truth object.
+    similarities: Vec<Vec<f64>>,
+    num_gt: usize,
+}
+
+impl ClassEntries {
+    /// Add the objects of a class in one sample. The predictions with
+    /// the highest scores are kept up to `max_detections`.
+    pub fn push<G, P, F>(
+        &mut self,
+        gts: &[G],
+        mut preds: Vec<(f64, P)>,
+        max_detections: usize,
+        similarity: F,
+    ) where
+        F: Fn(&P, &G) -> f64,
+    {
+        preds.sort_by(|(lhs, _), (rhs, _)| rhs.total_cmp(lhs));
+        preds.truncate(max_detections);
+
+        self.num_gt += gts.len();
+        self.num_pred += preds.len();
+        self.samples.push(SampleEntry {
+            similarities: preds
+                .iter()
+                .map(|(_, pred)| gts.iter().map(|gt| similarity(pred, gt)).collect())
+                .collect(),
+            scores: preds.into_iter().map(|(score, _)| score).collect(),
+            num_gt: gts.len(),
+        });
+    }
+
+    /// Match the predictions to ground truth objects in descending
+    /// score order. A prediction is paired with the most similar
+    /// unmatched object whose similarity reaches the threshold.
+    pub fn detections(&self, threshold: f64) -> Detections {
+        let mut results = vec![];
+
+        for entry in &self.samples {
+            let mut gt_matched = vec![false; entry.num_gt];
+
+            for (score, similarities) in entry.scores.iter().zip(&entry.similarities) {
+                let best = similarities
+                    .iter()
+                    .enumerate()
+                    .filter(|&(gt, &value)| !gt_matched[gt] && value >= threshold)
+                    .max_by(|(_, lhs), (_, rhs)| lhs.total_cmp(rhs))
+                    .map(|(gt, _)| gt);
+
+                if let Some(gt) = best {
+                    gt_matched[gt] = true;
+                }
+                results.push((*score, best.is_some()));
+            }
+        }
+
+        Detections {
+            results,
+            num_ground_truths: self.num_gt,
+        }
+    }
+
+    /// Summarize the detections over all similarity thresholds.
+    pub fn metrics<I>(&self, thresholds: I) -> ClassMetrics
+    where
+        I: IntoIterator<Item = f64>,
+    {
+        let results: Vec<_> = thresholds
+            .into_iter()
+            .map(|threshold| self.detections(threshold).ap_and_recall())
+            .collect();
+        let ap_per_threshold: Vec<_> = results
+            .iter()
+            .map(|result| result.map(|(ap, _)| ap))
+            .collect();
+
+        ClassMetrics {
+            num_ground_truths: self.num_gt,
+            num_predictions: self.num_pred,
+            ap: mean(ap_per_threshold.iter().flatten().copied()),
+            ar: mean(results.iter().flatten().map(|&(_, recall)| recall)),
+            ap_per_threshold,
+        }
+    }
+}
+
+fn mean<I>(values: I) -> Option<f64>
+where
+    I: IntoIterator<Item = f64>,
+{
+    let (count, sum) = values
+        .into_iter()
+        .fold((0, 0.0), |(count, sum), value| (count + 1, sum + value));
+    (count > 0).then(|| sum / count as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(lhs: f64, rhs: f64) -> bool {
+        (lhs - rhs).abs() < 1e-9
+    }
+
+    #[test]
+    fn ap_and_recall_of_ranked_detections() {
+        // Ranked by score: TP, FP, TP. The interpolated precision is 1
+        // up to recall 0.5 and 2/3 beyond.
+        let detections = Detections {
+            results: vec![(0.7, true), (0.9, true), (0.8, false)],
+            num_ground_truths: 2,
+        };
+        let (ap, recall) = detections.ap_and_recall().unwrap();
+        assert!(close(ap, (51.0 + 50.0 * 2.0 / 3.0) / 101.0));
+        assert!(close(recall, 1.0));
+    }
+
+    #[test]
+    fn ap_and_recall_with_missed_ground_truth() {
+        let detections = Detections {
+            results: vec![(0.9, true)],
+            num_ground_truths: 2,
+        };
+        let (ap, recall) = detections.ap_and_recall().unwrap();
+        assert!(close(ap, 51.0 / 101.0));
+        assert!(close(recall, 0.5));
+
+        let detections = Detections {
+            results: vec![(0.9, false)],
+            num_ground_truths: 0,
+        };
+        assert_eq!(detections.ap_and_recall(), None);
+    }
+
+    #[test]
+    fn detections_match_each_ground_truth_once() {
+        let similarity = |pred: &f64, gt: &f64| 1.0 - (pred - gt).abs();
+        let mut entries = ClassEntries::default();
+        entries.push(
+            &[0.0, 10.0],
+            vec![(0.8, 0.2), (0.6, 10.0), (0.9, 0.1)],
+            100,
+            similarity,
+        );
+
+        let detections = entries.detections(0.5);
+        assert_eq!(detections.results, [(0.9, true), (0.8, false), (0.6, true)]);
+        assert_eq!(detections.num_ground_truths, 2);
+
+        let metrics = entries.metrics([0.5, 0.85]);
+        assert_eq!(metrics.num_predictions, 3);
+        assert!(close(
+            metrics.ap_per_threshold[0].unwrap(),
+            (51.0 + 50.0 * 2.0 / 3.0) / 101.0
+        ));
+        // At 0.85 the prediction at 0.2 is too far from any ground
+        // truth, which leaves the ranking TP, FP, TP unchanged.
+        assert!(close(metrics.ar.unwrap(), 1.0));
+    }
+}
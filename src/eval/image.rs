@@ -0,0 +1,165 @@
+use super::{coco_iou_thresholds, ClassEntries, EvalReport, CONFIDENCE_TAG};
+use crate::{
+    matching::Region, ClassicalDataset, Geometry, ImageAnnotation, MediaAnnotation, MediaKind,
+    Object, Project, Result, Shape,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// The options for evaluating image object detections.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageEvalConfig {
+    /// The name of the tag storing the confidence of a prediction.
+    /// Predictions without the tag have a confidence of 1.
+    pub confidence_tag: String,
+    pub iou_thresholds: Vec<f64>,
+    /// The maximum number of predictions per image and class.
+    pub max_detections: usize,
+}
+
+impl Default for ImageEvalConfig {
+    fn default() -> Self {
+        Self {
+            confidence_tag: CONFIDENCE_TAG.to_string(),
+            iou_thresholds: coco_iou_thresholds(),
+            max_detections: 100,
+        }
+    }
+}
+
+/// Evaluate the predicted objects against the ground truth in the
+/// COCO fashion.
+///
+/// Images are paired by their dataset and image names. Rectangle,
+/// polygon and bitmap objects are evaluated, while other objects are
+/// ignored. Predicted images absent from the ground truth are ignored.
+pub fn evaluate_images(
+    ground_truth: &Project,
+    prediction: &Project,
+    config: &ImageEvalConfig,
+) -> Result<EvalReport> {
+    let mut classes: IndexMap<String, ClassEntries> = ground_truth
+        .meta
+        .classes
+        .iter()
+        .filter(|class| {
+            matches!(
+                class.shape,
+                Shape::Rectangle | Shape::Polygon | Shape::Bitmap
+            )
+        })
+        .map(|class| (class.title.clone(), ClassEntries::default()))
+        .collect();
+
+    let mut dataset_names: Vec<&String> = ground_truth.datasets.keys().collect();
+    dataset_names.sort_unstable();
+
+    let is_image = |dataset: &&dyn ClassicalDataset| dataset.media_kind() == MediaKind::Image;
+    for dataset_name in dataset_names {
+        let gt_dataset = &ground_truth.datasets[dataset_name];
+        if gt_dataset.kind.as_classical().filter(is_image).is_none() {
+            continue;
+        }
+        let pred_dataset = prediction
+            .datasets
+            .get(dataset_name)
+            .and_then(|dataset| dataset.kind.as_classical())
+            .filter(is_image);
+        if pred_dataset.is_none() {
+            warn!("dataset '{dataset_name}' is missing in the predictions");
+        }
+
+        for item in gt_dataset.iter_annotations() {
+            let item = item?;
+            let MediaAnnotation::Image(gt_ann) = &item.annotation else {
+                continue;
+            };
+            let pred_ann = match pred_dataset.and_then(|dataset| dataset.get_media(item.media_name))
+            {
+                Some(media) => match media.ann()? {
+                    MediaAnnotation::Image(ann) => Some(ann),
+                    _ => None,
+                },
+                None => None,
+            };
+            accumulate(gt_ann, pred_ann.as_ref(), config, &mut classes)?;
+        }
+    }
+
+    let classes = classes
+        .into_iter()
+        .map(|(name, entries)| {
+            let metrics = entries.metrics(config.iou_thresholds.iter().copied());
+            (name, metrics)
+        })
+        .collect();
+
+    Ok(EvalReport::new(config.iou_thresholds.clone(), classes))
+}
+
+fn accumulate(
+    gt_ann: &ImageAnnotation,
+    pred_ann: Option<&ImageAnnotation>,
+    config: &ImageEvalConfig,
+    classes: &mut IndexMap<String, ClassEntries>,
+) -> Result<()> {
+    let mut gt_regions: IndexMap<String, Vec<Region>> = IndexMap::new();
+    for obj in gt_ann.objects.iter().filter(|obj| is_area(&obj.geometry)) {
+        if let Some(class) = obj.class_name() {
+            gt_regions
+                .entry(class)
+                .or_default()
+                .push(Region::new(&obj.geometry)?);
+        }
+    }
+
+    let mut pred_regions: IndexMap<String, Vec<(f64, Region)>> = IndexMap::new();
+    let pred_objects = pred_ann.into_iter().flat_map(|ann| &ann.objects);
+    for obj in pred_objects.filter(|obj| is_area(&obj.geometry)) {
+        if let Some(class) = obj.class_name() {
+            let score = confidence(obj, &config.confidence_tag);
+            pred_regions
+                .entry(class)
+                .or_default()
+                .push((score, Region::new(&obj.geometry)?));
+        }
+    }
+
+    for (class, gts) in gt_regions {
+        let preds = pred_regions.shift_remove(&class).unwrap_or_default();
+        classes
+            .entry(class)
+            .or_default()
+            .push(&gts, preds, config.max_detections, Region::iou);
+    }
+
+    // Predictions of classes absent in this image are false positives.
+    for (class, preds) in pred_regions {
+        classes.entry(class).or_default().push::<Region, _, _>(
+            &[],
+            preds,
+            config.max_detections,
+            Region::iou,
+        );
+    }
+
+    Ok(())
+}
+
+fn is_area(geometry: &Geometry) -> bool {
+    matches!(
+        geometry,
+        Geometry::Rectangle(_) | Geometry::Polygon(_) | Geometry::Bitmap(_)
+    )
+}
+
+fn confidence(obj: &Object, tag_name: &str) -> f64 {
+    obj.geometry
+        .tags()
+        .into_iter()
+        .flatten()
+        .find(|tag| tag.name == tag_name)
+        .and_then(|tag| tag.value.as_ref()?.as_f64())
+        .unwrap_or(1.0)
+}
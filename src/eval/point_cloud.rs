@@ -0,0 +1,547 @@
+use super::{ClassEntries, EvalReport, CONFIDENCE_TAG};
+use crate::{
+    Cuboid3DGeometry, DatasetKind, Frame, Geometry, PointCloudAnnotation,
+    PointCloudEpisodeAnnotation, PointCloudGeometry, Project, Result, Shape, Tag,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// The criterion deciding whether a predicted cuboid matches a ground
+/// truth cuboid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchCriterion {
+    /// The IoU of the cuboid volumes.
+    #[default]
+    Iou3D,
+    /// The IoU of the cuboid footprints in the bird's-eye view.
+    BevIou,
+    /// The distance between the cuboid centers on the ground plane.
+    CenterDistance,
+}
+
+/// The options for evaluating 3D object detections.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointCloudEvalConfig {
+    /// The name of the tag storing the confidence of a prediction. The
+    /// tag is looked up on the figure first and then on its object.
+    /// Predictions without the tag have a confidence of 1.
+    pub confidence_tag: String,
+    pub criterion: MatchCriterion,
+    /// The minimum IoU, or the maximum center distance in meters, for a
+    /// prediction to match a ground truth cuboid. The AP is averaged
+    /// over the thresholds.
+    pub thresholds: Vec<f64>,
+    /// The boundaries in meters splitting the distance from the sensor
+    /// into bands. For example, `[30.0, 50.0]` gives the bands
+    /// `[0, 30)`, `[30, 50)` and `[50, inf)`.
+    pub distance_bands: Vec<f64>,
+    /// The maximum number of predictions per sample and class.
+    pub max_detections: usize,
+}
+
+impl Default for PointCloudEvalConfig {
+    fn default() -> Self {
+        Self {
+            confidence_tag: CONFIDENCE_TAG.to_string(),
+            criterion: MatchCriterion::default(),
+            thresholds: vec![0.5],
+            distance_bands: vec![30.0, 50.0],
+            max_detections: 500,
+        }
+    }
+}
+
+/// The evaluation report of 3D detections.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PointCloudEvalReport {
+    /// The metrics over all distances.
+    pub overall: EvalReport,
+    /// The metrics of each distance band.
+    pub bands: Vec<DistanceBandReport>,
+}
+
+/// The metrics within a range of distance from the sensor.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DistanceBandReport {
+    pub min_distance: f64,
+    /// The upper bound of the band, or `None` if unbounded.
+    pub max_distance: Option<f64>,
+    pub report: EvalReport,
+}
+
+/// Evaluate the predicted cuboids against the ground truth.
+///
+/// Point clouds are paired by their dataset and point cloud names, and
+/// episode frames by their dataset names and frame indices. Cuboids are
+/// compared within the same class, and both the ground truth and the
+/// predictions are assigned to distance bands by their own centers.
+pub fn evaluate_point_clouds(
+    ground_truth: &Project,
+    prediction: &Project,
+    config: &PointCloudEvalConfig,
+) -> Result<PointCloudEvalReport> {
+    let mut samples = vec![];
+
+    let mut dataset_names: Vec<&String> = ground_truth.datasets.keys().collect();
+    dataset_names.sort_unstable();
+
+    for dataset_name in dataset_names {
+        let pred_kind = prediction.datasets.get(dataset_name).map(|ds| &ds.kind);
+
+        match (&ground_truth.datasets[dataset_name].kind, pred_kind) {
+            (DatasetKind::PointCloud(gt_dataset), pred_kind) => {
+                let pred_dataset = match pred_kind {
+                    Some(DatasetKind::PointCloud(dataset)) => Some(dataset),
+                    _ => {
+                        warn!("dataset '{dataset_name}' is missing in the predictions");
+                        None
+                    }
+                };
+
+                for name in &gt_dataset.point_cloud_names {
+                    let gt_ann = gt_dataset.get_point_cloud(name).unwrap().ann()?;
+                    let pred_boxes = match pred_dataset.and_then(|ds| ds.get_point_cloud(name)) {
+                        Some(point_cloud) => point_cloud_boxes(&point_cloud.ann()?, config),
+                        None => vec![],
+                    };
+                    samples.push((point_cloud_boxes(&gt_ann, config), pred_boxes));
+                }
+            }
+            (DatasetKind::PointCloudEpisode(gt_dataset), pred_kind) => {
+                let pred_ann = match pred_kind {
+                    Some(DatasetKind::PointCloudEpisode(dataset)) => Some(&dataset.annotation),
+                    _ => {
+                        warn!("dataset '{dataset_name}' is missing in the predictions");
+                        None
+                    }
+                };
+                let gt_ann = &gt_dataset.annotation;
+                let pred_frames: HashMap<u64, &Frame> = pred_ann
+                    .into_iter()
+                    .flat_map(|ann| &ann.frames)
+                    .map(|frame| (frame.index, frame))
+                    .collect();
+
+                for frame in &gt_ann.frames {
+                    let pred_boxes = match (pred_ann, pred_frames.get(&frame.index)) {
+                        (Some(ann), Some(pred_frame)) => frame_boxes(ann, pred_frame, config),
+                        _ => vec![],
+                    };
+                    samples.push((frame_boxes(gt_ann, frame, config), pred_boxes));
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    let class_order: Vec<&str> = ground_truth
+        .meta
+        .classes
+        .iter()
+        .filter(|class| class.shape == Shape::Cuboid3D)
+        .map(|class| class.title.as_str())
+        .collect();
+
+    let overall = evaluate_samples(&samples, &class_order, config, |_| true);
+
+    let mut bounds = vec![0.0];
+    bounds.extend(config.distance_bands.iter().copied());
+    let bands = bounds
+        .iter()
+        .enumerate()
+        .map(|(index, &min_distance)| {
+            let max_distance = bounds.get(index + 1).copied();
+            let in_band = |cuboid: &ScoredBox| cuboid.cuboid.in_band(min_distance, max_distance);
+            DistanceBandReport {
+                min_distance,
+                max_distance,
+                report: evaluate_samples(&samples, &class_order, config, in_band),
+            }
+        })
+        .collect();
+
+    Ok(PointCloudEvalReport { overall, bands })
+}
+
+/// A cuboid in a sample with its class and confidence.
+#[derive(Debug, Clone)]
+struct ScoredBox {
+    class: String,
+    score: f64,
+    cuboid: Box3D,
+}
+
+fn evaluate_samples<F>(
+    samples: &[(Vec<ScoredBox>, Vec<ScoredBox>)],
+    class_order: &[&str],
+    config: &PointCloudEvalConfig,
+    filter: F,
+) -> EvalReport
+where
+    F: Fn(&ScoredBox) -> bool,
+{
+    let mut classes: IndexMap<String, ClassEntries> = class_order
+        .iter()
+        .map(|&class| (class.to_string(), ClassEntries::default()))
+        .collect();
+
+    let criterion = config.criterion;
+    let similarity = |pred: &Box3D, gt: &Box3D| match criterion {
+        MatchCriterion::Iou3D => pred.iou_3d(gt),
+        MatchCriterion::BevIou => pred.iou_bev(gt),
+        MatchCriterion::CenterDistance => -pred.center_distance(gt),
+    };
+
+    for (gts, preds) in samples {
+        let mut gt_groups: IndexMap<&str, Vec<Box3D>> = IndexMap::new();
+        for gt in gts.iter().filter(|gt| filter(gt)) {
+            gt_groups.entry(&gt.class).or_default().push(gt.cuboid);
+        }
+        let mut pred_groups: IndexMap<&str, Vec<(f64, Box3D)>> = IndexMap::new();
+        for pred in preds.iter().filter(|pred| filter(pred)) {
+            pred_groups
+                .entry(&pred.class)
+                .or_default()
+                .push((pred.score, pred.cuboid));
+        }
+
+        for (class, gts) in gt_groups {
+            let preds = pred_groups.shift_remove(class).unwrap_or_default();
+            classes.entry(class.to_string()).or_default().push(
+                &gts,
+                preds,
+                config.max_detections,
+                similarity,
+            );
+        }
+        for (class, preds) in pred_groups {
+            classes.entry(class.to_string()).or_default().push(
+                &[],
+                preds,
+                config.max_detections,
+                similarity,
+            );
+        }
+    }
+
+    // Distances are matched by negated similarity.
+    let thresholds = config.thresholds.iter().map(|&threshold| match criterion {
+        MatchCriterion::CenterDistance => -threshold,
+        _ => threshold,
+    });
+    let thresholds: Vec<f64> = thresholds.collect();
+
+    let classes = classes
+        .into_iter()
+        .map(|(name, entries)| {
+            let metrics = entries.metrics(thresholds.iter().copied());
+            (name, metrics)
+        })
+        .collect();
+
+    EvalReport::new(config.thresholds.clone(), classes)
+}
+
+fn point_cloud_boxes(ann: &PointCloudAnnotation, config: &PointCloudEvalConfig) -> Vec<ScoredBox> {
+    ann.figures
+        .iter()
+        .filter(|figure| figure.geometry_type == Shape::Cuboid3D)
+        .filter_map(|figure| {
+            let Some(obj) = ann.objects.iter().find(|obj| obj.key == figure.object_key) else {
+                warn!("figure '{}' refers to an unknown object", figure.key);
+                return None;
+            };
+            Some(ScoredBox {
+                class: obj.class_title.clone(),
+                score: confidence(&obj.tags, &config.confidence_tag).unwrap_or(1.0),
+                cuboid: Box3D::from(&figure.geometry),
+            })
+        })
+        .collect()
+}
+
+fn frame_boxes(
+    ann: &PointCloudEpisodeAnnotation,
+    frame: &Frame,
+    config: &PointCloudEvalConfig,
+) -> Vec<ScoredBox> {
+    frame
+        .figures
+        .iter()
+        .filter_map(|figure| {
+            let Geometry::Cuboid3D(cuboid) = &figure.geometry else {
+                return None;
+            };
+            let obj = ann.objects.iter().find(|obj| obj.key == figure.object_key);
+            let Some(class) = figure
+                .class_title
+                .clone()
+                .or_else(|| Some(obj?.class_title.clone()))
+            else {
+                warn!("figure '{}' refers to an unknown object", figure.key);
+                return None;
+            };
+
+            let score = cuboid
+                .tags
+                .as_deref()
+                .and_then(|tags| confidence(tags, &config.confidence_tag))
+                .or_else(|| confidence(&obj?.tags, &config.confidence_tag))
+                .unwrap_or(1.0);
+
+            Some(ScoredBox {
+                class,
+                score,
+                cuboid: Box3D::from(cuboid),
+            })
+        })
+        .collect()
+}
+
+fn confidence(tags: &[Tag], tag_name: &str) -> Option<f64> {
+    tags.iter()
+        .find(|tag| tag.name == tag_name)
+        .and_then(|tag| tag.value.as_ref()?.as_f64())
+}
+
+/// A cuboid rotated around the vertical axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Box3D {
+    center: [f64; 3],
+    dimensions: [f64; 3],
+    yaw: f64,
+}
+
+impl From<&PointCloudGeometry> for Box3D {
+    fn from(geometry: &PointCloudGeometry) -> Self {
+        let PointCloudGeometry {
+            position,
+            rotation,
+            dimensions,
+        } = geometry;
+        Self {
+            center: [position.x, position.y, position.z],
+            dimensions: [dimensions.x, dimensions.y, dimensions.z],
+            yaw: rotation.z,
+        }
+    }
+}
+
+impl From<&Cuboid3DGeometry> for Box3D {
+    fn from(geometry: &Cuboid3DGeometry) -> Self {
+        let Cuboid3DGeometry {
+            position,
+            rotation,
+            dimensions,
+            ..
+        } = geometry;
+        Self {
+            center: [position.x.raw(), position.y.raw(), position.z.raw()],
+            dimensions: [dimensions.x.raw(), dimensions.y.raw(), dimensions.z.raw()],
+            yaw: rotation.z.raw(),
+        }
+    }
+}
+
+impl Box3D {
+    /// The distance from the sensor on the ground plane.
+    fn range(&self) -> f64 {
+        self.center[0].hypot(self.center[1])
+    }
+
+    /// Check if the range lies in `[min_distance, max_distance)`.
+    fn in_band(&self, min_distance: f64, max_distance: Option<f64>) -> bool {
+        let range = self.range();
+        range >= min_distance && max_distance.is_none_or(|max| range < max)
+    }
+
+    fn center_distance(&self, other: &Self) -> f64 {
+        (self.center[0] - other.center[0]).hypot(self.center[1] - other.center[1])
+    }
+
+    /// The footprint corners in counter-clockwise order.
+    fn footprint(&self) -> [(f64, f64); 4] {
+        let [cx, cy, _] = self.center;
+        let [length, width, _] = self.dimensions;
+        let (sin, cos) = self.yaw.sin_cos();
+        [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)].map(|(sx, sy)| {
+            let (dx, dy) = (sx * length / 2.0, sy * width / 2.0);
+            (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+        })
+    }
+
+    fn bev_intersection(&self, other: &Self) -> f64 {
+        let clipped = clip_convex(&self.footprint(), &other.footprint());
+        polygon_area(&clipped)
+    }
+
+    fn iou_bev(&self, other: &Self) -> f64 {
+        let intersection = self.bev_intersection(other);
+        let area = |cuboid: &Self| cuboid.dimensions[0] * cuboid.dimensions[1];
+        let union = area(self) + area(other) - intersection;
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+
+    fn iou_3d(&self, other: &Self) -> f64 {
+        let bottom = |cuboid: &Self| cuboid.center[2] - cuboid.dimensions[2] / 2.0;
+        let top = |cuboid: &Self| cuboid.center[2] + cuboid.dimensions[2] / 2.0;
+        let overlap = (top(self).min(top(other)) - bottom(self).max(bottom(other))).max(0.0);
+
+        let intersection = self.bev_intersection(other) * overlap;
+        let volume = |cuboid: &Self| cuboid.dimensions.iter().product::<f64>();
+        let union = volume(self) + volume(other) - intersection;
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Clip a convex polygon by another convex polygon in counter-clockwise
+/// order using the Sutherland-Hodgman algorithm.
+fn clip_convex(subject: &[(f64, f64)], clip: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut output = subject.to_vec();
+
+    for (&(ax, ay), &(bx, by)) in clip.iter().zip(clip.iter().cycle().skip(1)) {
+        if output.is_empty() {
+            break;
+        }
+        let input = std::mem::take(&mut output);
+        let side = |(x, y): (f64, f64)| (bx - ax) * (y - ay) - (by - ay) * (x - ax);
+
+        for (&curr, &next) in input.iter().zip(input.iter().cycle().skip(1)) {
+            let (curr_side, next_side) = (side(curr), side(next));
+            if curr_side >= 0.0 {
+                output.push(curr);
+            }
+            if (curr_side >= 0.0) != (next_side >= 0.0) {
+                let t = curr_side / (curr_side - next_side);
+                output.push((
+                    curr.0 + t * (next.0 - curr.0),
+                    curr.1 + t * (next.1 - curr.1),
+                ));
+            }
+        }
+    }
+
+    output
+}
+
+fn polygon_area(points: &[(f64, f64)]) -> f64 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(&(x1, y1), &(x2, y2))| x1 * y2 - x2 * y1)
+        .sum::<f64>()
+        .abs()
+        / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_PI_4, SQRT_2};
+
+    fn cuboid(center: [f64; 3], dimensions: [f64; 3], yaw: f64) -> Box3D {
+        Box3D {
+            center,
+            dimensions,
+            yaw,
+        }
+    }
+
+    fn close(lhs: f64, rhs: f64) -> bool {
+        (lhs - rhs).abs() < 1e-9
+    }
+
+    #[test]
+    fn iou_of_identical_cuboids() {
+        let lhs = cuboid([5.0, 3.0, 1.0], [4.0, 2.0, 1.5], 0.3);
+        assert!(close(lhs.iou_3d(&lhs), 1.0));
+        assert!(close(lhs.iou_bev(&lhs), 1.0));
+    }
+
+    #[test]
+    fn iou_of_disjoint_cuboids() {
+        let lhs = cuboid([0.0, 0.0, 0.0], [2.0, 2.0, 2.0], 0.0);
+        let rhs = cuboid([5.0, 0.0, 0.0], [2.0, 2.0, 2.0], FRAC_PI_4);
+        assert_eq!(lhs.iou_3d(&rhs), 0.0);
+        assert_eq!(lhs.iou_bev(&rhs), 0.0);
+
+        // Stacked cuboids share the footprint but not the volume.
+        let above = cuboid([0.0, 0.0, 3.0], [2.0, 2.0, 2.0], 0.0);
+        assert!(close(lhs.iou_bev(&above), 1.0));
+        assert_eq!(lhs.iou_3d(&above), 0.0);
+    }
+
+    #[test]
+    fn bev_iou_of_rotated_square() {
+        // The 2x2 square and its copy rotated by 45 degrees overlap in a
+        // regular octagon, which is the square minus four corner
+        // triangles with legs of 2 - sqrt(2).
+        let lhs = cuboid([0.0, 0.0, 0.0], [2.0, 2.0, 1.0], 0.0);
+        let rhs = cuboid([0.0, 0.0, 0.0], [2.0, 2.0, 1.0], FRAC_PI_4);
+        let intersection = 4.0 - 2.0 * (2.0 - SQRT_2).powi(2);
+
+        assert!(close(lhs.bev_intersection(&rhs), intersection));
+        assert!(close(
+            lhs.iou_bev(&rhs),
+            intersection / (8.0 - intersection)
+        ));
+    }
+
+    #[test]
+    fn iou_3d_with_partial_height_overlap() {
+        // The cuboids share the footprint and half of their height.
+        let lhs = cuboid([0.0, 0.0, 0.0], [2.0, 2.0, 2.0], 0.0);
+        let rhs = cuboid([0.0, 0.0, 1.0], [2.0, 2.0, 2.0], 0.0);
+        assert!(close(lhs.iou_3d(&rhs), 4.0 / 12.0));
+    }
+
+    #[test]
+    fn band_boundary_belongs_to_the_upper_band() {
+        let on_boundary = cuboid([18.0, 24.0, 0.0], [1.0, 1.0, 1.0], 0.0);
+        assert!(close(on_boundary.range(), 30.0));
+        assert!(!on_boundary.in_band(0.0, Some(30.0)));
+        assert!(on_boundary.in_band(30.0, Some(50.0)));
+        assert!(on_boundary.in_band(30.0, None));
+    }
+
+    #[test]
+    fn center_distance_threshold_is_an_upper_bound() {
+        let scored = |x: f64| ScoredBox {
+            class: "car".to_string(),
+            score: 0.9,
+            cuboid: cuboid([x, 0.0, 0.0], [4.0, 2.0, 1.5], 0.0),
+        };
+        let samples = vec![
+            (vec![scored(10.0)], vec![scored(11.5)]),
+            (vec![scored(20.0)], vec![scored(22.5)]),
+        ];
+        let config = PointCloudEvalConfig {
+            criterion: MatchCriterion::CenterDistance,
+            thresholds: vec![2.0, 3.0],
+            ..Default::default()
+        };
+
+        let report = evaluate_samples(&samples, &["car"], &config, |_| true);
+        let metrics = &report.classes["car"];
+        assert_eq!(report.thresholds, [2.0, 3.0]);
+
+        // Within 2 meters only the first prediction matches, while both
+        // match within 3 meters.
+        let [Some(ap_near), Some(ap_far)] = metrics.ap_per_threshold[..] else {
+            panic!("the class has ground truth");
+        };
+        assert!(ap_near < 1.0);
+        assert!(close(ap_far, 1.0));
+    }
+}
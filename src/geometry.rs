@@ -1,3 +1,12 @@
+mod build;
+mod mask;
+mod ops;
+mod raster;
+
+pub use mask::Mask;
+pub use ops::{BoundingBox, Geometry2D};
+pub(crate) use raster::rasterize;
+
 use crate::{tags::Tag, Error, Result};
 use base64::prelude::*;
 use flate2::read::ZlibDecoder;
@@ -5,7 +14,7 @@ use noisy_float::types::R64;
 use serde::{Deserialize, Serialize};
 use std::io::{prelude::*, Cursor};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Shape {
     Rectangle,
@@ -37,6 +46,33 @@ pub enum Geometry {
     Cuboid3D(Cuboid3DGeometry),
 }
 
+impl Geometry {
+    /// Get the shape of the geometry as declared in the project meta.
+    pub fn shape(&self) -> Shape {
+        match self {
+            Geometry::Point(_) => Shape::Point,
+            Geometry::Rectangle(_) => Shape::Rectangle,
+            Geometry::Polygon(_) => Shape::Polygon,
+            Geometry::Polyline(_) => Shape::Line,
+            Geometry::Bitmap(_) => Shape::Bitmap,
+            Geometry::Cuboid3D(_) => Shape::Cuboid3D,
+        }
+    }
+
+    /// Get the tags attached to the geometry.
+    pub fn tags(&self) -> Option<&[Tag]> {
+        let tags = match self {
+            Geometry::Point(point) => &point.tags,
+            Geometry::Rectangle(rect) => &rect.tags,
+            Geometry::Polygon(polygon) => &polygon.tags,
+            Geometry::Polyline(polyline) => &polyline.tags,
+            Geometry::Bitmap(bitmap) => &bitmap.tags,
+            Geometry::Cuboid3D(cuboid3d) => &cuboid3d.tags,
+        };
+        tags.as_deref()
+    }
+}
+
 impl From<Cuboid3DGeometry> for Geometry {
     fn from(v: Cuboid3DGeometry) -> Self {
         Self::Cuboid3D(v)
@@ -73,16 +109,6 @@ impl From<PointGeometry> for Geometry {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct SerializedGeometry {
-    pub geometry_type: GeometryType,
-    pub tags: Option<Vec<Tag>>,
-    pub points: Option<Points>,
-    pub shape: Option<Shape>,
-    pub bitmap: Option<Bitmap>,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PointGeometry {
@@ -128,10 +154,14 @@ pub struct Cuboid3DGeometry {
     pub dimensions: Xyz,
 }
 
+/// The vertices of a geometry.
+///
+/// `exterior` is the outer contour and `interior` is the list of holes,
+/// each of which is a contour on its own.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Points {
     pub exterior: Vec<(R64, R64)>,
-    pub interior: Vec<(R64, R64)>,
+    pub interior: Vec<Vec<(R64, R64)>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
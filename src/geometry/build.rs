@@ -0,0 +1,114 @@
+use super::{
+    Bitmap, BitmapGeometry, Cuboid3DGeometry, Geometry, Mask, PointGeometry, Points,
+    PolygonGeometry, PolylineGeometry, RectangleGeometry, Xyz,
+};
+use crate::{Result, Shape};
+use noisy_float::types::{r64, R64};
+
+// The constructors panic on NaN coordinates, as `r64` does.
+
+impl Points {
+    /// Create points without holes from `f64` coordinates.
+    pub fn from_f64<I>(exterior: I) -> Self
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        Self {
+            exterior: to_r64(exterior),
+            interior: vec![],
+        }
+    }
+
+    /// Add a hole given by `f64` coordinates.
+    pub fn with_interior<I>(mut self, interior: I) -> Self
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        self.interior.push(to_r64(interior));
+        self
+    }
+}
+
+impl Xyz {
+    pub fn from_f64(x: f64, y: f64, z: f64) -> Self {
+        Self {
+            x: r64(x),
+            y: r64(y),
+            z: r64(z),
+        }
+    }
+}
+
+impl Geometry {
+    /// Create a point geometry.
+    pub fn point(x: f64, y: f64) -> Self {
+        PointGeometry {
+            tags: None,
+            points: Points::from_f64([(x, y)]),
+        }
+        .into()
+    }
+
+    /// Create a rectangle from two opposite corners.
+    pub fn rectangle(x1: f64, y1: f64, x2: f64, y2: f64) -> Self {
+        RectangleGeometry {
+            tags: None,
+            points: Points::from_f64([(x1.min(x2), y1.min(y2)), (x1.max(x2), y1.max(y2))]),
+        }
+        .into()
+    }
+
+    /// Create a polygon without holes.
+    pub fn polygon<I>(exterior: I) -> Self
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        Self::polygon_with_points(Points::from_f64(exterior))
+    }
+
+    /// Create a polygon from points, which may include holes.
+    pub fn polygon_with_points(points: Points) -> Self {
+        PolygonGeometry { tags: None, points }.into()
+    }
+
+    /// Create a polyline.
+    pub fn polyline<I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        PolylineGeometry {
+            tags: None,
+            points: Points::from_f64(points),
+        }
+        .into()
+    }
+
+    /// Create a bitmap from a mask placed at `origin`.
+    pub fn bitmap(mask: &Mask, origin: [u64; 2]) -> Result<Self> {
+        Ok(BitmapGeometry {
+            tags: None,
+            bitmap: Bitmap::from_mask(mask, origin)?,
+            shape: Some(Shape::Bitmap),
+        }
+        .into())
+    }
+
+    /// Create a 3D cuboid from its center, rotation angles and size.
+    pub fn cuboid_3d(position: [f64; 3], rotation: [f64; 3], dimensions: [f64; 3]) -> Self {
+        let xyz = |[x, y, z]: [f64; 3]| Xyz::from_f64(x, y, z);
+        Cuboid3DGeometry {
+            tags: None,
+            position: xyz(position),
+            rotation: xyz(rotation),
+            dimensions: xyz(dimensions),
+        }
+        .into()
+    }
+}
+
+fn to_r64<I>(points: I) -> Vec<(R64, R64)>
+where
+    I: IntoIterator<Item = (f64, f64)>,
+{
+    points.into_iter().map(|(x, y)| (r64(x), r64(y))).collect()
+}
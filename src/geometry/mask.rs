@@ -0,0 +1,126 @@
+use super::Bitmap;
+use crate::{Error, Result};
+use base64::prelude::*;
+use flate2::{write::ZlibEncoder, Compression};
+use png::{BitDepth, ColorType, Transformations};
+use std::io::{prelude::*, Cursor};
+
+/// A binary mask in row-major order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<bool>,
+}
+
+impl Mask {
+    /// Create an empty mask.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![false; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.data[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: bool) {
+        self.data[y * self.width + x] = value;
+    }
+
+    /// Count the number of pixels set in the mask.
+    pub fn count(&self) -> usize {
+        self.data.iter().filter(|&&value| value).count()
+    }
+
+    /// Iterate over the coordinates of the pixels set in the mask.
+    pub fn iter_set(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value)
+            .map(|(index, _)| (index % self.width, index / self.width))
+    }
+
+    /// Decode a mask from a PNG image. A pixel is set if its alpha value
+    /// is non-zero for images with alpha, or any channel is non-zero
+    /// otherwise.
+    pub fn decode_png(bytes: &[u8]) -> Result<Self> {
+        let mut decoder = png::Decoder::new(Cursor::new(bytes));
+        decoder.set_transformations(Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|_| Error::DecodeDataError)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buf)
+            .map_err(|_| Error::DecodeDataError)?;
+
+        let channels = info.color_type.samples();
+        let has_alpha = matches!(info.color_type, ColorType::GrayscaleAlpha | ColorType::Rgba);
+        let width = info.width as usize;
+        let height = info.height as usize;
+
+        let data = (0..height)
+            .flat_map(|y| {
+                let row = &buf[y * info.line_size..][..width * channels];
+                row.chunks_exact(channels).map(|pixel| {
+                    if has_alpha {
+                        pixel[channels - 1] != 0
+                    } else {
+                        pixel.iter().any(|&value| value != 0)
+                    }
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Encode the mask as a palette PNG image where unset pixels are
+    /// transparent, following the Supervisely convention.
+    pub fn encode_png(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(ColorType::Indexed);
+        encoder.set_depth(BitDepth::Eight);
+        encoder.set_palette(vec![0, 0, 0, 255, 255, 255]);
+        encoder.set_trns(vec![0, 255]);
+
+        let pixels: Vec<u8> = self.data.iter().map(|&value| value as u8).collect();
+        let mut writer = encoder.write_header().map_err(|_| Error::EncodeDataError)?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|_| Error::EncodeDataError)?;
+        writer.finish().map_err(|_| Error::EncodeDataError)?;
+
+        Ok(bytes)
+    }
+}
+
+impl Bitmap {
+    /// Decode the mask image stored in the bitmap.
+    pub fn decode_mask(&self) -> Result<Mask> {
+        Mask::decode_png(&self.decode_data()?)
+    }
+
+    /// Encode a mask into a bitmap placed at `origin`.
+    pub fn from_mask(mask: &Mask, origin: [u64; 2]) -> Result<Self> {
+        let png_bytes = mask.encode_png()?;
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder
+            .write_all(&png_bytes)
+            .map_err(|_| Error::EncodeDataError)?;
+        let zlib_bytes = encoder.finish().map_err(|_| Error::EncodeDataError)?;
+
+        Ok(Self {
+            data_encoded: BASE64_STANDARD.encode(zlib_bytes),
+            origin,
+        })
+    }
+}
@@ -0,0 +1,508 @@
+use super::{
+    BitmapGeometry, Geometry, PointGeometry, Points, PolygonGeometry, PolylineGeometry,
+    RectangleGeometry,
+};
+use crate::{Error, Result};
+use noisy_float::types::R64;
+use serde::{Deserialize, Serialize};
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    /// Compute the bounding box of a set of points.
+    pub fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        points.into_iter().fold(None, |bbox, (x, y)| {
+            let bbox = bbox.unwrap_or(Self {
+                min_x: x,
+                min_y: y,
+                max_x: x,
+                max_y: y,
+            });
+            Some(Self {
+                min_x: bbox.min_x.min(x),
+                min_y: bbox.min_y.min(y),
+                max_x: bbox.max_x.max(x),
+                max_y: bbox.max_y.max(y),
+            })
+        })
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width() * self.height()
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.min_x + self.max_x) / 2.0,
+            (self.min_y + self.max_y) / 2.0,
+        )
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        (self.min_x..=self.max_x).contains(&x) && (self.min_y..=self.max_y).contains(&y)
+    }
+
+    /// Compute the overlapping box, or `None` if the boxes are disjoint.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let bbox = Self {
+            min_x: self.min_x.max(other.min_x),
+            min_y: self.min_y.max(other.min_y),
+            max_x: self.max_x.min(other.max_x),
+            max_y: self.max_y.min(other.max_y),
+        };
+        (bbox.min_x <= bbox.max_x && bbox.min_y <= bbox.max_y).then_some(bbox)
+    }
+
+    /// Compute the smallest box enclosing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+}
+
+/// Measurements on planar geometries in pixel coordinates.
+///
+/// Bitmaps are measured on their decoded masks, where each pixel
+/// covers a unit square. The methods fail if the bitmap cannot be
+/// decoded, or if the geometry is not planar.
+pub trait Geometry2D {
+    /// The axis-aligned bounding box. Returns `None` for an empty
+    /// geometry.
+    fn bbox(&self) -> Result<Option<BoundingBox>>;
+
+    /// The area covered by the geometry. Points and polylines have zero
+    /// area.
+    fn area(&self) -> Result<f64>;
+
+    /// The center of mass. Returns `None` for an empty geometry.
+    fn centroid(&self) -> Result<Option<(f64, f64)>>;
+
+    /// The perimeter of an area, or the length of a polyline.
+    fn perimeter(&self) -> Result<f64>;
+
+    /// Check if the point lies inside the geometry.
+    fn contains(&self, x: f64, y: f64) -> Result<bool>;
+}
+
+impl Geometry2D for PointGeometry {
+    fn bbox(&self) -> Result<Option<BoundingBox>> {
+        Ok(BoundingBox::from_points(ring(&self.points.exterior)))
+    }
+
+    fn area(&self) -> Result<f64> {
+        Ok(0.0)
+    }
+
+    fn centroid(&self) -> Result<Option<(f64, f64)>> {
+        Ok(ring(&self.points.exterior).next())
+    }
+
+    fn perimeter(&self) -> Result<f64> {
+        Ok(0.0)
+    }
+
+    fn contains(&self, x: f64, y: f64) -> Result<bool> {
+        Ok(ring(&self.points.exterior).any(|point| point == (x, y)))
+    }
+}
+
+impl Geometry2D for RectangleGeometry {
+    fn bbox(&self) -> Result<Option<BoundingBox>> {
+        Ok(BoundingBox::from_points(ring(&self.points.exterior)))
+    }
+
+    fn area(&self) -> Result<f64> {
+        Ok(self.bbox()?.map(|bbox| bbox.area()).unwrap_or(0.0))
+    }
+
+    fn centroid(&self) -> Result<Option<(f64, f64)>> {
+        Ok(self.bbox()?.map(|bbox| bbox.center()))
+    }
+
+    fn perimeter(&self) -> Result<f64> {
+        Ok(self
+            .bbox()?
+            .map(|bbox| 2.0 * (bbox.width() + bbox.height()))
+            .unwrap_or(0.0))
+    }
+
+    fn contains(&self, x: f64, y: f64) -> Result<bool> {
+        Ok(self.bbox()?.is_some_and(|bbox| bbox.contains(x, y)))
+    }
+}
+
+impl Geometry2D for PolygonGeometry {
+    fn bbox(&self) -> Result<Option<BoundingBox>> {
+        Ok(BoundingBox::from_points(ring(&self.points.exterior)))
+    }
+
+    fn area(&self) -> Result<f64> {
+        let Points { exterior, interior } = &self.points;
+        let holes: f64 = interior
+            .iter()
+            .map(|hole| ring_signed_area(hole).abs())
+            .sum();
+        Ok((ring_signed_area(exterior).abs() - holes).max(0.0))
+    }
+
+    fn centroid(&self) -> Result<Option<(f64, f64)>> {
+        let Points { exterior, interior } = &self.points;
+
+        // Accumulate the area moments with holes subtracted.
+        let rings = [(exterior, 1.0)]
+            .into_iter()
+            .chain(interior.iter().map(|hole| (hole, -1.0)));
+        let (mut area, mut mx, mut my) = (0.0, 0.0, 0.0);
+        for (points, sign) in rings {
+            let (ring_area, (cx, cy)) = ring_moments(points);
+            let ring_area = ring_area.abs() * sign;
+            area += ring_area;
+            mx += ring_area * cx;
+            my += ring_area * cy;
+        }
+
+        if area.abs() > f64::EPSILON {
+            return Ok(Some((mx / area, my / area)));
+        }
+
+        // Fall back to the vertex mean for degenerate polygons.
+        let count = exterior.len() as f64;
+        let (sx, sy) = ring(exterior).fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        Ok((count > 0.0).then(|| (sx / count, sy / count)))
+    }
+
+    fn perimeter(&self) -> Result<f64> {
+        let Points { exterior, interior } = &self.points;
+        let holes: f64 = interior.iter().map(|hole| ring_length(hole, true)).sum();
+        Ok(ring_length(exterior, true) + holes)
+    }
+
+    fn contains(&self, x: f64, y: f64) -> Result<bool> {
+        let Points { exterior, interior } = &self.points;
+        Ok(ring_contains(exterior, x, y) && !interior.iter().any(|hole| ring_contains(hole, x, y)))
+    }
+}
+
+impl Geometry2D for PolylineGeometry {
+    fn bbox(&self) -> Result<Option<BoundingBox>> {
+        Ok(BoundingBox::from_points(ring(&self.points.exterior)))
+    }
+
+    fn area(&self) -> Result<f64> {
+        Ok(0.0)
+    }
+
+    fn centroid(&self) -> Result<Option<(f64, f64)>> {
+        let points: Vec<_> = ring(&self.points.exterior).collect();
+
+        // Weight the midpoint of each segment by its length.
+        let (mut length, mut mx, mut my) = (0.0, 0.0, 0.0);
+        for pair in points.windows(2) {
+            let [(x1, y1), (x2, y2)] = [pair[0], pair[1]];
+            let segment = (x2 - x1).hypot(y2 - y1);
+            length += segment;
+            mx += segment * (x1 + x2) / 2.0;
+            my += segment * (y1 + y2) / 2.0;
+        }
+
+        if length > 0.0 {
+            Ok(Some((mx / length, my / length)))
+        } else {
+            Ok(points.first().copied())
+        }
+    }
+
+    fn perimeter(&self) -> Result<f64> {
+        Ok(ring_length(&self.points.exterior, false))
+    }
+
+    fn contains(&self, x: f64, y: f64) -> Result<bool> {
+        let points: Vec<_> = ring(&self.points.exterior).collect();
+        let found = points.windows(2).any(|pair| {
+            let [(x1, y1), (x2, y2)] = [pair[0], pair[1]];
+            let cross = (x2 - x1) * (y - y1) - (y2 - y1) * (x - x1);
+            let within =
+                (x1.min(x2)..=x1.max(x2)).contains(&x) && (y1.min(y2)..=y1.max(y2)).contains(&y);
+            within && cross.abs() <= 1e-9 * (x2 - x1).hypot(y2 - y1).max(1.0)
+        });
+        Ok(found || points.first() == Some(&(x, y)))
+    }
+}
+
+impl Geometry2D for BitmapGeometry {
+    fn bbox(&self) -> Result<Option<BoundingBox>> {
+        let mask = self.bitmap.decode_mask()?;
+        let [ox, oy] = self.bitmap.origin;
+        let bbox = BoundingBox::from_points(
+            mask.iter_set()
+                .flat_map(|(x, y)| [(x as f64, y as f64), (x as f64 + 1.0, y as f64 + 1.0)]),
+        )
+        .map(|bbox| BoundingBox {
+            min_x: bbox.min_x + ox as f64,
+            min_y: bbox.min_y + oy as f64,
+            max_x: bbox.max_x + ox as f64,
+            max_y: bbox.max_y + oy as f64,
+        });
+        Ok(bbox)
+    }
+
+    fn area(&self) -> Result<f64> {
+        Ok(self.bitmap.decode_mask()?.count() as f64)
+    }
+
+    fn centroid(&self) -> Result<Option<(f64, f64)>> {
+        let mask = self.bitmap.decode_mask()?;
+        let [ox, oy] = self.bitmap.origin;
+
+        let (count, sx, sy) = mask
+            .iter_set()
+            .fold((0usize, 0.0, 0.0), |(n, sx, sy), (x, y)| {
+                (n + 1, sx + x as f64 + 0.5, sy + y as f64 + 0.5)
+            });
+        let count = count as f64;
+        Ok((count > 0.0).then(|| (ox as f64 + sx / count, oy as f64 + sy / count)))
+    }
+
+    fn perimeter(&self) -> Result<f64> {
+        let mask = self.bitmap.decode_mask()?;
+
+        // Count the pixel edges between set and unset pixels.
+        let edges: usize = mask
+            .iter_set()
+            .map(|(x, y)| {
+                let neighbors = [
+                    x == 0 || !mask.get(x - 1, y),
+                    !mask.get(x + 1, y),
+                    y == 0 || !mask.get(x, y - 1),
+                    !mask.get(x, y + 1),
+                ];
+                neighbors.into_iter().filter(|&exposed| exposed).count()
+            })
+            .sum();
+        Ok(edges as f64)
+    }
+
+    fn contains(&self, x: f64, y: f64) -> Result<bool> {
+        let [ox, oy] = self.bitmap.origin;
+        let (x, y) = ((x - ox as f64).floor(), (y - oy as f64).floor());
+        if x < 0.0 || y < 0.0 {
+            return Ok(false);
+        }
+        let mask = self.bitmap.decode_mask()?;
+        Ok(mask.get(x as usize, y as usize))
+    }
+}
+
+impl Geometry2D for Geometry {
+    fn bbox(&self) -> Result<Option<BoundingBox>> {
+        self.as_2d()?.bbox()
+    }
+
+    fn area(&self) -> Result<f64> {
+        self.as_2d()?.area()
+    }
+
+    fn centroid(&self) -> Result<Option<(f64, f64)>> {
+        self.as_2d()?.centroid()
+    }
+
+    fn perimeter(&self) -> Result<f64> {
+        self.as_2d()?.perimeter()
+    }
+
+    fn contains(&self, x: f64, y: f64) -> Result<bool> {
+        self.as_2d()?.contains(x, y)
+    }
+}
+
+impl Geometry {
+    /// View the geometry as a planar geometry. Fails on 3D cuboids.
+    pub fn as_2d(&self) -> Result<&dyn Geometry2D> {
+        let geometry: &dyn Geometry2D = match self {
+            Geometry::Point(point) => point,
+            Geometry::Rectangle(rect) => rect,
+            Geometry::Polygon(polygon) => polygon,
+            Geometry::Polyline(polyline) => polyline,
+            Geometry::Bitmap(bitmap) => bitmap,
+            Geometry::Cuboid3D(_) => return Err(Error::Expect2DGeometry),
+        };
+        Ok(geometry)
+    }
+}
+
+pub(crate) fn ring(points: &[(R64, R64)]) -> impl Iterator<Item = (f64, f64)> + Clone + '_ {
+    points.iter().map(|&(x, y)| (x.raw(), y.raw()))
+}
+
+/// Compute the signed area of a closed ring by the shoelace formula.
+pub(crate) fn ring_signed_area(points: &[(R64, R64)]) -> f64 {
+    ring_moments(points).0
+}
+
+/// Compute the signed area and the centroid of a closed ring.
+fn ring_moments(points: &[(R64, R64)]) -> (f64, (f64, f64)) {
+    let (mut area, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for ((x1, y1), (x2, y2)) in ring(points).zip(ring(points).cycle().skip(1)) {
+        let cross = x1 * y2 - x2 * y1;
+        area += cross;
+        cx += (x1 + x2) * cross;
+        cy += (y1 + y2) * cross;
+    }
+    let area = area / 2.0;
+
+    if area == 0.0 {
+        (0.0, (0.0, 0.0))
+    } else {
+        (area, (cx / (6.0 * area), cy / (6.0 * area)))
+    }
+}
+
+fn ring_length(points: &[(R64, R64)], closed: bool) -> f64 {
+    let segments = points.len().saturating_sub(if closed { 0 } else { 1 });
+    ring(points)
+        .zip(ring(points).cycle().skip(1))
+        .take(segments)
+        .map(|((x1, y1), (x2, y2))| (x2 - x1).hypot(y2 - y1))
+        .sum()
+}
+
+/// Test if a point is inside a closed ring by the even-odd rule.
+pub(crate) fn ring_contains(points: &[(R64, R64)], x: f64, y: f64) -> bool {
+    ring(points)
+        .zip(ring(points).cycle().skip(1))
+        .filter(|&((x1, y1), (x2, y2))| {
+            (y1 > y) != (y2 > y) && x < x1 + (y - y1) * (x2 - x1) / (y2 - y1)
+        })
+        .count()
+        % 2
+        == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bitmap, Mask};
+    use noisy_float::types::r64;
+
+    fn ring_of(points: &[(f64, f64)]) -> Vec<(R64, R64)> {
+        points.iter().map(|&(x, y)| (r64(x), r64(y))).collect()
+    }
+
+    fn square_with_hole() -> PolygonGeometry {
+        PolygonGeometry {
+            tags: None,
+            points: Points {
+                exterior: ring_of(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+                interior: vec![ring_of(&[(2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (2.0, 4.0)])],
+            },
+        }
+    }
+
+    fn close(lhs: f64, rhs: f64) -> bool {
+        (lhs - rhs).abs() < 1e-9
+    }
+
+    #[test]
+    fn polygon_with_hole_measurements() {
+        let polygon = square_with_hole();
+        assert!(close(polygon.area().unwrap(), 96.0));
+        assert!(close(polygon.perimeter().unwrap(), 48.0));
+
+        // The square contributes (5, 5) with weight 100 and the hole
+        // removes (3, 3) with weight 4.
+        let (cx, cy) = polygon.centroid().unwrap().unwrap();
+        assert!(close(cx, 488.0 / 96.0) && close(cy, 488.0 / 96.0));
+
+        let bbox = polygon.bbox().unwrap().unwrap();
+        assert_eq!(
+            (bbox.min_x, bbox.min_y, bbox.max_x, bbox.max_y),
+            (0.0, 0.0, 10.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn polygon_hole_is_not_contained() {
+        let polygon = square_with_hole();
+        assert!(polygon.contains(7.0, 7.0).unwrap());
+        assert!(!polygon.contains(3.0, 3.0).unwrap());
+        assert!(!polygon.contains(11.0, 5.0).unwrap());
+    }
+
+    #[test]
+    fn bitmap_measurements() {
+        // An L-shaped mask of 3 pixels placed at (10, 20).
+        let mut mask = Mask::new(2, 2);
+        mask.set(0, 0, true);
+        mask.set(1, 0, true);
+        mask.set(0, 1, true);
+        let bitmap = BitmapGeometry {
+            tags: None,
+            bitmap: Bitmap::from_mask(&mask, [10, 20]).unwrap(),
+            shape: None,
+        };
+
+        assert!(close(bitmap.area().unwrap(), 3.0));
+        assert!(close(bitmap.perimeter().unwrap(), 8.0));
+
+        let (cx, cy) = bitmap.centroid().unwrap().unwrap();
+        assert!(close(cx, 10.0 + 2.5 / 3.0) && close(cy, 20.0 + 2.5 / 3.0));
+
+        let bbox = bitmap.bbox().unwrap().unwrap();
+        assert_eq!(
+            (bbox.min_x, bbox.min_y, bbox.max_x, bbox.max_y),
+            (10.0, 20.0, 12.0, 22.0)
+        );
+
+        assert!(bitmap.contains(10.5, 21.5).unwrap());
+        assert!(!bitmap.contains(11.5, 21.5).unwrap());
+        assert!(!bitmap.contains(9.5, 20.5).unwrap());
+    }
+
+    #[test]
+    fn deserialize_points_with_and_without_holes() {
+        let polygon: PolygonGeometry = serde_json::from_str(
+            r#"{
+                "points": {
+                    "exterior": [[0, 0], [10, 0], [10, 10], [0, 10]],
+                    "interior": [[[2, 2], [4, 2], [4, 4], [2, 4]]]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(polygon, square_with_hole());
+
+        let polygon: PolygonGeometry = serde_json::from_str(
+            r#"{
+                "points": {
+                    "exterior": [[0, 0], [10, 0], [10, 10], [0, 10]],
+                    "interior": []
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(polygon.points.interior.is_empty());
+        assert!(close(polygon.area().unwrap(), 100.0));
+    }
+}
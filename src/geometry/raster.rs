@@ -0,0 +1,459 @@
+use super::{
+    ops::{ring, ring_contains, ring_signed_area},
+    Bitmap, BitmapGeometry, Geometry, Geometry2D, Mask, Points, PolygonGeometry, Shape,
+};
+use crate::{Error, Result};
+use noisy_float::types::{r64, R64};
+use std::collections::BTreeMap;
+
+impl PolygonGeometry {
+    /// Rasterize the polygon into a bitmap with a tight origin.
+    ///
+    /// A pixel is set if its center lies inside the exterior and outside
+    /// every hole. Returns `None` if the polygon covers no pixel center.
+    pub fn to_bitmap(&self) -> Result<Option<BitmapGeometry>> {
+        let Some((mask, origin)) = rasterize(&polygon_rings(&self.points)) else {
+            return Ok(None);
+        };
+
+        Ok(Some(BitmapGeometry {
+            tags: self.tags.clone(),
+            bitmap: Bitmap::from_mask(&mask, origin)?,
+            shape: Some(Shape::Bitmap),
+        }))
+    }
+}
+
+impl Geometry {
+    /// Rasterize an area geometry into a mask and its origin. Returns
+    /// `None` if the geometry covers no pixel center.
+    pub fn to_mask(&self) -> Result<Option<(Mask, [u64; 2])>> {
+        let rings: Vec<Vec<(f64, f64)>> = match self {
+            Geometry::Rectangle(rect) => {
+                let Some(bbox) = rect.bbox()? else {
+                    return Ok(None);
+                };
+                vec![vec![
+                    (bbox.min_x, bbox.min_y),
+                    (bbox.max_x, bbox.min_y),
+                    (bbox.max_x, bbox.max_y),
+                    (bbox.min_x, bbox.max_y),
+                ]]
+            }
+            Geometry::Polygon(polygon) => polygon_rings(&polygon.points),
+            Geometry::Bitmap(bitmap) => {
+                let mask = bitmap.bitmap.decode_mask()?;
+                return Ok(Some((mask, bitmap.bitmap.origin)));
+            }
+            Geometry::Point(_) | Geometry::Polyline(_) | Geometry::Cuboid3D(_) => {
+                return Err(Error::ExpectAreaGeometry)
+            }
+        };
+        Ok(rasterize(&rings))
+    }
+}
+
+impl BitmapGeometry {
+    /// Trace the bitmap into polygons along the pixel boundaries.
+    ///
+    /// Each 4-connected region becomes a polygon, with the enclosed
+    /// background regions as its holes. If `tolerance` is given, the
+    /// contours are simplified by the Douglas-Peucker algorithm with
+    /// that maximum deviation in pixels.
+    pub fn to_polygons(&self, tolerance: Option<f64>) -> Result<Vec<PolygonGeometry>> {
+        let mask = self.bitmap.decode_mask()?;
+        let [ox, oy] = self.bitmap.origin;
+
+        let mut outers = vec![];
+        let mut holes = vec![];
+        for contour in trace_contours(&mask) {
+            let points: Vec<(R64, R64)> = contour
+                .vertices
+                .iter()
+                .map(|&(x, y)| (r64((x + ox as i64) as f64), r64((y + oy as i64) as f64)))
+                .collect();
+            let area = ring_signed_area(&points);
+            let (px, py) = contour.probe;
+            let probe = (px + ox as f64, py + oy as f64);
+
+            if area > 0.0 {
+                outers.push((points, area, vec![]));
+            } else {
+                holes.push((points, probe));
+            }
+        }
+
+        // Assign each hole to the smallest outer contour enclosing it.
+        for (hole, (px, py)) in holes {
+            let parent = outers
+                .iter_mut()
+                .filter(|(outer, _, _)| ring_contains(outer, px, py))
+                .min_by(|(_, lhs, _), (_, rhs, _)| lhs.total_cmp(rhs));
+            if let Some((_, _, parent_holes)) = parent {
+                parent_holes.push(hole);
+            }
+        }
+
+        let polygons = outers
+            .into_iter()
+            .map(|(exterior, _, interior)| {
+                let simplify = |points: Vec<(R64, R64)>| match tolerance {
+                    Some(tolerance) => simplify_ring(points, tolerance),
+                    None => points,
+                };
+                PolygonGeometry {
+                    tags: self.tags.clone(),
+                    points: Points {
+                        exterior: simplify(exterior),
+                        interior: interior.into_iter().map(simplify).collect(),
+                    },
+                }
+            })
+            .collect();
+
+        Ok(polygons)
+    }
+}
+
+impl Mask {
+    /// Crop the mask to the bounding box of the pixels set. Returns the
+    /// cropped mask and its offset, or `None` if the mask is empty.
+    pub fn crop_to_content(&self) -> Option<(Mask, [usize; 2])> {
+        let (mut min_x, mut min_y) = (usize::MAX, usize::MAX);
+        let (mut max_x, mut max_y) = (0, 0);
+        for (x, y) in self.iter_set() {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        if min_x == usize::MAX {
+            return None;
+        }
+
+        let mut cropped = Mask::new(max_x - min_x + 1, max_y - min_y + 1);
+        for (x, y) in self.iter_set() {
+            cropped.set(x - min_x, y - min_y, true);
+        }
+        Some((cropped, [min_x, min_y]))
+    }
+}
+
+fn polygon_rings(points: &Points) -> Vec<Vec<(f64, f64)>> {
+    let Points { exterior, interior } = points;
+    [exterior]
+        .into_iter()
+        .chain(interior)
+        .map(|points| ring(points).collect())
+        .collect()
+}
+
+/// Rasterize rings by the even-odd rule, sampling at pixel centers.
+/// Returns the tight mask and its origin, or `None` if no pixel is
+/// covered. Pixels at negative coordinates are discarded.
+pub(crate) fn rasterize(rings: &[Vec<(f64, f64)>]) -> Option<(Mask, [u64; 2])> {
+    let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+    let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for &(x, y) in rings.iter().flatten() {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+
+    // The range of pixels whose centers fall in the bounding box.
+    let x0 = (min_x - 0.5).ceil().max(0.0);
+    let y0 = (min_y - 0.5).ceil().max(0.0);
+    let x1 = (max_x - 0.5).floor();
+    let y1 = (max_y - 0.5).floor();
+    if !(x0 <= x1 && y0 <= y1) {
+        return None;
+    }
+    let (x0, y0) = (x0 as usize, y0 as usize);
+    let mut mask = Mask::new(x1 as usize - x0 + 1, y1 as usize - y0 + 1);
+
+    let mut crossings = vec![];
+    for row in 0..mask.height {
+        let yc = (y0 + row) as f64 + 0.5;
+
+        crossings.clear();
+        for points in rings {
+            let edges = points.iter().zip(points.iter().cycle().skip(1));
+            for (&(ax, ay), &(bx, by)) in edges {
+                if (ay > yc) != (by > yc) {
+                    crossings.push(ax + (yc - ay) * (bx - ax) / (by - ay));
+                }
+            }
+        }
+        crossings.sort_by(f64::total_cmp);
+
+        for span in crossings.chunks_exact(2) {
+            let begin = ((span[0] - 0.5).ceil() - x0 as f64).max(0.0) as usize;
+            let end = ((span[1] - 0.5).ceil() - x0 as f64).max(0.0) as usize;
+            for col in begin..end.min(mask.width) {
+                mask.set(col, row, true);
+            }
+        }
+    }
+
+    let (mask, [dx, dy]) = mask.crop_to_content()?;
+    Some((mask, [(x0 + dx) as u64, (y0 + dy) as u64]))
+}
+
+/// A closed contour along pixel boundaries.
+struct Contour {
+    vertices: Vec<(i64, i64)>,
+    /// The center of a set pixel next to the contour.
+    probe: (f64, f64),
+}
+
+/// Trace the boundaries of a mask into closed contours. Outer contours
+/// have positive signed area in image coordinates and holes have
+/// negative signed area.
+fn trace_contours(mask: &Mask) -> Vec<Contour> {
+    // Collect the pixel edges facing the background, oriented so that
+    // every set pixel is traversed clockwise on the screen.
+    let mut edges: BTreeMap<(i64, i64), Vec<(i64, i64)>> = BTreeMap::new();
+    for (x, y) in mask.iter_set() {
+        let (x, y) = (x as i64, y as i64);
+        let is_unset = |dx: i64, dy: i64| {
+            let (nx, ny) = (x + dx, y + dy);
+            nx < 0 || ny < 0 || !mask.get(nx as usize, ny as usize)
+        };
+        let sides = [
+            ((0, -1), (x, y), (x + 1, y)),
+            ((1, 0), (x + 1, y), (x + 1, y + 1)),
+            ((0, 1), (x + 1, y + 1), (x, y + 1)),
+            ((-1, 0), (x, y + 1), (x, y)),
+        ];
+        for ((dx, dy), from, to) in sides {
+            if is_unset(dx, dy) {
+                edges.entry(from).or_default().push(to);
+            }
+        }
+    }
+
+    let mut contours = vec![];
+    while let Some(&start) = edges.keys().next() {
+        let first = take_edge(&mut edges, start, |_| true);
+        let (dx, dy) = (first.0 - start.0, first.1 - start.1);
+
+        // The owner pixel lies on the right-hand side of the edge.
+        let probe = (
+            start.0 as f64 + (dx - dy) as f64 / 2.0,
+            start.1 as f64 + (dy + dx) as f64 / 2.0,
+        );
+
+        let mut vertices = vec![start];
+        let mut prev = start;
+        let mut curr = first;
+        while curr != start {
+            vertices.push(curr);
+            let (dx, dy) = (curr.0 - prev.0, curr.1 - prev.1);
+
+            // Prefer right turns to keep diagonal pixels apart.
+            let right = (curr.0 - dy, curr.1 + dx);
+            let next = take_edge(&mut edges, curr, |to| to == right);
+            prev = curr;
+            curr = next;
+        }
+
+        contours.push(Contour {
+            vertices: remove_collinear(vertices),
+            probe,
+        });
+    }
+
+    contours
+}
+
+/// Remove an edge starting at `from`, choosing the preferred target if
+/// there are several.
+fn take_edge<F>(
+    edges: &mut BTreeMap<(i64, i64), Vec<(i64, i64)>>,
+    from: (i64, i64),
+    prefer: F,
+) -> (i64, i64)
+where
+    F: Fn((i64, i64)) -> bool,
+{
+    let targets = edges
+        .get_mut(&from)
+        .expect("pixel boundaries must be closed");
+    let index = targets.iter().position(|&to| prefer(to)).unwrap_or(0);
+    let to = targets.swap_remove(index);
+    if targets.is_empty() {
+        edges.remove(&from);
+    }
+    to
+}
+
+fn remove_collinear(vertices: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    let len = vertices.len();
+    (0..len)
+        .filter(|&index| {
+            let (px, py) = vertices[(index + len - 1) % len];
+            let (cx, cy) = vertices[index];
+            let (nx, ny) = vertices[(index + 1) % len];
+            (cx - px) * (ny - cy) != (cy - py) * (nx - cx)
+        })
+        .map(|index| vertices[index])
+        .collect()
+}
+
+/// Simplify a closed ring by the Douglas-Peucker algorithm. The ring is
+/// kept unchanged if it would degenerate.
+fn simplify_ring(points: Vec<(R64, R64)>, tolerance: f64) -> Vec<(R64, R64)> {
+    if points.len() <= 3 {
+        return points;
+    }
+    let coords: Vec<(f64, f64)> = ring(&points).collect();
+
+    // Split the ring at the vertex farthest from the first one.
+    let (x0, y0) = coords[0];
+    let split = (1..coords.len())
+        .max_by(|&lhs, &rhs| {
+            let dist = |index: usize| (coords[index].0 - x0).hypot(coords[index].1 - y0);
+            dist(lhs).total_cmp(&dist(rhs))
+        })
+        .unwrap();
+
+    let mut keep = vec![false; coords.len()];
+    keep[0] = true;
+    keep[split] = true;
+    let mut closed = coords.clone();
+    closed.push(coords[0]);
+    douglas_peucker(&closed, 0, split, tolerance, &mut keep);
+    douglas_peucker(&closed, split, coords.len(), tolerance, &mut keep);
+
+    let simplified: Vec<_> = points
+        .iter()
+        .zip(&keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(&point, _)| point)
+        .collect();
+
+    if simplified.len() < 3 {
+        points
+    } else {
+        simplified
+    }
+}
+
+fn douglas_peucker(
+    points: &[(f64, f64)],
+    begin: usize,
+    end: usize,
+    tolerance: f64,
+    keep: &mut [bool],
+) {
+    if end <= begin + 1 {
+        return;
+    }
+    let (ax, ay) = points[begin];
+    let (bx, by) = points[end];
+    let len = (bx - ax).hypot(by - ay);
+
+    let distance = |(x, y): (f64, f64)| {
+        if len == 0.0 {
+            (x - ax).hypot(y - ay)
+        } else {
+            ((bx - ax) * (ay - y) - (ax - x) * (by - ay)).abs() / len
+        }
+    };
+    let (index, max_dist) = (begin + 1..end)
+        .map(|index| (index, distance(points[index])))
+        .max_by(|(_, lhs), (_, rhs)| lhs.total_cmp(rhs))
+        .unwrap();
+
+    if max_dist > tolerance {
+        keep[index] = true;
+        douglas_peucker(points, begin, index, tolerance, keep);
+        douglas_peucker(points, index, end, tolerance, keep);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn polygon(exterior: &[(f64, f64)], interior: &[&[(f64, f64)]]) -> PolygonGeometry {
+        PolygonGeometry {
+            tags: None,
+            points: Points {
+                exterior: exterior.iter().map(|&(x, y)| (r64(x), r64(y))).collect(),
+                interior: interior
+                    .iter()
+                    .map(|ring| ring.iter().map(|&(x, y)| (r64(x), r64(y))).collect())
+                    .collect(),
+            },
+        }
+    }
+
+    fn vertex_set(points: &[(R64, R64)]) -> HashSet<(i64, i64)> {
+        points
+            .iter()
+            .map(|&(x, y)| (x.raw() as i64, y.raw() as i64))
+            .collect()
+    }
+
+    #[test]
+    fn rectangle_round_trip() {
+        let rect = [(2.0, 3.0), (6.0, 3.0), (6.0, 5.0), (2.0, 5.0)];
+        let bitmap = polygon(&rect, &[]).to_bitmap().unwrap().unwrap();
+        assert_eq!(bitmap.bitmap.origin, [2, 3]);
+        let mask = bitmap.bitmap.decode_mask().unwrap();
+        assert_eq!((mask.width, mask.height, mask.count()), (4, 2, 8));
+
+        let polygons = bitmap.to_polygons(None).unwrap();
+        assert_eq!(polygons.len(), 1);
+        assert!(polygons[0].points.interior.is_empty());
+        assert_eq!(
+            vertex_set(&polygons[0].points.exterior),
+            HashSet::from([(2, 3), (6, 3), (6, 5), (2, 5)])
+        );
+    }
+
+    #[test]
+    fn polygon_with_hole_round_trip() {
+        let exterior = [(0.0, 0.0), (6.0, 0.0), (6.0, 6.0), (0.0, 6.0)];
+        let hole = [(2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (2.0, 4.0)];
+        let bitmap = polygon(&exterior, &[&hole]).to_bitmap().unwrap().unwrap();
+        assert_eq!(bitmap.bitmap.decode_mask().unwrap().count(), 32);
+
+        let polygons = bitmap.to_polygons(None).unwrap();
+        assert_eq!(polygons.len(), 1);
+        let points = &polygons[0].points;
+        assert_eq!(
+            vertex_set(&points.exterior),
+            HashSet::from([(0, 0), (6, 0), (6, 6), (0, 6)])
+        );
+        assert_eq!(points.interior.len(), 1);
+        assert_eq!(
+            vertex_set(&points.interior[0]),
+            HashSet::from([(2, 2), (4, 2), (4, 4), (2, 4)])
+        );
+
+        // Rasterizing the traced polygon gives back the same bitmap.
+        let traced = polygons[0].to_bitmap().unwrap().unwrap();
+        assert_eq!(traced.bitmap.origin, bitmap.bitmap.origin);
+        assert_eq!(
+            traced.bitmap.decode_mask().unwrap(),
+            bitmap.bitmap.decode_mask().unwrap()
+        );
+    }
+
+    #[test]
+    fn diagonal_pixels_are_separate_contours() {
+        let mut mask = Mask::new(2, 2);
+        mask.set(0, 0, true);
+        mask.set(1, 1, true);
+
+        let contours = trace_contours(&mask);
+        assert_eq!(contours.len(), 2);
+        for contour in contours {
+            assert_eq!(contour.vertices.len(), 4);
+        }
+    }
+}
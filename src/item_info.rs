@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// The folder holding the user metadata of each media.
+pub const ITEM_META_DIR: &str = "meta";
+
+/// The custom metadata of a media, stored in `meta/<name>.json` or in
+/// the `meta` field of the item info.
+pub type ItemMeta = Map<String, Value>;
+
+/// The item info of a media as exported from the platform, stored in
+/// `img_info`, `video_info` or `pointcloud_info`.
+///
+/// Fields not modelled here are kept in `extra`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemInfo {
+    /// The id of the media on the platform.
+    pub id: Option<usize>,
+    pub name: Option<String>,
+    pub link: Option<String>,
+    pub hash: Option<String>,
+    pub mime: Option<String>,
+    pub ext: Option<String>,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub labels_count: Option<usize>,
+    pub dataset_id: Option<usize>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub path_original: Option<String>,
+    pub full_storage_url: Option<String>,
+    pub meta: Option<ItemMeta>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
@@ -0,0 +1,275 @@
+use crate::{IdAllocator, KeyIdMap, MediaAnnotation, Tag};
+use std::collections::{HashMap, HashSet};
+
+/// The file name of the key-id map in a project directory.
+pub const KEY_ID_MAP_FILE: &str = "key_id_map.json";
+
+/// The kinds of entities identified by keys in video and point cloud
+/// annotations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyKind {
+    Tag,
+    Object,
+    Figure,
+    Video,
+}
+
+impl KeyKind {
+    pub const ALL: [Self; 4] = [Self::Tag, Self::Object, Self::Figure, Self::Video];
+}
+
+impl KeyIdMap {
+    /// Get the ids of a kind of entities keyed by their keys.
+    pub fn ids(&self, kind: KeyKind) -> &HashMap<String, usize> {
+        match kind {
+            KeyKind::Tag => &self.tags,
+            KeyKind::Object => &self.objects,
+            KeyKind::Figure => &self.figures,
+            KeyKind::Video => &self.videos,
+        }
+    }
+
+    pub fn ids_mut(&mut self, kind: KeyKind) -> &mut HashMap<String, usize> {
+        match kind {
+            KeyKind::Tag => &mut self.tags,
+            KeyKind::Object => &mut self.objects,
+            KeyKind::Figure => &mut self.figures,
+            KeyKind::Video => &mut self.videos,
+        }
+    }
+
+    /// Resolve the id of a key.
+    pub fn id(&self, kind: KeyKind, key: &str) -> Option<usize> {
+        self.ids(kind).get(key).copied()
+    }
+
+    /// Resolve the key of an id.
+    ///
+    /// The map keeps no reverse index, so this scans all keys of the
+    /// kind in linear time. Use [`Self::inverse`] to resolve many ids.
+    pub fn key(&self, kind: KeyKind, id: usize) -> Option<&str> {
+        self.ids(kind)
+            .iter()
+            .find(|(_, &other)| other == id)
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Get the keys of a kind of entities indexed by their ids.
+    pub fn inverse(&self, kind: KeyKind) -> HashMap<usize, &str> {
+        self.ids(kind)
+            .iter()
+            .map(|(key, &id)| (id, key.as_str()))
+            .collect()
+    }
+
+    /// Build a map covering exactly the given keys.
+    ///
+    /// Known keys keep their ids unless the id is taken by another key,
+    /// and the other keys are given ids past all known ids. Each id is
+    /// thus unique within its kind.
+    pub fn regenerate<'a, I>(&self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = (KeyKind, &'a str)>,
+    {
+        let mut allocators = KeyKind::ALL.map(|kind| {
+            let mut ids = IdAllocator::new();
+            for &id in self.ids(kind).values() {
+                ids.reserve(id);
+            }
+            ids
+        });
+        let mut used: [HashSet<usize>; 4] = Default::default();
+        let mut map = Self::default();
+
+        for (kind, key) in keys {
+            let index = kind as usize;
+            if map.ids(kind).contains_key(key) {
+                continue;
+            }
+
+            let id = match self.id(kind, key) {
+                Some(id) if !used[index].contains(&id) => id,
+                _ => allocators[index].next_id(),
+            };
+            used[index].insert(id);
+            map.ids_mut(kind).insert(key.to_string(), id);
+        }
+
+        map
+    }
+}
+
+impl MediaAnnotation {
+    /// Get the keys of the video, tags, objects and figures in the
+    /// annotation.
+    pub fn keys(&self) -> Vec<(KeyKind, &str)> {
+        let mut keys = vec![];
+
+        match self {
+            MediaAnnotation::Image(ann) => {
+                keys.extend(tag_keys(ann.tags.iter().flatten()));
+                for obj in &ann.objects {
+                    keys.extend(tag_keys(obj.geometry.tags().into_iter().flatten()));
+                }
+            }
+            MediaAnnotation::Video(ann) => {
+                keys.push((KeyKind::Video, ann.key.as_str()));
+                keys.extend(tag_keys(&ann.tags));
+                for obj in &ann.objects {
+                    keys.push((KeyKind::Object, obj.key.as_str()));
+                    keys.extend(tag_keys(obj.tags.iter().flatten()));
+                }
+                for frame in &ann.frames {
+                    keys.extend(
+                        frame
+                            .figures
+                            .iter()
+                            .map(|fig| (KeyKind::Figure, fig.key.as_str())),
+                    );
+                }
+            }
+            MediaAnnotation::PointCloud(ann) => {
+                keys.extend(ann.key.as_deref().map(|key| (KeyKind::Video, key)));
+                keys.extend(tag_keys(&ann.tags));
+                for obj in &ann.objects {
+                    keys.push((KeyKind::Object, obj.key.as_str()));
+                    keys.extend(tag_keys(&obj.tags));
+                }
+                keys.extend(
+                    ann.figures
+                        .iter()
+                        .map(|fig| (KeyKind::Figure, fig.key.as_str())),
+                );
+            }
+            MediaAnnotation::PointCloudEpisode(ann) => {
+                keys.extend(ann.key.as_deref().map(|key| (KeyKind::Video, key)));
+                keys.extend(tag_keys(&ann.tags));
+                for obj in &ann.objects {
+                    keys.push((KeyKind::Object, obj.key.as_str()));
+                    keys.extend(tag_keys(&obj.tags));
+                }
+                for frame in &ann.frames {
+                    keys.extend(
+                        frame
+                            .figures
+                            .iter()
+                            .map(|fig| (KeyKind::Figure, fig.key.as_str())),
+                    );
+                }
+            }
+        }
+
+        keys
+    }
+}
+
+fn tag_keys<'a, I>(tags: I) -> impl Iterator<Item = (KeyKind, &'a str)>
+where
+    I: IntoIterator<Item = &'a Tag>,
+{
+    tags.into_iter()
+        .filter_map(|tag| Some((KeyKind::Tag, tag.key.as_deref()?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Figure, Frame, Geometry, MemoryStorage, Project, Size, VideoAnnotation, VideoObject,
+    };
+    use std::{path::Path, sync::Arc};
+
+    fn video_object(key: &str) -> VideoObject {
+        VideoObject {
+            key: key.to_string(),
+            class_title: Some("car".to_string()),
+            tags: None,
+            labeler_login: None,
+        }
+    }
+
+    fn figure(key: &str, object_key: &str) -> Figure {
+        Figure {
+            key: key.to_string(),
+            object_key: object_key.to_string(),
+            geometry: Geometry::point(1.0, 1.0),
+            class_title: None,
+            labeler_login: None,
+        }
+    }
+
+    #[test]
+    fn regenerate_and_reload_key_id_map() {
+        let tag = Tag {
+            key: Some("tag-a".to_string()),
+            ..Tag::new("quality".to_string(), "good")
+        };
+        let ann = VideoAnnotation {
+            size: Size {
+                width: 10,
+                height: 10,
+            },
+            key: "video".to_string(),
+            tags: vec![tag],
+            objects: vec![video_object("obj-a"), video_object("obj-b")],
+            frames: vec![Frame {
+                index: 0,
+                figures: vec![figure("fig-a", "obj-a"), figure("fig-b", "obj-b")],
+            }],
+            frames_count: 1,
+            ..VideoAnnotation::default()
+        };
+
+        let storage = Arc::new(MemoryStorage::new());
+        storage
+            .insert("meta.json", r#"{"classes": [], "tags": []}"#)
+            .unwrap();
+        storage
+            .insert(
+                KEY_ID_MAP_FILE,
+                r#"{
+                    "tags": {},
+                    "objects": {"obj-a": 7, "stale": 8},
+                    "figures": {"fig-a": 3},
+                    "videos": {"video": 1}
+                }"#,
+            )
+            .unwrap();
+        storage.insert("ds/video/a.mp4", []).unwrap();
+        storage
+            .insert("ds/ann/a.mp4.json", serde_json::to_vec(&ann).unwrap())
+            .unwrap();
+
+        let mut project = Project::open_with(storage.clone(), Path::new("")).unwrap();
+        let map = project.regenerate_key_id_map().unwrap().clone();
+        project.save_key_id_map().unwrap();
+
+        let ids = |kind| {
+            let mut ids: Vec<(&str, usize)> = map
+                .ids(kind)
+                .iter()
+                .map(|(key, &id)| (key.as_str(), id))
+                .collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(ids(KeyKind::Tag), [("tag-a", 1)]);
+        assert_eq!(ids(KeyKind::Object), [("obj-a", 7), ("obj-b", 9)]);
+        assert_eq!(ids(KeyKind::Figure), [("fig-a", 3), ("fig-b", 4)]);
+        assert_eq!(ids(KeyKind::Video), [("video", 1)]);
+
+        let reloaded = Project::open_with(storage, Path::new("")).unwrap();
+        let reloaded = reloaded.key_id_map.unwrap();
+        assert_eq!(reloaded, map);
+
+        for kind in KeyKind::ALL {
+            let inverse = reloaded.inverse(kind);
+            assert_eq!(inverse.len(), reloaded.ids(kind).len());
+            for (key, &id) in reloaded.ids(kind) {
+                assert_eq!(inverse[&id], key);
+                assert_eq!(reloaded.key(kind, id), Some(key.as_str()));
+            }
+        }
+        assert_eq!(reloaded.key(KeyKind::Object, 8), None);
+    }
+}
@@ -0,0 +1,471 @@
+use crate::{
+    utils::load_json, Bitmap, BitmapGeometry, Error, Geometry, ImageAnnotation, Object,
+    PointGeometry, Points, PolygonGeometry, PolylineGeometry, RectangleGeometry, Result, Shape,
+    Size,
+};
+use base64::prelude::*;
+use flate2::{write::ZlibEncoder, Compression};
+use indexmap::IndexMap;
+use noisy_float::types::{r64, R64};
+use serde::{Deserialize, Serialize};
+use std::{f64::consts::PI, io::prelude::*, path::Path};
+use tracing::warn;
+
+/// The number of vertices used to approximate a LabelMe circle as a polygon.
+const CIRCLE_VERTICES: usize = 32;
+
+/// The annotation file produced by LabelMe.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelMeAnnotation {
+    pub version: String,
+    pub flags: Option<IndexMap<String, bool>>,
+    pub shapes: Vec<LabelMeShape>,
+    pub image_path: String,
+    pub image_data: Option<String>,
+    pub image_height: u64,
+    pub image_width: u64,
+}
+
+/// A labeled shape in a LabelMe annotation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelMeShape {
+    pub label: String,
+    pub points: Vec<[f64; 2]>,
+    pub group_id: Option<i64>,
+    pub description: Option<String>,
+    pub shape_type: LabelMeShapeType,
+    pub flags: Option<IndexMap<String, bool>>,
+    /// The base64 encoded PNG mask of a mask shape, cropped to the box
+    /// spanned by its two points.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<String>,
+}
+
+/// The shape types supported by LabelMe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelMeShapeType {
+    Rectangle,
+    Polygon,
+    Linestrip,
+    Line,
+    Point,
+    Points,
+    Circle,
+    Mask,
+}
+
+impl LabelMeAnnotation {
+    /// Load a LabelMe annotation from a JSON file.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        load_json(path)
+    }
+
+    /// Convert to a Supervisely image annotation.
+    ///
+    /// Shape labels become object class titles and object ids are
+    /// assigned in the order of the shapes. Circles are approximated by
+    /// polygons and masks become bitmaps. Point sets have no Supervisely
+    /// counterpart and fail the conversion.
+    pub fn to_image_annotation(&self) -> Result<ImageAnnotation> {
+        let objects: Vec<_> = self
+            .shapes
+            .iter()
+            .enumerate()
+            .map(|(id, shape)| -> Result<_> {
+                Ok(Object {
+                    id,
+                    class_id: None,
+                    class_title: Some(shape.label.clone()),
+                    labeler_login: None,
+                    created_at: None,
+                    updated_at: None,
+                    geometry: shape.to_geometry()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let name = Path::new(&self.image_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.image_path)
+            .to_string();
+
+        Ok(ImageAnnotation {
+            name,
+            description: None,
+            size: Size {
+                width: self.image_width,
+                height: self.image_height,
+            },
+            tags: None,
+            objects,
+        })
+    }
+
+    /// Convert a Supervisely image annotation to a LabelMe annotation.
+    ///
+    /// Bitmap and cuboid objects have no LabelMe counterpart and are
+    /// skipped. Polygon holes are dropped.
+    pub fn from_image_annotation(ann: &ImageAnnotation) -> Self {
+        let shapes = ann
+            .objects
+            .iter()
+            .filter_map(|obj| {
+                let Some(label) = obj.class_name() else {
+                    warn!("skip object {} without a class", obj.id);
+                    return None;
+                };
+                LabelMeShape::from_geometry(label, &obj.geometry)
+            })
+            .collect();
+
+        Self {
+            version: "5.0.1".to_string(),
+            flags: Some(IndexMap::new()),
+            shapes,
+            image_path: ann.name.clone(),
+            image_data: None,
+            image_height: ann.size.height,
+            image_width: ann.size.width,
+        }
+    }
+}
+
+impl LabelMeShape {
+    /// Convert the shape to a Supervisely geometry.
+    pub fn to_geometry(&self) -> Result<Geometry> {
+        let Self {
+            label,
+            points,
+            shape_type,
+            mask,
+            ..
+        } = self;
+
+        let geometry = match shape_type {
+            LabelMeShapeType::Rectangle => {
+                let [[x1, y1], [x2, y2]] = points.as_slice() else {
+                    return Err(Error::invalid_labelme_shape(
+                        label,
+                        "a rectangle must have exactly 2 points",
+                    ));
+                };
+                RectangleGeometry {
+                    tags: None,
+                    points: exterior_points([
+                        [x1.min(*x2), y1.min(*y2)],
+                        [x1.max(*x2), y1.max(*y2)],
+                    ]),
+                }
+                .into()
+            }
+            LabelMeShapeType::Polygon => {
+                if points.len() < 3 {
+                    return Err(Error::invalid_labelme_shape(
+                        label,
+                        "a polygon must have at least 3 points",
+                    ));
+                }
+                PolygonGeometry {
+                    tags: None,
+                    points: exterior_points(points.iter().copied()),
+                }
+                .into()
+            }
+            LabelMeShapeType::Linestrip | LabelMeShapeType::Line => {
+                if points.len() < 2 {
+                    return Err(Error::invalid_labelme_shape(
+                        label,
+                        "a line must have at least 2 points",
+                    ));
+                }
+                PolylineGeometry {
+                    tags: None,
+                    points: exterior_points(points.iter().copied()),
+                }
+                .into()
+            }
+            LabelMeShapeType::Point => {
+                let [point] = points.as_slice() else {
+                    return Err(Error::invalid_labelme_shape(
+                        label,
+                        "a point must have exactly 1 point",
+                    ));
+                };
+                PointGeometry {
+                    tags: None,
+                    points: exterior_points([*point]),
+                }
+                .into()
+            }
+            LabelMeShapeType::Circle => {
+                let [[cx, cy], [px, py]] = points.as_slice() else {
+                    return Err(Error::invalid_labelme_shape(
+                        label,
+                        "a circle must have exactly 2 points",
+                    ));
+                };
+                let radius = (px - cx).hypot(py - cy);
+                let vertices = (0..CIRCLE_VERTICES).map(|index| {
+                    let angle = 2.0 * PI * index as f64 / CIRCLE_VERTICES as f64;
+                    [cx + radius * angle.cos(), cy + radius * angle.sin()]
+                });
+                PolygonGeometry {
+                    tags: None,
+                    points: exterior_points(vertices),
+                }
+                .into()
+            }
+            LabelMeShapeType::Points => {
+                return Err(Error::invalid_labelme_shape(
+                    label,
+                    "point sets are not supported by Supervisely",
+                ));
+            }
+            LabelMeShapeType::Mask => {
+                let (Some(mask), [[x, y], _]) = (mask, points.as_slice()) else {
+                    return Err(Error::invalid_labelme_shape(
+                        label,
+                        "a mask must have mask data and exactly 2 points",
+                    ));
+                };
+                let png_bytes = BASE64_STANDARD.decode(mask).map_err(|_| {
+                    Error::invalid_labelme_shape(label, "the mask data is not valid base64")
+                })?;
+                let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+                let zlib_bytes = encoder
+                    .write_all(&png_bytes)
+                    .and_then(|()| encoder.finish())
+                    .map_err(|_| {
+                        Error::invalid_labelme_shape(label, "cannot compress the mask data")
+                    })?;
+
+                BitmapGeometry {
+                    tags: None,
+                    bitmap: Bitmap {
+                        data_encoded: BASE64_STANDARD.encode(zlib_bytes),
+                        origin: [*x as u64, *y as u64],
+                    },
+                    shape: Some(Shape::Bitmap),
+                }
+                .into()
+            }
+        };
+
+        Ok(geometry)
+    }
+
+    /// Build a LabelMe shape from a Supervisely geometry. Returns `None`
+    /// if the geometry cannot be represented in LabelMe.
+    pub fn from_geometry(label: String, geometry: &Geometry) -> Option<Self> {
+        let (shape_type, points) = match geometry {
+            Geometry::Point(point) => (LabelMeShapeType::Point, &point.points),
+            Geometry::Rectangle(rect) => (LabelMeShapeType::Rectangle, &rect.points),
+            Geometry::Polygon(polygon) => {
+                if !polygon.points.interior.is_empty() {
+                    warn!(
+                        "drop the holes of polygon '{label}' since LabelMe does not support them"
+                    );
+                }
+                (LabelMeShapeType::Polygon, &polygon.points)
+            }
+            Geometry::Polyline(polyline) => (LabelMeShapeType::Linestrip, &polyline.points),
+            Geometry::Bitmap(_) | Geometry::Cuboid3D(_) => {
+                warn!("skip object '{label}' whose geometry is not supported by LabelMe");
+                return None;
+            }
+        };
+
+        let points = points
+            .exterior
+            .iter()
+            .map(|&(x, y)| [x.raw(), y.raw()])
+            .collect();
+
+        Some(Self {
+            label,
+            points,
+            group_id: None,
+            description: None,
+            shape_type,
+            flags: Some(IndexMap::new()),
+            mask: None,
+        })
+    }
+}
+
+fn exterior_points<I>(points: I) -> Points
+where
+    I: IntoIterator<Item = [f64; 2]>,
+{
+    let exterior: Vec<(R64, R64)> = points.into_iter().map(|[x, y]| (r64(x), r64(y))).collect();
+    Points {
+        exterior,
+        interior: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_shape(shape_type: &str, points: &str) -> LabelMeShape {
+        serde_json::from_str(&format!(
+            r#"{{
+                "label": "car",
+                "points": {points},
+                "group_id": null,
+                "description": "",
+                "shape_type": "{shape_type}",
+                "flags": {{}}
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    fn exterior(geometry: &Geometry) -> Vec<(f64, f64)> {
+        let points = match geometry {
+            Geometry::Point(point) => &point.points,
+            Geometry::Rectangle(rect) => &rect.points,
+            Geometry::Polygon(polygon) => &polygon.points,
+            Geometry::Polyline(polyline) => &polyline.points,
+            _ => panic!("unexpected geometry {geometry:?}"),
+        };
+        points
+            .exterior
+            .iter()
+            .map(|&(x, y)| (x.raw(), y.raw()))
+            .collect()
+    }
+
+    #[test]
+    fn convert_rectangle() {
+        let shape = parse_shape("rectangle", "[[30, 40], [10, 20]]");
+        let geometry = shape.to_geometry().unwrap();
+        assert!(matches!(geometry, Geometry::Rectangle(_)));
+        assert_eq!(exterior(&geometry), [(10.0, 20.0), (30.0, 40.0)]);
+
+        let shape = parse_shape("rectangle", "[[30, 40]]");
+        assert!(matches!(
+            shape.to_geometry(),
+            Err(Error::InvalidLabelMeShape { .. })
+        ));
+    }
+
+    #[test]
+    fn convert_polygon() {
+        let shape = parse_shape("polygon", "[[0, 0], [4, 0], [4, 3]]");
+        let geometry = shape.to_geometry().unwrap();
+        assert!(matches!(geometry, Geometry::Polygon(_)));
+        assert_eq!(exterior(&geometry), [(0.0, 0.0), (4.0, 0.0), (4.0, 3.0)]);
+
+        let shape = parse_shape("polygon", "[[0, 0], [4, 0]]");
+        assert!(shape.to_geometry().is_err());
+    }
+
+    #[test]
+    fn convert_lines() {
+        for shape_type in ["linestrip", "line"] {
+            let shape = parse_shape(shape_type, "[[0, 0], [4, 3]]");
+            let geometry = shape.to_geometry().unwrap();
+            assert!(matches!(geometry, Geometry::Polyline(_)));
+            assert_eq!(exterior(&geometry), [(0.0, 0.0), (4.0, 3.0)]);
+        }
+    }
+
+    #[test]
+    fn convert_point() {
+        let shape = parse_shape("point", "[[2.5, 3.5]]");
+        let geometry = shape.to_geometry().unwrap();
+        assert!(matches!(geometry, Geometry::Point(_)));
+        assert_eq!(exterior(&geometry), [(2.5, 3.5)]);
+    }
+
+    #[test]
+    fn reject_point_sets() {
+        let shape = parse_shape("points", "[[0, 0], [1, 1]]");
+        assert_eq!(shape.shape_type, LabelMeShapeType::Points);
+        assert!(matches!(
+            shape.to_geometry(),
+            Err(Error::InvalidLabelMeShape { .. })
+        ));
+    }
+
+    #[test]
+    fn convert_circle() {
+        let shape = parse_shape("circle", "[[10, 10], [13, 14]]");
+        let geometry = shape.to_geometry().unwrap();
+        assert!(matches!(geometry, Geometry::Polygon(_)));
+
+        let vertices = exterior(&geometry);
+        assert_eq!(vertices.len(), CIRCLE_VERTICES);
+        for (x, y) in vertices {
+            assert!(((x - 10.0).hypot(y - 10.0) - 5.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn convert_mask() {
+        let png_bytes = b"\x89PNG mask".to_vec();
+        let mut shape = parse_shape("mask", "[[3.2, 4.7], [10, 12]]");
+        assert!(shape.to_geometry().is_err());
+
+        shape.mask = Some(BASE64_STANDARD.encode(&png_bytes));
+        let Geometry::Bitmap(bitmap) = shape.to_geometry().unwrap() else {
+            panic!("a mask must become a bitmap");
+        };
+        assert_eq!(bitmap.bitmap.origin, [3, 4]);
+        assert_eq!(bitmap.bitmap.decode_data().unwrap(), png_bytes);
+    }
+
+    #[test]
+    fn round_trip_through_image_annotation() {
+        let ann: LabelMeAnnotation = serde_json::from_str(
+            r#"{
+                "version": "5.0.1",
+                "flags": {},
+                "shapes": [
+                    {
+                        "label": "car",
+                        "points": [[10, 20], [30, 40]],
+                        "group_id": null,
+                        "description": "",
+                        "shape_type": "rectangle",
+                        "flags": {}
+                    },
+                    {
+                        "label": "road",
+                        "points": [[0, 0], [4, 0], [4, 3]],
+                        "group_id": null,
+                        "description": "",
+                        "shape_type": "polygon",
+                        "flags": {}
+                    }
+                ],
+                "imagePath": "images/0001.jpg",
+                "imageData": null,
+                "imageHeight": 480,
+                "imageWidth": 640
+            }"#,
+        )
+        .unwrap();
+
+        let image_ann = ann.to_image_annotation().unwrap();
+        assert_eq!(image_ann.name, "0001.jpg");
+        assert_eq!(image_ann.objects.len(), 2);
+        assert_eq!(image_ann.objects[1].class_title.as_deref(), Some("road"));
+
+        let converted = LabelMeAnnotation::from_image_annotation(&image_ann);
+        assert_eq!(converted.shapes.len(), 2);
+        for (shape, original) in converted.shapes.iter().zip(&ann.shapes) {
+            assert_eq!(shape.label, original.label);
+            assert_eq!(shape.shape_type, original.shape_type);
+            assert_eq!(shape.points, original.points);
+        }
+    }
+}
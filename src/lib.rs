@@ -1,22 +1,54 @@
+mod agreement;
 mod annotations;
+mod builder;
 mod dataset;
+mod diff;
+mod dota;
 mod episode;
 mod error;
+mod eval;
 mod geometry;
+mod item_info;
+mod key_id_map;
+mod labelme;
+mod matching;
+mod media_info;
+mod merge;
 mod objects;
 mod project;
 mod project_meta;
+mod query;
 mod related_images;
+mod remap;
+mod split;
+mod stats;
+mod storage;
 mod tags;
 mod utils;
 
+pub use agreement::*;
 pub use annotations::*;
+pub use builder::*;
 pub use dataset::*;
+pub use diff::*;
+pub use dota::*;
 pub use episode::*;
 pub use error::*;
+pub use eval::*;
 pub use geometry::*;
+pub use item_info::*;
+pub use key_id_map::*;
+pub use labelme::*;
+pub use matching::*;
+pub use media_info::*;
+pub use merge::*;
 pub use objects::*;
 pub use project::*;
 pub use project_meta::*;
+pub use query::*;
 pub use related_images::*;
+pub use remap::*;
+pub use split::*;
+pub use stats::*;
+pub use storage::*;
 pub use tags::*;
@@ -0,0 +1,486 @@
+use crate::{
+    geometry::rasterize, BoundingBox, Geometry, Geometry2D, ImageAnnotation, Mask, Object, Result,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The algorithm used to pair objects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMethod {
+    /// Maximize the total IoU of the pairs by the Hungarian algorithm.
+    #[default]
+    Hungarian,
+    /// Repeatedly pair the two objects with the highest IoU.
+    Greedy,
+}
+
+/// The options for matching objects between two annotations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchConfig {
+    /// The minimum IoU for two objects to be paired.
+    pub iou_threshold: f64,
+    pub method: MatchMethod,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            iou_threshold: 0.5,
+            method: MatchMethod::default(),
+        }
+    }
+}
+
+/// A pair of matched objects, referred by their indices in the
+/// annotation object lists.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ObjectMatch {
+    pub left: usize,
+    pub right: usize,
+    pub iou: f64,
+}
+
+/// The result of matching objects between two annotations.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub matches: Vec<ObjectMatch>,
+    pub unmatched_left: Vec<usize>,
+    pub unmatched_right: Vec<usize>,
+}
+
+/// Compute the intersection over union of two area geometries.
+///
+/// Two rectangles are compared exactly. Otherwise the geometries are
+/// rasterized and compared pixel-wise, in the same way as the COCO
+/// evaluation treats segmentation masks.
+pub fn iou(lhs: &Geometry, rhs: &Geometry) -> Result<f64> {
+    Ok(Region::new(lhs)?.iou(&Region::new(rhs)?))
+}
+
+/// Pair the objects of two image annotations by class and IoU.
+///
+/// Objects are only paired with objects of the same class, which is
+/// identified by the class title, or by the class id if the title is
+/// missing. Objects whose geometry has no area, such as points and
+/// polylines, are left out of the result.
+pub fn match_objects(
+    left: &ImageAnnotation,
+    right: &ImageAnnotation,
+    config: &MatchConfig,
+) -> Result<MatchResult> {
+    let left = group_by_class(&left.objects)?;
+    let right = group_by_class(&right.objects)?;
+
+    let mut result = MatchResult::default();
+
+    for (class, lhs) in &left {
+        let Some(rhs) = right.get(class) else {
+            result
+                .unmatched_left
+                .extend(lhs.iter().map(|(index, _)| index));
+            continue;
+        };
+
+        let ious: Vec<Vec<f64>> = lhs
+            .iter()
+            .map(|(_, lregion)| {
+                rhs.iter()
+                    .map(|(_, rregion)| lregion.iou(rregion))
+                    .collect()
+            })
+            .collect();
+        let pairs = assign(&ious, config.iou_threshold, config.method);
+
+        let mut left_matched = vec![false; lhs.len()];
+        let mut right_matched = vec![false; rhs.len()];
+        for (li, ri, iou) in pairs {
+            left_matched[li] = true;
+            right_matched[ri] = true;
+            result.matches.push(ObjectMatch {
+                left: lhs[li].0,
+                right: rhs[ri].0,
+                iou,
+            });
+        }
+
+        result.unmatched_left.extend(
+            lhs.iter()
+                .zip(left_matched)
+                .filter(|(_, matched)| !matched)
+                .map(|((index, _), _)| index),
+        );
+        result.unmatched_right.extend(
+            rhs.iter()
+                .zip(right_matched)
+                .filter(|(_, matched)| !matched)
+                .map(|((index, _), _)| index),
+        );
+    }
+
+    for (class, rhs) in &right {
+        if !left.contains_key(class) {
+            result
+                .unmatched_right
+                .extend(rhs.iter().map(|(index, _)| index));
+        }
+    }
+
+    result.matches.sort_by_key(|m| m.left);
+    result.unmatched_left.sort_unstable();
+    result.unmatched_right.sort_unstable();
+    Ok(result)
+}
+
+/// Pair rows and columns of an IoU matrix. Returns the `(row, column,
+/// IoU)` triples whose IoU reaches the threshold.
+pub(crate) fn assign(
+    ious: &[Vec<f64>],
+    threshold: f64,
+    method: MatchMethod,
+) -> Vec<(usize, usize, f64)> {
+    let rows = ious.len();
+    let cols = ious.first().map(|row| row.len()).unwrap_or(0);
+    if rows == 0 || cols == 0 {
+        return vec![];
+    }
+
+    match method {
+        MatchMethod::Greedy => {
+            let mut candidates: Vec<(usize, usize, f64)> = ious
+                .iter()
+                .enumerate()
+                .flat_map(|(r, row)| row.iter().enumerate().map(move |(c, &iou)| (r, c, iou)))
+                .filter(|&(_, _, iou)| iou >= threshold)
+                .collect();
+            candidates.sort_by(|lhs, rhs| rhs.2.total_cmp(&lhs.2));
+
+            let mut row_used = vec![false; rows];
+            let mut col_used = vec![false; cols];
+            candidates
+                .into_iter()
+                .filter(|&(r, c, _)| {
+                    let free = !row_used[r] && !col_used[c];
+                    if free {
+                        row_used[r] = true;
+                        col_used[c] = true;
+                    }
+                    free
+                })
+                .collect()
+        }
+        MatchMethod::Hungarian => {
+            // Pairs below the threshold cost nothing, so that they never
+            // displace a valid pair.
+            let cost = |r: usize, c: usize| {
+                let iou = ious[r][c];
+                if iou >= threshold {
+                    -iou
+                } else {
+                    0.0
+                }
+            };
+
+            let assignment = if rows <= cols {
+                hungarian(rows, cols, cost)
+            } else {
+                hungarian(cols, rows, |c, r| cost(r, c))
+                    .into_iter()
+                    .map(|(c, r)| (r, c))
+                    .collect()
+            };
+
+            assignment
+                .into_iter()
+                .map(|(r, c)| (r, c, ious[r][c]))
+                .filter(|&(_, _, iou)| iou >= threshold)
+                .collect()
+        }
+    }
+}
+
+/// Solve the rectangular assignment problem with `rows <= cols` and
+/// return the `(row, column)` pairs minimizing the total cost.
+fn hungarian<F>(rows: usize, cols: usize, cost: F) -> Vec<(usize, usize)>
+where
+    F: Fn(usize, usize) -> f64,
+{
+    // Potentials and matching use 1-based indices with 0 as a sentinel.
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; cols + 1];
+    let mut owner = vec![0; cols + 1];
+    let mut way = vec![0; cols + 1];
+
+    for row in 1..=rows {
+        owner[0] = row;
+        let mut col0 = 0;
+        let mut min_slack = vec![f64::INFINITY; cols + 1];
+        let mut used = vec![false; cols + 1];
+
+        loop {
+            used[col0] = true;
+            let row0 = owner[col0];
+            let mut delta = f64::INFINITY;
+            let mut col1 = 0;
+
+            for col in 1..=cols {
+                if used[col] {
+                    continue;
+                }
+                let slack = cost(row0 - 1, col - 1) - u[row0] - v[col];
+                if slack < min_slack[col] {
+                    min_slack[col] = slack;
+                    way[col] = col0;
+                }
+                if min_slack[col] < delta {
+                    delta = min_slack[col];
+                    col1 = col;
+                }
+            }
+
+            for col in 0..=cols {
+                if used[col] {
+                    u[owner[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min_slack[col] -= delta;
+                }
+            }
+
+            col0 = col1;
+            if owner[col0] == 0 {
+                break;
+            }
+        }
+
+        while col0 != 0 {
+            let col1 = way[col0];
+            owner[col0] = owner[col1];
+            col0 = col1;
+        }
+    }
+
+    (1..=cols)
+        .filter(|&col| owner[col] != 0)
+        .map(|col| (owner[col] - 1, col - 1))
+        .collect()
+}
+
+/// The indices and regions of area objects keyed by class names.
+type ClassGroups = IndexMap<Option<String>, Vec<(usize, Region)>>;
+
+/// Group the area objects by their class names.
+fn group_by_class(objects: &[Object]) -> Result<ClassGroups> {
+    let mut groups = ClassGroups::new();
+
+    for (index, obj) in objects.iter().enumerate() {
+        if !matches!(
+            obj.geometry,
+            Geometry::Rectangle(_) | Geometry::Polygon(_) | Geometry::Bitmap(_)
+        ) {
+            continue;
+        }
+        groups
+            .entry(obj.class_name())
+            .or_default()
+            .push((index, Region::new(&obj.geometry)?));
+    }
+
+    Ok(groups)
+}
+
+/// A rasterized or exact planar region used to compute IoU.
+#[derive(Debug, Clone)]
+pub(crate) enum Region {
+    Empty,
+    Box(BoundingBox),
+    Mask(Mask, [u64; 2]),
+}
+
+impl Region {
+    pub(crate) fn new(geometry: &Geometry) -> Result<Self> {
+        let region = match geometry {
+            Geometry::Rectangle(rect) => match rect.bbox()? {
+                Some(bbox) => Self::Box(bbox),
+                None => Self::Empty,
+            },
+            _ => match geometry.to_mask()? {
+                Some((mask, origin)) => Self::Mask(mask, origin),
+                None => Self::Empty,
+            },
+        };
+        Ok(region)
+    }
+
+    pub(crate) fn iou(&self, other: &Self) -> f64 {
+        let (intersection, union) = match (self, other) {
+            (Region::Box(lhs), Region::Box(rhs)) => {
+                let intersection = lhs.intersection(rhs).map(|bbox| bbox.area()).unwrap_or(0.0);
+                (intersection, lhs.area() + rhs.area() - intersection)
+            }
+            _ => {
+                let (Some((lmask, lorigin)), Some((rmask, rorigin))) = (self.mask(), other.mask())
+                else {
+                    return 0.0;
+                };
+                let intersection = mask_intersection(&lmask, lorigin, &rmask, rorigin) as f64;
+                let union = (lmask.count() + rmask.count()) as f64 - intersection;
+                (intersection, union)
+            }
+        };
+
+        if union > 0.0 {
+            intersection / union
+        } else {
+            0.0
+        }
+    }
+
+    fn mask(&self) -> Option<(Cow<'_, Mask>, [u64; 2])> {
+        match self {
+            Region::Empty => None,
+            Region::Box(bbox) => {
+                let corners = vec![
+                    (bbox.min_x, bbox.min_y),
+                    (bbox.max_x, bbox.min_y),
+                    (bbox.max_x, bbox.max_y),
+                    (bbox.min_x, bbox.max_y),
+                ];
+                let (mask, origin) = rasterize(&[corners])?;
+                Some((Cow::Owned(mask), origin))
+            }
+            Region::Mask(mask, origin) => Some((Cow::Borrowed(mask), *origin)),
+        }
+    }
+}
+
+fn mask_intersection(lhs: &Mask, lorigin: [u64; 2], rhs: &Mask, rorigin: [u64; 2]) -> usize {
+    let [lx, ly] = lorigin.map(|value| value as i64);
+    let [rx, ry] = rorigin.map(|value| value as i64);
+
+    lhs.iter_set()
+        .filter(|&(x, y)| {
+            let (x, y) = (x as i64 + lx - rx, y as i64 + ly - ry);
+            x >= 0 && y >= 0 && rhs.get(x as usize, y as usize)
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Points, RectangleGeometry};
+    use noisy_float::types::r64;
+
+    fn object(
+        id: usize,
+        title: Option<&str>,
+        class_id: Option<usize>,
+        geometry: Geometry,
+    ) -> Object {
+        Object {
+            id,
+            class_id,
+            class_title: title.map(str::to_string),
+            labeler_login: None,
+            created_at: None,
+            updated_at: None,
+            geometry,
+        }
+    }
+
+    fn rectangle(x1: f64, y1: f64, x2: f64, y2: f64) -> Geometry {
+        Geometry::Rectangle(RectangleGeometry {
+            tags: None,
+            points: Points {
+                exterior: vec![(r64(x1), r64(y1)), (r64(x2), r64(y2))],
+                interior: vec![],
+            },
+        })
+    }
+
+    fn annotation(objects: Vec<Object>) -> ImageAnnotation {
+        ImageAnnotation {
+            name: "image.png".to_string(),
+            description: None,
+            size: crate::Size {
+                width: 100,
+                height: 100,
+            },
+            tags: None,
+            objects,
+        }
+    }
+
+    #[test]
+    fn match_objects_by_class_name() {
+        let left = annotation(vec![object(
+            1,
+            Some("car"),
+            Some(1),
+            rectangle(0.0, 0.0, 10.0, 10.0),
+        )]);
+        let right = annotation(vec![
+            object(1, Some("car"), None, rectangle(0.0, 0.0, 10.0, 10.0)),
+            object(2, Some("car"), Some(2), rectangle(20.0, 20.0, 30.0, 30.0)),
+            object(3, Some("dog"), Some(3), rectangle(0.0, 0.0, 10.0, 10.0)),
+        ]);
+
+        let result = match_objects(&left, &right, &MatchConfig::default()).unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!((result.matches[0].left, result.matches[0].right), (0, 0));
+        assert!(result.unmatched_left.is_empty());
+        assert_eq!(result.unmatched_right, vec![1, 2]);
+    }
+
+    #[test]
+    fn match_objects_falls_back_to_class_id() {
+        let left = annotation(vec![object(
+            1,
+            None,
+            Some(7),
+            rectangle(0.0, 0.0, 10.0, 10.0),
+        )]);
+        let right = annotation(vec![
+            object(1, None, Some(7), rectangle(0.0, 0.0, 10.0, 10.0)),
+            object(2, Some("car"), Some(7), rectangle(0.0, 0.0, 10.0, 10.0)),
+        ]);
+
+        let result = match_objects(&left, &right, &MatchConfig::default()).unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!((result.matches[0].left, result.matches[0].right), (0, 0));
+        assert_eq!(result.unmatched_right, vec![1]);
+    }
+
+    #[test]
+    fn hungarian_finds_the_optimum() {
+        let ious = vec![
+            vec![0.9, 0.8, 0.0],
+            vec![0.85, 0.0, 0.0],
+            vec![0.0, 0.6, 0.7],
+        ];
+
+        let mut pairs: Vec<(usize, usize)> = assign(&ious, 0.5, MatchMethod::Hungarian)
+            .into_iter()
+            .map(|(r, c, _)| (r, c))
+            .collect();
+        pairs.sort_unstable();
+        assert_eq!(pairs, vec![(0, 1), (1, 0), (2, 2)]);
+
+        let mut pairs: Vec<(usize, usize)> = assign(&ious, 0.5, MatchMethod::Greedy)
+            .into_iter()
+            .map(|(r, c, _)| (r, c))
+            .collect();
+        pairs.sort_unstable();
+        assert_eq!(pairs, vec![(0, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn hungarian_skips_pairs_below_threshold() {
+        let ious = vec![vec![0.3, 0.0], vec![0.0, 0.6], vec![0.0, 0.55]];
+
+        let pairs = assign(&ious, 0.5, MatchMethod::Hungarian);
+        assert_eq!(pairs, vec![(1, 1, 0.6)]);
+    }
+}
@@ -0,0 +1,314 @@
+use crate::Size;
+use sha2::{Digest, Sha256};
+use std::io::{self, Cursor, Read};
+
+/// The metadata of a media file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// The file size in bytes.
+    pub file_size: u64,
+    /// The hex encoded SHA-256 checksum of the file content.
+    pub sha256: String,
+}
+
+/// The metadata of an image file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageMetadata {
+    pub file: FileMetadata,
+    /// The image dimensions read from the file header, if the format
+    /// is recognized.
+    pub dimensions: Option<Size>,
+}
+
+impl FileMetadata {
+    /// Compute the metadata by reading through the file content.
+    pub fn from_reader<R>(reader: R) -> io::Result<Self>
+    where
+        R: Read,
+    {
+        let mut reader = DigestReader::new(reader);
+        io::copy(&mut reader, &mut io::sink())?;
+        Ok(reader.finish())
+    }
+}
+
+impl ImageMetadata {
+    /// Compute the metadata by reading through the image content once.
+    pub fn from_reader<R>(reader: R) -> io::Result<Self>
+    where
+        R: Read,
+    {
+        let mut reader = DigestReader::new(reader);
+        let dimensions = image_dimensions(&mut reader)?;
+        io::copy(&mut reader, &mut io::sink())?;
+
+        Ok(Self {
+            file: reader.finish(),
+            dimensions,
+        })
+    }
+}
+
+/// Read the image dimensions from the header of a PNG, JPEG, GIF, BMP
+/// or WebP image.
+///
+/// Only the header is consumed from the reader. It returns `None` if
+/// the format is not recognized or the header is truncated.
+pub fn image_dimensions<R>(reader: &mut R) -> io::Result<Option<Size>>
+where
+    R: Read,
+{
+    match probe_dimensions(reader) {
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        result => result,
+    }
+}
+
+fn probe_dimensions<R>(reader: &mut R) -> io::Result<Option<Size>>
+where
+    R: Read,
+{
+    let mut header = [0; 30];
+    let len = read_up_to(reader, &mut header)?;
+    let header = &header[..len];
+
+    let u16_le = |at: usize| u16::from_le_bytes([header[at], header[at + 1]]) as u64;
+    let u32_be = |at: usize| u32::from_be_bytes(header[at..at + 4].try_into().unwrap()) as u64;
+    let u32_le = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+    let u24_le = |at: usize| u32::from_le_bytes([header[at], header[at + 1], header[at + 2], 0]);
+
+    let size = |width, height| Some(Size { width, height });
+
+    let dimensions = if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        match len {
+            24.. if &header[12..16] == b"IHDR" => size(u32_be(16), u32_be(20)),
+            _ => None,
+        }
+    } else if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+        match len {
+            10.. => size(u16_le(6), u16_le(8)),
+            _ => None,
+        }
+    } else if header.starts_with(b"BM") {
+        match len {
+            // The OS/2 header stores 16-bit dimensions.
+            26.. if u32_le(14) == 12 => size(u16_le(18), u16_le(20)),
+            // Negative heights mark top-down bitmaps.
+            26.. => size(
+                (u32_le(18) as i32).unsigned_abs() as u64,
+                (u32_le(22) as i32).unsigned_abs() as u64,
+            ),
+            _ => None,
+        }
+    } else if header.starts_with(b"RIFF") && len >= 30 && &header[8..12] == b"WEBP" {
+        match &header[12..16] {
+            b"VP8 " if header[23..26] == [0x9d, 0x01, 0x2a] => {
+                size(u16_le(26) & 0x3fff, u16_le(28) & 0x3fff)
+            }
+            b"VP8L" if header[20] == 0x2f => {
+                let bits = u32_le(21);
+                size(
+                    (bits & 0x3fff) as u64 + 1,
+                    ((bits >> 14) & 0x3fff) as u64 + 1,
+                )
+            }
+            b"VP8X" => size(u24_le(24) as u64 + 1, u24_le(27) as u64 + 1),
+            _ => None,
+        }
+    } else if header.starts_with(&[0xff, 0xd8]) {
+        let mut reader = Cursor::new(&header[2..]).chain(reader);
+        jpeg_dimensions(&mut reader)?
+    } else {
+        None
+    };
+
+    Ok(dimensions)
+}
+
+/// Walk the JPEG segments until a start-of-frame marker.
+fn jpeg_dimensions<R>(reader: &mut R) -> io::Result<Option<Size>>
+where
+    R: Read,
+{
+    let mut byte = [0; 1];
+    let mut buf = [0; 5];
+
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0] != 0xff {
+            return Ok(None);
+        }
+
+        // Skip the fill bytes.
+        let marker = loop {
+            reader.read_exact(&mut byte)?;
+            if byte[0] != 0xff {
+                break byte[0];
+            }
+        };
+
+        // Standalone markers carry no segment.
+        if matches!(marker, 0x01 | 0xd0..=0xd8) {
+            continue;
+        }
+        if marker == 0xd9 {
+            return Ok(None);
+        }
+
+        reader.read_exact(&mut buf[..2])?;
+        let seg_len = u16::from_be_bytes([buf[0], buf[1]]) as u64;
+        if seg_len < 2 {
+            return Ok(None);
+        }
+
+        let is_sof = matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+        if is_sof {
+            reader.read_exact(&mut buf)?;
+            let height = u16::from_be_bytes([buf[1], buf[2]]) as u64;
+            let width = u16::from_be_bytes([buf[3], buf[4]]) as u64;
+            return Ok(Some(Size { width, height }));
+        }
+
+        let skip = seg_len - 2;
+        if io::copy(&mut reader.by_ref().take(skip), &mut io::sink())? < skip {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+}
+
+fn read_up_to<R>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize>
+where
+    R: Read,
+{
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(len)
+}
+
+/// Hashes and counts the bytes as they are read.
+struct DigestReader<R> {
+    inner: R,
+    hasher: Sha256,
+    len: u64,
+}
+
+impl<R> DigestReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            len: 0,
+        }
+    }
+
+    fn finish(self) -> FileMetadata {
+        FileMetadata {
+            file_size: self.len,
+            sha256: format!("{:x}", self.hasher.finalize()),
+        }
+    }
+}
+
+impl<R> Read for DigestReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.len += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bytes.extend(width.to_be_bytes());
+        bytes.extend(height.to_be_bytes());
+        bytes.extend([8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xd8];
+        // An APP0 segment to be skipped.
+        bytes.extend([0xff, 0xe0, 0x00, 0x06, b'J', b'F', b'I', b'F']);
+        // Fill bytes before the SOF0 marker.
+        bytes.extend([0xff, 0xff, 0xc0, 0x00, 0x11, 0x08]);
+        bytes.extend(height.to_be_bytes());
+        bytes.extend(width.to_be_bytes());
+        bytes.extend([0x03; 10]);
+        bytes
+    }
+
+    fn dimensions(bytes: &[u8]) -> Option<Size> {
+        image_dimensions(&mut Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn read_png_dimensions() {
+        assert_eq!(
+            dimensions(&png(640, 480)),
+            Some(Size {
+                width: 640,
+                height: 480
+            })
+        );
+    }
+
+    #[test]
+    fn read_jpeg_dimensions() {
+        assert_eq!(
+            dimensions(&jpeg(1920, 1080)),
+            Some(Size {
+                width: 1920,
+                height: 1080
+            })
+        );
+    }
+
+    #[test]
+    fn truncated_headers_have_no_dimensions() {
+        assert_eq!(dimensions(&png(640, 480)[..20]), None);
+        assert_eq!(dimensions(&jpeg(1920, 1080)[..12]), None);
+        assert_eq!(dimensions(&[0xff, 0xd8]), None);
+        assert_eq!(dimensions(b"not an image"), None);
+    }
+
+    #[test]
+    fn image_metadata_reads_the_whole_content() {
+        let mut bytes = png(2, 3);
+        bytes.extend([0; 100]);
+
+        let metadata = ImageMetadata::from_reader(Cursor::new(&bytes)).unwrap();
+        assert_eq!(metadata.file.file_size, bytes.len() as u64);
+        assert_eq!(
+            metadata.dimensions,
+            Some(Size {
+                width: 2,
+                height: 3
+            })
+        );
+        assert_eq!(
+            metadata.file,
+            FileMetadata::from_reader(Cursor::new(&bytes)).unwrap()
+        );
+
+        let abc = FileMetadata::from_reader(Cursor::new(b"abc")).unwrap();
+        assert_eq!(
+            abc.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
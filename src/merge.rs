@@ -0,0 +1,410 @@
+use crate::{
+    utils::{copy_tree, write_json},
+    ClassMeta, DatasetKind, Error, FsStorage, ImageAnnotation, KeyIdMap, KeyKind, Project,
+    ProjectMeta, Result, Storage, TagMeta, KEY_ID_MAP_FILE,
+};
+use indexmap::IndexMap;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::warn;
+
+impl Project {
+    /// Combine several projects into a new project in the output
+    /// directory.
+    ///
+    /// Classes and tags are united by their titles and names, and fail
+    /// to merge if their shapes or value types differ. Colliding
+    /// top-level dataset names get a numeric suffix, such as `ds_1`,
+    /// and nested datasets move along with their top-level dataset.
+    /// Class ids and image object ids are shifted so that they stay
+    /// unique across projects, and the key-id maps are united and
+    /// regenerated against the merged annotations.
+    ///
+    /// The source projects are read through their storages, so that
+    /// archived projects can be merged as well.
+    pub fn merge<P>(projects: &[Project], output_dir: P) -> Result<Project>
+    where
+        P: AsRef<Path>,
+    {
+        Self::merge_with(projects, Arc::new(FsStorage), output_dir.as_ref())
+    }
+
+    /// Combine several projects into a new project in a directory of a
+    /// storage. See [`Self::merge`].
+    pub fn merge_with(
+        projects: &[Project],
+        storage: Arc<dyn Storage>,
+        output_dir: &Path,
+    ) -> Result<Project> {
+        let meta = merge_meta(projects.iter().map(|project| &project.meta))?;
+        let class_ids: HashMap<&str, Option<usize>> = meta
+            .classes
+            .iter()
+            .map(|class| (class.title.as_str(), class.id))
+            .collect();
+
+        let mut dataset_names = HashSet::new();
+        let mut object_offset = IdOffset::default();
+        let mut key_id_map: Option<KeyIdMap> = None;
+
+        for project in projects {
+            // Image object ids are shifted past those of previous projects.
+            object_offset.next_project();
+
+            let mut names: Vec<&String> = project.datasets.keys().collect();
+            names.sort_unstable();
+
+            // Nested datasets are copied along with their top-level
+            // directory.
+            let mut top_names: HashMap<&str, String> = HashMap::new();
+
+            for name in names {
+                let dataset = &project.datasets[name];
+                let top = name.split('/').next().unwrap_or(name);
+                let new_top = match top_names.get(top) {
+                    Some(new_top) => new_top.clone(),
+                    None => {
+                        let new_top = unique_name(top, &mut dataset_names);
+                        if new_top != top {
+                            warn!("dataset '{top}' is renamed to '{new_top}' to avoid collision");
+                        }
+                        copy_tree(
+                            &*project.storage,
+                            &project.project_dir.join(top),
+                            &*storage,
+                            &output_dir.join(&new_top),
+                        )?;
+                        top_names.insert(top, new_top.clone());
+                        new_top
+                    }
+                };
+                let nested_dir: PathBuf = dataset
+                    .kind
+                    .dataset_dir()
+                    .strip_prefix(&project.project_dir)
+                    .map(|rel| rel.iter().skip(1).collect())
+                    .unwrap_or_default();
+                let new_dir = output_dir.join(new_top).join(nested_dir);
+
+                let DatasetKind::Image(dataset) = &dataset.kind else {
+                    continue;
+                };
+
+                let old_titles: HashMap<usize, &str> = project
+                    .meta
+                    .classes
+                    .iter()
+                    .filter_map(|class| Some((class.id?, class.title.as_str())))
+                    .collect();
+
+                for image_name in &dataset.image_names {
+                    let image =
+                        dataset
+                            .get_image(image_name)
+                            .ok_or_else(|| Error::MediaNotFound {
+                                dataset: name.clone(),
+                                name: image_name.clone(),
+                            })?;
+                    let mut ann: ImageAnnotation = image.ann()?;
+                    for obj in &mut ann.objects {
+                        let title = obj
+                            .class_title
+                            .as_deref()
+                            .or_else(|| old_titles.get(&obj.class_id?).copied());
+                        if let Some(title) = title {
+                            obj.class_id = class_ids.get(title).copied().flatten();
+                        }
+                        obj.id = object_offset.shift(obj.id);
+                    }
+                    let path = new_dir.join("ann").join(format!("{image_name}.json"));
+                    write_json(&*storage, &path, &ann)?;
+                }
+            }
+
+            if let Some(map) = &project.key_id_map {
+                let merged = key_id_map.get_or_insert_with(KeyIdMap::default);
+                for kind in KeyKind::ALL {
+                    for (key, &id) in map.ids(kind) {
+                        if merged.ids_mut(kind).insert(key.clone(), id).is_some() {
+                            warn!("key '{key}' appears in multiple projects");
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(map) = &key_id_map {
+            write_json(&*storage, &output_dir.join(KEY_ID_MAP_FILE), map)?;
+        }
+        write_json(&*storage, &output_dir.join("meta.json"), &meta)?;
+
+        // Ids colliding across projects are reassigned by rebuilding the
+        // united map against the merged annotations.
+        let mut merged = Project::open_with(storage, output_dir)?;
+        if merged.key_id_map.is_some() {
+            merged.regenerate_key_id_map()?;
+            merged.save_key_id_map()?;
+        }
+        Ok(merged)
+    }
+}
+
+/// Unite the classes and tags of several project metas.
+///
+/// Class ids are kept unless they collide with a previous class, in
+/// which case a new id is allocated.
+pub fn merge_meta<'a, I>(metas: I) -> Result<ProjectMeta>
+where
+    I: IntoIterator<Item = &'a ProjectMeta>,
+{
+    let mut classes: IndexMap<String, ClassMeta> = IndexMap::new();
+    let mut tags: IndexMap<String, TagMeta> = IndexMap::new();
+
+    for meta in metas {
+        for class in &meta.classes {
+            match classes.get_mut(&class.title) {
+                Some(merged) => {
+                    if merged.shape != class.shape {
+                        return Err(Error::IncompatibleClassShapes {
+                            title: class.title.clone(),
+                            lhs: merged.shape,
+                            rhs: class.shape,
+                        });
+                    }
+                    merged.color = merged.color.or(class.color);
+                }
+                None => {
+                    classes.insert(class.title.clone(), class.clone());
+                }
+            }
+        }
+
+        for tag in &meta.tags {
+            match tags.get_mut(&tag.name) {
+                Some(merged) => {
+                    if merged.value_type != tag.value_type {
+                        return Err(Error::IncompatibleTagTypes {
+                            name: tag.name.clone(),
+                            lhs: merged.value_type,
+                            rhs: tag.value_type,
+                        });
+                    }
+                    merged.color = merged.color.or(tag.color);
+                    if let (Some(values), Some(other)) = (&mut merged.values, &tag.values) {
+                        values.extend(other.iter().cloned());
+                    }
+                }
+                None => {
+                    tags.insert(tag.name.clone(), tag.clone());
+                }
+            }
+        }
+    }
+
+    let mut used_ids = HashSet::new();
+    let mut next_id = classes
+        .values()
+        .filter_map(|class| class.id)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    for class in classes.values_mut() {
+        let Some(id) = class.id else {
+            continue;
+        };
+        if !used_ids.insert(id) {
+            class.id = Some(next_id);
+            used_ids.insert(next_id);
+            next_id += 1;
+        }
+    }
+
+    Ok(ProjectMeta {
+        classes: classes.into_values().collect(),
+        tags: tags.into_values().collect(),
+    })
+}
+
+/// Shifts the ids of a project past the ids of previous projects.
+#[derive(Debug, Clone, Copy, Default)]
+struct IdOffset {
+    offset: usize,
+    max_id: Option<usize>,
+}
+
+impl IdOffset {
+    fn next_project(&mut self) {
+        self.offset = self.max_id.map(|id| id + 1).unwrap_or(0);
+    }
+
+    fn shift(&mut self, id: usize) -> usize {
+        let id = id + self.offset;
+        self.max_id = Some(self.max_id.map_or(id, |max| max.max(id)));
+        id
+    }
+}
+
+fn unique_name(name: &str, used: &mut HashSet<String>) -> String {
+    let mut candidate = name.to_string();
+    let mut suffix = 1;
+    while used.contains(&candidate) {
+        candidate = format!("{name}_{suffix}");
+        suffix += 1;
+    }
+    used.insert(candidate.clone());
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MediaAnnotation, MemoryStorage, Shape};
+
+    fn class(id: usize, title: &str, shape: Shape) -> ClassMeta {
+        ClassMeta {
+            id: Some(id),
+            ..ClassMeta::new(title.to_string(), shape)
+        }
+    }
+
+    fn project(classes: &[ClassMeta], object_ids: &[usize], key_id_map: &str) -> Project {
+        let meta = ProjectMeta {
+            classes: classes.to_vec(),
+            tags: vec![],
+        };
+        let objects: Vec<String> = object_ids
+            .iter()
+            .map(|id| {
+                format!(
+                    r#"{{
+                        "id": {id},
+                        "classId": null,
+                        "classTitle": "car",
+                        "labelerLogin": null,
+                        "createdAt": null,
+                        "updatedAt": null,
+                        "geometryType": "rectangle",
+                        "tags": [],
+                        "points": {{"exterior": [[0, 0], [1, 1]], "interior": []}}
+                    }}"#
+                )
+            })
+            .collect();
+        let ann = format!(
+            r#"{{"name": "a.png", "size": {{"width": 10, "height": 10}}, "tags": [], "objects": [{}]}}"#,
+            objects.join(",")
+        );
+
+        let storage = MemoryStorage::new();
+        storage
+            .insert("meta.json", serde_json::to_vec(&meta).unwrap())
+            .unwrap();
+        storage.insert(KEY_ID_MAP_FILE, key_id_map).unwrap();
+        storage.insert("ds/img/a.png", []).unwrap();
+        storage.insert("ds/ann/a.png.json", ann).unwrap();
+        Project::open_with(Arc::new(storage), Path::new("")).unwrap()
+    }
+
+    const EMPTY_KEY_ID_MAP: &str = r#"{"tags": {}, "objects": {}, "figures": {}, "videos": {}}"#;
+
+    #[test]
+    fn merge_meta_unites_classes() {
+        let lhs = ProjectMeta {
+            classes: vec![
+                class(1, "car", Shape::Rectangle),
+                class(2, "dog", Shape::Polygon),
+            ],
+            tags: vec![],
+        };
+        let rhs = ProjectMeta {
+            classes: vec![
+                class(1, "cat", Shape::Bitmap),
+                class(5, "car", Shape::Rectangle),
+            ],
+            tags: vec![],
+        };
+
+        let meta = merge_meta([&lhs, &rhs]).unwrap();
+        let classes: Vec<(&str, Option<usize>)> = meta
+            .classes
+            .iter()
+            .map(|class| (class.title.as_str(), class.id))
+            .collect();
+        assert_eq!(
+            classes,
+            [("car", Some(1)), ("dog", Some(2)), ("cat", Some(3))]
+        );
+    }
+
+    #[test]
+    fn merge_meta_rejects_class_conflicts() {
+        let lhs = ProjectMeta {
+            classes: vec![class(1, "car", Shape::Rectangle)],
+            tags: vec![],
+        };
+        let rhs = ProjectMeta {
+            classes: vec![class(1, "car", Shape::Bitmap)],
+            tags: vec![],
+        };
+
+        let result = merge_meta([&lhs, &rhs]);
+        assert!(matches!(
+            result,
+            Err(Error::IncompatibleClassShapes {
+                lhs: Shape::Rectangle,
+                rhs: Shape::Bitmap,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn id_offset_shifts_past_previous_projects() {
+        let mut offset = IdOffset::default();
+        offset.next_project();
+        let first: Vec<usize> = [1, 2].map(|id| offset.shift(id)).into();
+        offset.next_project();
+        let second: Vec<usize> = [1, 5].map(|id| offset.shift(id)).into();
+        offset.next_project();
+        let third = offset.shift(0);
+
+        assert_eq!(first, [1, 2]);
+        assert_eq!(second, [4, 8]);
+        assert_eq!(third, 9);
+    }
+
+    #[test]
+    fn merge_shifts_colliding_ids() {
+        let classes = [class(1, "car", Shape::Rectangle)];
+        let projects = [
+            project(
+                &classes,
+                &[1, 2],
+                r#"{"tags": {}, "objects": {}, "figures": {}, "videos": {"a": 1}}"#,
+            ),
+            project(&classes, &[1], EMPTY_KEY_ID_MAP),
+        ];
+
+        let storage = Arc::new(MemoryStorage::new());
+        let merged = Project::merge_with(&projects, storage, Path::new("out")).unwrap();
+
+        let mut names: Vec<&String> = merged.datasets.keys().collect();
+        names.sort_unstable();
+        assert_eq!(names, ["ds", "ds_1"]);
+
+        let mut ids = vec![];
+        for item in merged.iter_annotations() {
+            let MediaAnnotation::Image(ann) = item.unwrap().annotation else {
+                unreachable!();
+            };
+            ids.extend(ann.objects.iter().map(|obj| (obj.id, obj.class_id)));
+        }
+        assert_eq!(ids, [(1, Some(1)), (2, Some(1)), (4, Some(1))]);
+
+        // Image annotations have no keys, so the regenerated map is empty.
+        assert_eq!(merged.key_id_map, Some(KeyIdMap::default()));
+    }
+}
@@ -6,6 +6,8 @@ pub struct Object {
     pub id: usize,
     #[serde(rename = "classId")]
     pub class_id: Option<usize>,
+    #[serde(rename = "classTitle")]
+    pub class_title: Option<String>,
     #[serde(rename = "labelerLogin")]
     pub labeler_login: Option<String>,
     #[serde(rename = "createdAt")]
@@ -16,6 +18,18 @@ pub struct Object {
     pub geometry: Geometry,
 }
 
+impl Object {
+    /// Get the class title, falling back to the class id if the title is
+    /// missing.
+    pub fn class_name(&self) -> Option<String> {
+        match (&self.class_title, self.class_id) {
+            (Some(title), _) => Some(title.clone()),
+            (None, Some(class_id)) => Some(class_id.to_string()),
+            (None, None) => None,
+        }
+    }
+}
+
 mod serde_object_geometry {
     use crate::{
         BitmapGeometry, Cuboid3DGeometry, Geometry, PointGeometry, PolygonGeometry,
@@ -66,7 +80,7 @@ mod serde_object_geometry {
     }
 
     #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-    #[serde(tag = "geometryType", content = "geometry", rename_all = "camelCase")]
+    #[serde(tag = "geometryType", rename_all = "camelCase")]
     pub enum SerializedGeometryRef<'a> {
         Point(&'a PointGeometry),
         Rectangle(&'a RectangleGeometry),
@@ -113,3 +127,36 @@ mod serde_object_geometry {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serialize_and_deserialize_object() {
+        let value = json!({
+            "id": 1,
+            "classId": 2,
+            "classTitle": "car",
+            "labelerLogin": "alice",
+            "createdAt": "2024-01-01T00:00:00.000Z",
+            "updatedAt": null,
+            "geometryType": "rectangle",
+            "tags": [],
+            "points": {
+                "exterior": [[1.0, 2.0], [3.0, 4.0]],
+                "interior": []
+            }
+        });
+
+        let obj: Object = serde_json::from_value(value.clone()).unwrap();
+        assert!(matches!(obj.geometry, Geometry::Rectangle(_)));
+
+        // The geometry fields are flattened into the object as they are
+        // read, so that saved annotations can be loaded again.
+        let serialized = serde_json::to_value(&obj).unwrap();
+        assert_eq!(serialized, value);
+        assert_eq!(serde_json::from_value::<Object>(serialized).unwrap(), obj);
+    }
+}
@@ -1,9 +1,17 @@
-use crate::{utils::load_json, Dataset, Error, ProjectMeta, Result};
-use itertools::Itertools;
+use crate::{
+    utils::{read_json, read_json_if_exists, write_json},
+    AnnotationItem, Dataset, Error, FsStorage, KeyIdMap, KeyKind, ProjectMeta, Result, Storage,
+    KEY_ID_MAP_FILE,
+};
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
+    sync::Arc,
 };
+use tracing::warn;
+
+/// The folder holding the nested datasets of a dataset.
+pub const NESTED_DATASETS_DIR: &str = "datasets";
 
 /// Represent a Supervisely project.
 #[derive(Debug, Clone)]
@@ -11,6 +19,9 @@ pub struct Project {
     pub project_dir: PathBuf,
     pub meta: ProjectMeta,
     pub datasets: HashMap<String, Dataset>,
+    /// The key-id map loaded from `key_id_map.json`, if present.
+    pub key_id_map: Option<KeyIdMap>,
+    pub storage: Arc<dyn Storage>,
 }
 
 impl Project {
@@ -18,49 +29,517 @@ impl Project {
     where
         P: AsRef<Path>,
     {
+        Self::open_with(Arc::new(FsStorage), dir.as_ref())
+    }
+
+    /// Open a project in a directory of a storage.
+    pub fn open_with(storage: Arc<dyn Storage>, dir: &Path) -> Result<Self> {
+        let meta: ProjectMeta = read_json(&*storage, &dir.join("meta.json"))?;
+        let key_id_map = read_json_if_exists(&*storage, &dir.join(KEY_ID_MAP_FILE))?;
+
+        // Scan the dataset folders, descending into nested datasets.
+        let mut datasets = HashMap::new();
+        let mut pending = vec![(dir.to_path_buf(), None)];
+
+        while let Some((parent_dir, parent)) = pending.pop() {
+            for entry in storage.list_dir(&parent_dir)? {
+                if !entry.is_dir {
+                    continue;
+                }
+                let path = parent_dir.join(&entry.name);
+                let name = nested_name(parent.as_deref(), &entry.name);
+                let has_children = storage.exists(&path.join(NESTED_DATASETS_DIR));
+
+                let dataset = Dataset::open_with(storage.clone(), &path);
+                if let Some(dataset) = skip_unknown_layout(dataset, has_children)? {
+                    insert_dataset(&mut datasets, name.clone(), parent.clone(), dataset);
+                }
+                if has_children {
+                    pending.push((path.join(NESTED_DATASETS_DIR), Some(name)));
+                }
+            }
+        }
+
+        Ok(Self {
+            project_dir: dir.to_path_buf(),
+            meta,
+            datasets,
+            key_id_map,
+            storage,
+        })
+    }
+
+    /// Open a project packed in a `.tar` or `.zip` archive, which
+    /// requires the `tar` or `zip` feature respectively.
+    ///
+    /// The project is either at the archive root or in a top-level
+    /// directory.
+    pub fn open_archive<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let storage = open_archive_storage(path)?;
+
+        let root = Path::new("");
+        let project_dir = if storage.exists(&root.join("meta.json")) {
+            root.to_path_buf()
+        } else {
+            storage
+                .list_dir(root)?
+                .into_iter()
+                .filter(|entry| entry.is_dir)
+                .map(|entry| root.join(entry.name))
+                .find(|dir| storage.exists(&dir.join("meta.json")))
+                .ok_or_else(|| Error::ProjectNotFoundInArchive(path.to_path_buf()))?
+        };
+
+        Self::open_with(storage, &project_dir)
+    }
+
+    /// The async counterpart of [`Self::open`].
+    #[cfg(feature = "async")]
+    pub async fn open_async<P>(dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        use crate::utils::load_json_async;
+
         let dir = dir.as_ref();
-        let meta: ProjectMeta = load_json(dir.join("meta.json"))?;
-
-        // Scan the dataset folders
-        let datasets: HashMap<_, _> = dir
-            .read_dir()
-            .map_err(|error| Error::read_dir_error(dir, error))?
-            .map(|entry| -> Result<_> {
-                let entry = entry.map_err(|error| Error::read_dir_error(dir, error))?;
-                let path = entry.path();
+        let meta: ProjectMeta = load_json_async(dir.join("meta.json")).await?;
+        let key_id_map_path = dir.join(KEY_ID_MAP_FILE);
+        let key_id_map = if tokio::fs::try_exists(&key_id_map_path)
+            .await
+            .unwrap_or(false)
+        {
+            Some(load_json_async(key_id_map_path).await?)
+        } else {
+            None
+        };
 
-                let path = if path.is_file() {
-                    return Ok(None);
-                } else if path.is_dir() {
-                    path
-                } else {
-                    let resolved_path = path
-                        .canonicalize()
-                        .map_err(|error| Error::resolve_path_error(&path, error))?;
-
-                    if resolved_path.is_dir() {
-                        return Ok(None);
-                    }
-                    path
-                };
+        // Scan the dataset folders, descending into nested datasets.
+        let mut datasets = HashMap::new();
+        let mut pending = vec![(dir.to_path_buf(), None)];
+
+        while let Some((parent_dir, parent)) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&parent_dir)
+                .await
+                .map_err(|error| Error::read_dir_error(&parent_dir, error))?;
+
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|error| Error::read_dir_error(&parent_dir, error))?
+            {
+                let path = entry.path();
+                let metadata = tokio::fs::metadata(&path)
+                    .await
+                    .map_err(|error| Error::resolve_path_error(&path, error))?;
+                if !metadata.is_dir() {
+                    continue;
+                }
 
-                let dataset_name = path
+                let dir_name = entry
                     .file_name()
-                    .expect("unable to get the directory name")
                     .to_str()
                     .ok_or_else(|| Error::expect_utf8_file_name(&path))?
                     .to_string();
-                let dataset = Dataset::open(&path)?;
+                let name = nested_name(parent.as_deref(), &dir_name);
+                let has_children = tokio::fs::try_exists(path.join(NESTED_DATASETS_DIR))
+                    .await
+                    .unwrap_or(false);
 
-                Ok(Some((dataset_name, dataset)))
-            })
-            .flatten_ok()
-            .try_collect()?;
+                let dataset = Dataset::open_async(&path).await;
+                if let Some(dataset) = skip_unknown_layout(dataset, has_children)? {
+                    insert_dataset(&mut datasets, name.clone(), parent.clone(), dataset);
+                }
+                if has_children {
+                    pending.push((path.join(NESTED_DATASETS_DIR), Some(name)));
+                }
+            }
+        }
 
         Ok(Self {
             project_dir: dir.to_path_buf(),
             meta,
             datasets,
+            key_id_map,
+            storage: Arc::new(FsStorage),
         })
     }
+
+    /// Get the datasets nested directly in a dataset, sorted by name.
+    pub fn children(&self, name: &str) -> Vec<&Dataset> {
+        let mut children: Vec<&Dataset> = self
+            .datasets
+            .values()
+            .filter(|dataset| dataset.parent.as_deref() == Some(name))
+            .collect();
+        children.sort_unstable_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+        children
+    }
+
+    /// Overwrite the `meta.json` of the project.
+    pub fn save_meta(&self) -> Result<()> {
+        let path = self.project_dir.join("meta.json");
+        write_json(&*self.storage, &path, &self.meta)
+    }
+
+    /// Rebuild the key-id map from the keys in the annotations.
+    ///
+    /// Keys already in the map keep their ids, keys missing from the
+    /// annotations are dropped, and new keys are given fresh ids.
+    pub fn regenerate_key_id_map(&mut self) -> Result<&KeyIdMap> {
+        let mut keys: Vec<(KeyKind, String)> = vec![];
+        for item in self.iter_annotations() {
+            let item = item?;
+            keys.extend(
+                item.annotation
+                    .keys()
+                    .into_iter()
+                    .map(|(kind, key)| (kind, key.to_string())),
+            );
+        }
+
+        let keys = keys.iter().map(|(kind, key)| (*kind, key.as_str()));
+        let map = match &self.key_id_map {
+            Some(map) => map.regenerate(keys),
+            None => KeyIdMap::default().regenerate(keys),
+        };
+        Ok(self.key_id_map.insert(map))
+    }
+
+    /// Overwrite the `key_id_map.json` of the project if the project
+    /// has a key-id map.
+    pub fn save_key_id_map(&self) -> Result<()> {
+        let Some(map) = &self.key_id_map else {
+            return Ok(());
+        };
+        let path = self.project_dir.join(KEY_ID_MAP_FILE);
+        write_json(&*self.storage, &path, map)
+    }
+
+    /// Iterate over the annotations of all datasets in the order of
+    /// dataset names.
+    ///
+    /// Annotation files are parsed one at a time as the iterator
+    /// advances, so that memory use stays bounded on large projects.
+    /// Episode annotations are cloned from the copies loaded when the
+    /// project was opened.
+    pub fn iter_annotations(&self) -> impl Iterator<Item = Result<AnnotationItem<'_>>> + '_ {
+        let mut datasets: Vec<(&String, &Dataset)> = self.datasets.iter().collect();
+        datasets.sort_unstable_by_key(|(name, _)| *name);
+        datasets
+            .into_iter()
+            .flat_map(|(_, dataset)| dataset.iter_annotations())
+    }
+
+    /// Load the annotations of all datasets in parallel, ordered as
+    /// [`Project::iter_annotations`].
+    ///
+    /// Every annotation file is attempted. Failures are collected into
+    /// [`Error::MultipleErrors`] rather than stopping at the first one.
+    /// Episode annotations are cloned as in [`Project::iter_annotations`].
+    #[cfg(feature = "parallel")]
+    pub fn load_all_annotations(&self) -> Result<Vec<AnnotationItem<'_>>> {
+        use crate::DatasetKind;
+        use itertools::Itertools;
+        use rayon::prelude::*;
+
+        let mut datasets: Vec<(&String, &Dataset)> = self.datasets.iter().collect();
+        datasets.sort_unstable_by_key(|(name, _)| *name);
+
+        let results: Vec<Result<AnnotationItem<'_>>> = datasets
+            .into_par_iter()
+            .flat_map_iter(|(_, dataset)| -> Vec<Result<AnnotationItem<'_>>> {
+                let dataset_name = dataset.name.as_str();
+                let item = move |media_name, annotation| AnnotationItem {
+                    dataset: dataset_name,
+                    media_name,
+                    annotation,
+                };
+
+                match &dataset.kind {
+                    DatasetKind::Image(dataset) => dataset
+                        .par_iter()
+                        .map(|(name, ann)| Ok(item(name, ann?.into())))
+                        .collect(),
+                    DatasetKind::Video(dataset) => dataset
+                        .par_iter()
+                        .map(|(name, ann)| Ok(item(name, ann?.into())))
+                        .collect(),
+                    DatasetKind::PointCloud(dataset) => dataset
+                        .par_iter()
+                        .map(|(name, ann)| Ok(item(name, ann?.into())))
+                        .collect(),
+                    DatasetKind::PointCloudEpisode(dataset) => {
+                        vec![Ok(item(dataset_name, dataset.annotation.clone().into()))]
+                    }
+                }
+            })
+            .collect();
+
+        let (items, errors): (Vec<_>, Vec<_>) = results.into_iter().partition_result();
+        match Error::multiple(errors) {
+            Some(error) => Err(error),
+            None => Ok(items),
+        }
+    }
+}
+
+fn nested_name(parent: Option<&str>, dir_name: &str) -> String {
+    match parent {
+        Some(parent) => format!("{parent}/{dir_name}"),
+        None => dir_name.to_string(),
+    }
+}
+
+/// Skip the directory if it is not a dataset, with a warning unless it
+/// groups nested datasets.
+fn skip_unknown_layout(dataset: Result<Dataset>, has_children: bool) -> Result<Option<Dataset>> {
+    match dataset {
+        Ok(dataset) => Ok(Some(dataset)),
+        Err(Error::UnknownDatasetLayout(dir)) => {
+            if !has_children {
+                warn!("skip directory '{}' which is not a dataset", dir.display());
+            }
+            Ok(None)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+fn insert_dataset(
+    datasets: &mut HashMap<String, Dataset>,
+    name: String,
+    parent: Option<String>,
+    dataset: Dataset,
+) {
+    let dataset = Dataset {
+        name: name.clone(),
+        parent,
+        ..dataset
+    };
+    datasets.insert(name, dataset);
+}
+
+fn open_archive_storage(path: &Path) -> Result<Arc<dyn Storage>> {
+    #[cfg(feature = "tar")]
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tar"))
+    {
+        return Ok(Arc::new(crate::TarStorage::open(path)?));
+    }
+    #[cfg(feature = "zip")]
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+    {
+        return Ok(Arc::new(crate::ZipStorage::open(path)?));
+    }
+    Err(Error::UnsupportedArchive(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStorage;
+
+    fn image_ann(name: &str, num_objects: usize) -> String {
+        let object = r#"{
+            "id": 1,
+            "classId": null,
+            "classTitle": "car",
+            "labelerLogin": null,
+            "createdAt": null,
+            "updatedAt": null,
+            "geometryType": "point",
+            "tags": [],
+            "points": {"exterior": [[1, 1]], "interior": []}
+        }"#;
+        format!(
+            r#"{{"name": "{name}", "size": {{"width": 10, "height": 10}}, "tags": [], "objects": [{}]}}"#,
+            vec![object; num_objects].join(",")
+        )
+    }
+
+    fn project(broken: bool) -> Project {
+        let storage = MemoryStorage::new();
+        storage
+            .insert("meta.json", r#"{"classes": [], "tags": []}"#)
+            .unwrap();
+        for (name, num_objects) in [("b.png", 2), ("a.png", 1)] {
+            storage.insert(format!("images/img/{name}"), []).unwrap();
+            storage
+                .insert(
+                    format!("images/ann/{name}.json"),
+                    image_ann(name, num_objects),
+                )
+                .unwrap();
+        }
+        if broken {
+            storage.insert("images/ann/a.png.json", "{").unwrap();
+            storage.insert("images/ann/b.png.json", "[]").unwrap();
+        }
+        storage
+            .insert("episode/frame_pointcloud_map.json", r#"{"0": "000.pcd"}"#)
+            .unwrap();
+        storage
+            .insert(
+                "episode/annotation.json",
+                r#"{"description": "", "key": "e", "tags": [], "objects": [], "frames": []}"#,
+            )
+            .unwrap();
+        Project::open_with(Arc::new(storage), Path::new("")).unwrap()
+    }
+
+    fn summary<'a>(
+        items: impl IntoIterator<Item = AnnotationItem<'a>>,
+    ) -> Vec<(&'a str, &'a str, usize)> {
+        items
+            .into_iter()
+            .map(|item| (item.dataset, item.media_name, item.annotation.num_objects()))
+            .collect()
+    }
+
+    const EXPECTED_SUMMARY: [(&str, &str, usize); 3] = [
+        ("episode", "episode", 0),
+        ("images", "a.png", 1),
+        ("images", "b.png", 2),
+    ];
+
+    #[test]
+    fn iter_annotations_in_order() {
+        let project = project(false);
+        let items: Vec<_> = project.iter_annotations().collect::<Result<_>>().unwrap();
+        assert_eq!(summary(items), EXPECTED_SUMMARY);
+    }
+
+    #[test]
+    fn iter_annotations_yields_errors_per_item() {
+        let project = project(true);
+        let results: Vec<bool> = project
+            .iter_annotations()
+            .map(|item| item.is_ok())
+            .collect();
+        assert_eq!(results, [true, false, false]);
+    }
+
+    #[test]
+    fn open_nested_datasets() {
+        let storage = MemoryStorage::new();
+        storage
+            .insert("meta.json", r#"{"classes": [], "tags": []}"#)
+            .unwrap();
+        for dir in ["parent", "parent/datasets/child", "group/datasets/leaf"] {
+            storage.insert(format!("{dir}/img/a.png"), []).unwrap();
+            storage
+                .insert(format!("{dir}/ann/a.png.json"), image_ann("a.png", 0))
+                .unwrap();
+        }
+        storage.insert("misc/readme.txt", "not a dataset").unwrap();
+        let project = Project::open_with(Arc::new(storage), Path::new("")).unwrap();
+
+        let mut names: Vec<(&str, Option<&str>)> = project
+            .datasets
+            .values()
+            .map(|dataset| (dataset.name.as_str(), dataset.parent.as_deref()))
+            .collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            [
+                ("group/leaf", Some("group")),
+                ("parent", None),
+                ("parent/child", Some("parent"))
+            ]
+        );
+
+        let children: Vec<&str> = project
+            .children("parent")
+            .into_iter()
+            .map(|dataset| dataset.name.as_str())
+            .collect();
+        assert_eq!(children, ["parent/child"]);
+    }
+
+    #[test]
+    fn skip_only_unknown_layouts() {
+        let unknown = Err(Error::UnknownDatasetLayout(PathBuf::from("misc")));
+        assert!(skip_unknown_layout(unknown, false).unwrap().is_none());
+        let unknown = Err(Error::UnknownDatasetLayout(PathBuf::from("group")));
+        assert!(skip_unknown_layout(unknown, true).unwrap().is_none());
+
+        let broken = Err(Error::ExpectSingleMediaDirectory(PathBuf::from("broken")));
+        assert!(matches!(
+            skip_unknown_layout(broken, false),
+            Err(Error::ExpectSingleMediaDirectory(_))
+        ));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn load_all_annotations_agrees_with_iter_annotations() {
+        let project = project(false);
+        assert_eq!(
+            summary(project.load_all_annotations().unwrap()),
+            EXPECTED_SUMMARY
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn load_all_annotations_collects_all_failures() {
+        let project = project(true);
+        let Err(Error::MultipleErrors { first, rest }) = project.load_all_annotations() else {
+            panic!("expect multiple errors");
+        };
+        let paths: Vec<String> = [&*first]
+            .into_iter()
+            .chain(&rest)
+            .map(|error| match error {
+                Error::ParseJsonFileError { path, .. } => path.display().to_string(),
+                error => panic!("unexpected error {error}"),
+            })
+            .collect();
+        assert_eq!(paths, ["images/ann/a.png.json", "images/ann/b.png.json"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn open_async_agrees_with_open() {
+        use crate::DatasetKind;
+        use std::fs;
+
+        let dir =
+            std::env::temp_dir().join(format!("supervisely-open-async-{}", std::process::id()));
+        fs::create_dir_all(dir.join("ds/img")).unwrap();
+        fs::create_dir_all(dir.join("ds/ann")).unwrap();
+        fs::write(dir.join("meta.json"), r#"{"classes": [], "tags": []}"#).unwrap();
+        fs::write(dir.join("ds/img/a.png"), []).unwrap();
+        fs::write(
+            dir.join("ds/ann/a.png.json"),
+            r#"{"name": "a.png", "size": {"width": 10, "height": 10}, "tags": [], "objects": []}"#,
+        )
+        .unwrap();
+
+        let project = Project::open_async(&dir).await.unwrap();
+        let expected = Project::open(&dir).unwrap();
+        assert_eq!(project.meta, expected.meta);
+        assert_eq!(project.key_id_map, None);
+
+        let DatasetKind::Image(dataset) = &project.datasets["ds"].kind else {
+            panic!("expect an image dataset");
+        };
+        let ann = dataset
+            .get_image("a.png")
+            .unwrap()
+            .ann_async()
+            .await
+            .unwrap();
+        assert_eq!(ann.size.width, 10);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -11,7 +11,7 @@ pub struct KeyIdMap {
     pub videos: HashMap<String, usize>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProjectMeta {
     pub classes: Vec<ClassMeta>,
     pub tags: Vec<TagMeta>,
@@ -19,6 +19,8 @@ pub struct ProjectMeta {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClassMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
     pub title: String,
     pub shape: Shape,
     #[serde(with = "serde_color")]
@@ -35,7 +37,38 @@ pub struct TagMeta {
     pub values: Option<HashSet<String>>,
 }
 
+impl ClassMeta {
+    pub fn new(title: String, shape: Shape) -> Self {
+        Self {
+            id: None,
+            title,
+            shape,
+            color: None,
+            geometry_config: GeometryConfig::default(),
+        }
+    }
+}
+
 impl TagMeta {
+    pub fn new_none(name: String) -> Self {
+        Self {
+            name,
+            color: None,
+            value_type: ValueType::None,
+            values: None,
+        }
+    }
+    pub fn new_one_of_string<I>(name: String, values: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+    {
+        Self {
+            name,
+            color: None,
+            value_type: ValueType::OneOfString,
+            values: Some(values.into_iter().collect()),
+        }
+    }
     pub fn new_any_number(name: String) -> Self {
         Self {
             name,
@@ -0,0 +1,651 @@
+use crate::{
+    Geometry, ImageAnnotation, MediaAnnotation, Object, PointCloudAnnotation,
+    PointCloudEpisodeAnnotation, Project, ProjectMeta, Result, Shape, Tag, TagValue,
+    VideoAnnotation,
+};
+use indexmap::{IndexMap, IndexSet};
+use itertools::Itertools;
+use noisy_float::types::R64;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, ops::Not};
+
+/// A condition on an object.
+///
+/// For videos, point clouds and episodes, the geometry conditions hold
+/// if any figure of the object satisfies them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Predicate {
+    /// Always true.
+    #[default]
+    Any,
+    /// The class title is one of the set.
+    ClassIn(IndexSet<String>),
+    /// The object or one of its figures has a tag with the name. If the
+    /// value is given, the tag value must be equal to it.
+    HasTag {
+        name: String,
+        value: Option<TagValue>,
+    },
+    /// The figure area in pixels lies within the inclusive range.
+    /// Geometries without area never satisfy the condition.
+    AreaRange {
+        min: Option<R64>,
+        max: Option<R64>,
+    },
+    /// The object or one of its figures was created by the labeler.
+    LabelerLogin(String),
+    /// The creation time lies within `[start, end)`. Timestamps are
+    /// compared as ISO 8601 strings.
+    ///
+    /// Only image objects record their creation time, so the condition
+    /// never holds for video, point cloud and episode objects.
+    CreatedBetween {
+        start: Option<String>,
+        end: Option<String>,
+    },
+    /// The figure has one of the shapes.
+    GeometryType(IndexSet<Shape>),
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Not(Box<Predicate>),
+}
+
+/// A condition on a media item.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaPredicate {
+    /// Always true.
+    #[default]
+    Any,
+    /// The media name is one of the set.
+    NameIn(IndexSet<String>),
+    /// The media has a tag with the name. If the value is given, the
+    /// tag value must be equal to it.
+    HasTag {
+        name: String,
+        value: Option<TagValue>,
+    },
+    /// At least one object satisfies the predicate.
+    HasObject(Predicate),
+    And(Vec<MediaPredicate>),
+    Or(Vec<MediaPredicate>),
+    Not(Box<MediaPredicate>),
+}
+
+/// Select media and the objects within them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Query {
+    /// The media to keep.
+    pub media: MediaPredicate,
+    /// The objects to keep in the selected media.
+    pub objects: Predicate,
+}
+
+/// The annotations of a project selected by a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectView {
+    pub meta: ProjectMeta,
+    pub datasets: IndexMap<String, DatasetView>,
+}
+
+/// The annotations of a dataset selected by a query.
+///
+/// Items are keyed by media names. A point cloud episode has a single
+/// item keyed by the dataset name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DatasetView {
+    pub name: String,
+    pub items: IndexMap<String, MediaAnnotation>,
+}
+
+impl Predicate {
+    /// Combine two predicates that must both hold.
+    pub fn and(self, other: Self) -> Self {
+        match self {
+            Predicate::And(mut predicates) => {
+                predicates.push(other);
+                Predicate::And(predicates)
+            }
+            predicate => Predicate::And(vec![predicate, other]),
+        }
+    }
+
+    /// Combine two predicates either of which must hold.
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Predicate::Or(mut predicates) => {
+                predicates.push(other);
+                Predicate::Or(predicates)
+            }
+            predicate => Predicate::Or(vec![predicate, other]),
+        }
+    }
+
+    /// Test the predicate on an image object.
+    pub fn matches(&self, obj: &Object) -> Result<bool> {
+        self.eval(&Subject::from_object(obj))
+    }
+
+    fn eval(&self, subject: &Subject<'_>) -> Result<bool> {
+        let yes = match self {
+            Predicate::Any => true,
+            Predicate::ClassIn(classes) => subject
+                .class
+                .as_ref()
+                .is_some_and(|class| classes.contains(class.as_ref())),
+            Predicate::HasTag { name, value } => has_tag(&subject.tags, name, value.as_ref()),
+            Predicate::AreaRange { min, max } => {
+                let mut yes = false;
+                for figure in &subject.figures {
+                    let FigureRef::Geometry(geometry) = figure else {
+                        continue;
+                    };
+                    if !matches!(
+                        geometry,
+                        Geometry::Rectangle(_) | Geometry::Polygon(_) | Geometry::Bitmap(_)
+                    ) {
+                        continue;
+                    }
+                    let area = geometry.as_2d()?.area()?;
+                    if min.is_none_or(|min| area >= min.raw())
+                        && max.is_none_or(|max| area <= max.raw())
+                    {
+                        yes = true;
+                        break;
+                    }
+                }
+                yes
+            }
+            Predicate::LabelerLogin(login) => subject.labeler_logins.contains(&login.as_str()),
+            Predicate::CreatedBetween { start, end } => subject.created_at.is_some_and(|time| {
+                start.as_deref().is_none_or(|start| time >= start)
+                    && end.as_deref().is_none_or(|end| time < end)
+            }),
+            Predicate::GeometryType(shapes) => subject
+                .figures
+                .iter()
+                .any(|figure| shapes.contains(&figure.shape())),
+            Predicate::And(predicates) => {
+                for predicate in predicates {
+                    if !predicate.eval(subject)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Predicate::Or(predicates) => {
+                for predicate in predicates {
+                    if predicate.eval(subject)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            Predicate::Not(predicate) => !predicate.eval(subject)?,
+        };
+        Ok(yes)
+    }
+}
+
+impl Not for Predicate {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Predicate::Not(predicate) => *predicate,
+            predicate => Predicate::Not(Box::new(predicate)),
+        }
+    }
+}
+
+impl MediaPredicate {
+    fn eval(&self, name: &str, ann: &MediaAnnotation) -> Result<bool> {
+        let yes = match self {
+            MediaPredicate::Any => true,
+            MediaPredicate::NameIn(names) => names.contains(name),
+            MediaPredicate::HasTag { name, value } => {
+                let tags: Vec<&Tag> = match ann {
+                    MediaAnnotation::Image(ann) => ann.tags.iter().flatten().collect(),
+                    MediaAnnotation::Video(ann) => ann.tags.iter().collect(),
+                    MediaAnnotation::PointCloud(ann) => ann.tags.iter().collect(),
+                    MediaAnnotation::PointCloudEpisode(ann) => ann.tags.iter().collect(),
+                };
+                has_tag(&tags, name, value.as_ref())
+            }
+            MediaPredicate::HasObject(predicate) => {
+                for subject in subjects(ann) {
+                    if predicate.eval(&subject)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            MediaPredicate::And(predicates) => {
+                for predicate in predicates {
+                    if !predicate.eval(name, ann)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            MediaPredicate::Or(predicates) => {
+                for predicate in predicates {
+                    if predicate.eval(name, ann)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            MediaPredicate::Not(predicate) => !predicate.eval(name, ann)?,
+        };
+        Ok(yes)
+    }
+}
+
+impl Not for MediaPredicate {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        match self {
+            MediaPredicate::Not(predicate) => *predicate,
+            predicate => MediaPredicate::Not(Box::new(predicate)),
+        }
+    }
+}
+
+impl Query {
+    /// Apply the query on the annotation of a media item. Returns
+    /// `None` if the media is not selected, or the annotation with the
+    /// selected objects and their figures otherwise.
+    pub fn apply(&self, name: &str, ann: &MediaAnnotation) -> Result<Option<MediaAnnotation>> {
+        if !self.media.eval(name, ann)? {
+            return Ok(None);
+        }
+        let keep: Vec<bool> = subjects(ann)
+            .iter()
+            .map(|subject| self.objects.eval(subject))
+            .try_collect()?;
+
+        let ann = match ann {
+            MediaAnnotation::Image(ann) => filter_image(ann, &keep).into(),
+            MediaAnnotation::Video(ann) => filter_video(ann, &keep).into(),
+            MediaAnnotation::PointCloud(ann) => filter_point_cloud(ann, &keep).into(),
+            MediaAnnotation::PointCloudEpisode(ann) => filter_episode(ann, &keep).into(),
+        };
+        Ok(Some(ann))
+    }
+}
+
+impl Project {
+    /// Load the annotations selected by the query.
+    pub fn query(&self, query: &Query) -> Result<ProjectView> {
+        let mut dataset_names: Vec<&String> = self.datasets.keys().collect();
+        dataset_names.sort_unstable();
+
+        let mut datasets = IndexMap::new();
+
+        for dataset_name in dataset_names {
+            let mut items = IndexMap::new();
+            for item in self.datasets[dataset_name].iter_annotations() {
+                let item = item?;
+                if let Some(ann) = query.apply(item.media_name, &item.annotation)? {
+                    items.insert(item.media_name.to_string(), ann);
+                }
+            }
+
+            datasets.insert(
+                dataset_name.clone(),
+                DatasetView {
+                    name: dataset_name.clone(),
+                    items,
+                },
+            );
+        }
+
+        Ok(ProjectView {
+            meta: self.meta.clone(),
+            datasets,
+        })
+    }
+}
+
+/// The properties of an object tested by predicates.
+struct Subject<'a> {
+    class: Option<Cow<'a, str>>,
+    tags: Vec<&'a Tag>,
+    labeler_logins: Vec<&'a str>,
+    created_at: Option<&'a str>,
+    figures: Vec<FigureRef<'a>>,
+}
+
+enum FigureRef<'a> {
+    Geometry(&'a Geometry),
+    Shape(Shape),
+}
+
+impl FigureRef<'_> {
+    fn shape(&self) -> Shape {
+        match self {
+            FigureRef::Geometry(geometry) => geometry.shape(),
+            FigureRef::Shape(shape) => *shape,
+        }
+    }
+}
+
+impl<'a> Subject<'a> {
+    fn from_object(obj: &'a Object) -> Self {
+        Self {
+            class: obj.class_name().map(Cow::Owned),
+            tags: obj.geometry.tags().into_iter().flatten().collect(),
+            labeler_logins: obj.labeler_login.as_deref().into_iter().collect(),
+            created_at: obj.created_at.as_deref(),
+            figures: vec![FigureRef::Geometry(&obj.geometry)],
+        }
+    }
+}
+
+/// Collect the subjects of the objects in the order of the object list.
+fn subjects(ann: &MediaAnnotation) -> Vec<Subject<'_>> {
+    match ann {
+        MediaAnnotation::Image(ann) => ann.objects.iter().map(Subject::from_object).collect(),
+        MediaAnnotation::Video(ann) => ann
+            .objects
+            .iter()
+            .map(|obj| {
+                let figures: Vec<_> = ann
+                    .frames
+                    .iter()
+                    .flat_map(|frame| &frame.figures)
+                    .filter(|figure| figure.object_key == obj.key)
+                    .collect();
+                let mut tags: Vec<&Tag> = obj.tags.iter().flatten().collect();
+                tags.extend(
+                    figures
+                        .iter()
+                        .flat_map(|figure| figure.geometry.tags().into_iter().flatten()),
+                );
+                let mut labeler_logins: Vec<&str> =
+                    obj.labeler_login.as_deref().into_iter().collect();
+                labeler_logins.extend(
+                    figures
+                        .iter()
+                        .filter_map(|figure| figure.labeler_login.as_deref()),
+                );
+
+                Subject {
+                    class: obj.class_title.as_deref().map(Cow::Borrowed),
+                    tags,
+                    labeler_logins,
+                    created_at: None,
+                    figures: figures
+                        .iter()
+                        .map(|figure| FigureRef::Geometry(&figure.geometry))
+                        .collect(),
+                }
+            })
+            .collect(),
+        MediaAnnotation::PointCloud(ann) => ann
+            .objects
+            .iter()
+            .map(|obj| Subject {
+                class: Some(Cow::Borrowed(&obj.class_title)),
+                tags: obj.tags.iter().collect(),
+                labeler_logins: vec![],
+                created_at: None,
+                figures: ann
+                    .figures
+                    .iter()
+                    .filter(|figure| figure.object_key == obj.key)
+                    .map(|figure| FigureRef::Shape(figure.geometry_type))
+                    .collect(),
+            })
+            .collect(),
+        MediaAnnotation::PointCloudEpisode(ann) => ann
+            .objects
+            .iter()
+            .map(|obj| {
+                let figures: Vec<_> = ann
+                    .frames
+                    .iter()
+                    .flat_map(|frame| &frame.figures)
+                    .filter(|figure| figure.object_key == obj.key)
+                    .collect();
+                let mut tags: Vec<&Tag> = obj.tags.iter().collect();
+                tags.extend(
+                    figures
+                        .iter()
+                        .flat_map(|figure| figure.geometry.tags().into_iter().flatten()),
+                );
+
+                Subject {
+                    class: Some(Cow::Borrowed(&obj.class_title)),
+                    tags,
+                    labeler_logins: figures
+                        .iter()
+                        .filter_map(|figure| figure.labeler_login.as_deref())
+                        .collect(),
+                    created_at: None,
+                    figures: figures
+                        .iter()
+                        .map(|figure| FigureRef::Geometry(&figure.geometry))
+                        .collect(),
+                }
+            })
+            .collect(),
+    }
+}
+
+fn has_tag(tags: &[&Tag], name: &str, value: Option<&TagValue>) -> bool {
+    tags.iter()
+        .any(|tag| tag.name == name && value.is_none_or(|value| tag.value.as_ref() == Some(value)))
+}
+
+fn filter_image(ann: &ImageAnnotation, keep: &[bool]) -> ImageAnnotation {
+    ImageAnnotation {
+        objects: retained(&ann.objects, keep),
+        ..ann.clone()
+    }
+}
+
+fn filter_video(ann: &VideoAnnotation, keep: &[bool]) -> VideoAnnotation {
+    let objects = retained(&ann.objects, keep);
+    let keys: IndexSet<&str> = objects.iter().map(|obj| obj.key.as_str()).collect();
+
+    let mut ann = ann.clone();
+    for frame in &mut ann.frames {
+        frame
+            .figures
+            .retain(|figure| keys.contains(figure.object_key.as_str()));
+    }
+    VideoAnnotation { objects, ..ann }
+}
+
+fn filter_point_cloud(ann: &PointCloudAnnotation, keep: &[bool]) -> PointCloudAnnotation {
+    let objects = retained(&ann.objects, keep);
+    let keys: IndexSet<&str> = objects.iter().map(|obj| obj.key.as_str()).collect();
+
+    PointCloudAnnotation {
+        figures: ann
+            .figures
+            .iter()
+            .filter(|figure| keys.contains(figure.object_key.as_str()))
+            .cloned()
+            .collect(),
+        objects,
+        ..ann.clone()
+    }
+}
+
+fn filter_episode(ann: &PointCloudEpisodeAnnotation, keep: &[bool]) -> PointCloudEpisodeAnnotation {
+    let objects = retained(&ann.objects, keep);
+    let keys: IndexSet<&str> = objects.iter().map(|obj| obj.key.as_str()).collect();
+
+    let mut ann = ann.clone();
+    for frame in &mut ann.frames {
+        frame
+            .figures
+            .retain(|figure| keys.contains(figure.object_key.as_str()));
+    }
+    PointCloudEpisodeAnnotation { objects, ..ann }
+}
+
+fn retained<T: Clone>(items: &[T], keep: &[bool]) -> Vec<T> {
+    items
+        .iter()
+        .zip(keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(item, _)| item.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Points, RectangleGeometry, Size, VideoObject};
+    use noisy_float::types::r64;
+
+    fn object(id: usize, class: &str, login: &str, created_at: &str, width: f64) -> Object {
+        Object {
+            id,
+            class_id: None,
+            class_title: Some(class.to_string()),
+            labeler_login: Some(login.to_string()),
+            created_at: Some(created_at.to_string()),
+            updated_at: None,
+            geometry: Geometry::Rectangle(RectangleGeometry {
+                tags: None,
+                points: Points {
+                    exterior: vec![(r64(0.0), r64(0.0)), (r64(width), r64(10.0))],
+                    interior: vec![],
+                },
+            }),
+        }
+    }
+
+    fn image_annotation() -> MediaAnnotation {
+        ImageAnnotation {
+            name: "image.png".to_string(),
+            description: None,
+            size: Size {
+                width: 100,
+                height: 100,
+            },
+            tags: None,
+            objects: vec![
+                object(1, "car", "alice", "2024-01-01T00:00:00.000Z", 10.0),
+                object(2, "car", "bob", "2024-02-01T00:00:00.000Z", 50.0),
+                object(3, "dog", "alice", "2024-03-01T00:00:00.000Z", 5.0),
+            ],
+        }
+        .into()
+    }
+
+    fn kept_ids(query: &Query, ann: &MediaAnnotation) -> Option<Vec<usize>> {
+        let MediaAnnotation::Image(ann) = query.apply("image.png", ann).unwrap()? else {
+            unreachable!();
+        };
+        Some(ann.objects.iter().map(|obj| obj.id).collect())
+    }
+
+    fn classes(classes: &[&str]) -> Predicate {
+        Predicate::ClassIn(classes.iter().map(|class| class.to_string()).collect())
+    }
+
+    #[test]
+    fn combine_object_predicates() {
+        let ann = image_annotation();
+        let query = |objects| Query {
+            media: MediaPredicate::Any,
+            objects,
+        };
+
+        let cars_by_alice = classes(&["car"]).and(Predicate::LabelerLogin("alice".to_string()));
+        assert_eq!(kept_ids(&query(cars_by_alice), &ann), Some(vec![1]));
+
+        let large_or_dog = Predicate::AreaRange {
+            min: Some(r64(400.0)),
+            max: None,
+        }
+        .or(classes(&["dog"]));
+        assert_eq!(kept_ids(&query(large_or_dog), &ann), Some(vec![2, 3]));
+
+        let not_car = !classes(&["car"]);
+        assert_eq!(kept_ids(&query(not_car.clone()), &ann), Some(vec![3]));
+        assert_eq!(!not_car, classes(&["car"]));
+    }
+
+    #[test]
+    fn combine_media_predicates() {
+        let ann = image_annotation();
+        let query = |media| Query {
+            media,
+            objects: Predicate::Any,
+        };
+        let has_dog = MediaPredicate::HasObject(classes(&["dog"]));
+        let has_cat = MediaPredicate::HasObject(classes(&["cat"]));
+
+        assert_eq!(kept_ids(&query(has_dog.clone()), &ann), Some(vec![1, 2, 3]));
+        assert_eq!(kept_ids(&query(has_cat.clone()), &ann), None);
+        assert_eq!(
+            kept_ids(
+                &query(MediaPredicate::Or(vec![has_cat.clone(), has_dog.clone()])),
+                &ann
+            ),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(
+            kept_ids(
+                &query(MediaPredicate::And(vec![has_cat.clone(), has_dog])),
+                &ann
+            ),
+            None
+        );
+        assert!(kept_ids(&query(!has_cat), &ann).is_some());
+    }
+
+    #[test]
+    fn created_between_on_image_objects() {
+        let ann = image_annotation();
+        let query = Query {
+            media: MediaPredicate::Any,
+            objects: Predicate::CreatedBetween {
+                start: Some("2024-02-01".to_string()),
+                end: Some("2024-03-01".to_string()),
+            },
+        };
+        assert_eq!(kept_ids(&query, &ann), Some(vec![2]));
+    }
+
+    #[test]
+    fn created_between_never_holds_on_video_objects() {
+        let ann: MediaAnnotation = VideoAnnotation {
+            objects: vec![VideoObject {
+                key: "key".to_string(),
+                class_title: Some("car".to_string()),
+                tags: None,
+                labeler_login: Some("alice".to_string()),
+            }],
+            ..Default::default()
+        }
+        .into();
+        let query = |objects| Query {
+            media: MediaPredicate::Any,
+            objects,
+        };
+        let num_objects = |query: &Query| {
+            query
+                .apply("video.mp4", &ann)
+                .unwrap()
+                .unwrap()
+                .num_objects()
+        };
+
+        let created = Predicate::CreatedBetween {
+            start: None,
+            end: None,
+        };
+        assert_eq!(num_objects(&query(created)), 0);
+        assert_eq!(num_objects(&query(classes(&["car"]))), 1);
+    }
+}
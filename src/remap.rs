@@ -0,0 +1,477 @@
+use crate::{
+    utils::load_json, AnnotationItem, ClassMeta, DatasetKind, Error, Geometry, Geometry2D,
+    ImageAnnotation, MediaAnnotation, PointCloudAnnotation, PointCloudEpisodeAnnotation,
+    PolygonGeometry, Project, ProjectMeta, Result, Shape, VideoAnnotation,
+};
+use indexmap::IndexMap;
+use noisy_float::types::r64;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+use tracing::warn;
+
+/// A table renaming class titles.
+///
+/// Several source titles may map to the same target title, in which
+/// case the classes are merged. Titles absent in the table are kept.
+/// Mappings are not chained, so a target title may not be renamed again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ClassMapping {
+    /// The target title of each source title.
+    pub titles: IndexMap<String, String>,
+}
+
+impl ClassMapping {
+    /// Load the mapping from a JSON object of source and target titles.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        load_json(path)
+    }
+
+    /// Add a source title mapped to a target title.
+    pub fn insert<S, T>(&mut self, source: S, target: T)
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        self.titles.insert(source.into(), target.into());
+    }
+
+    /// Get the target title of a class title.
+    pub fn target<'a>(&'a self, title: &'a str) -> &'a str {
+        self.titles.get(title).map(String::as_str).unwrap_or(title)
+    }
+
+    /// Rename and merge the classes in the project meta.
+    ///
+    /// Merged classes keep the first available color and id. Classes of
+    /// the same shape are merged directly, and rectangles merged with
+    /// polygons become polygons. Other shape combinations are rejected.
+    pub fn remap_meta(&self, meta: &ProjectMeta) -> Result<ClassRemap> {
+        for (source, target) in &self.titles {
+            let next = self.target(target);
+            if next != target {
+                return Err(Error::ChainedClassMapping {
+                    title: source.clone(),
+                    target: target.clone(),
+                    next: next.to_string(),
+                });
+            }
+        }
+
+        for source in self.titles.keys() {
+            if !meta.classes.iter().any(|class| &class.title == source) {
+                warn!("class '{source}' in the mapping is missing in the project meta");
+            }
+        }
+
+        let mut classes: IndexMap<String, ClassMeta> = IndexMap::new();
+        let mut to_polygon = HashSet::new();
+
+        for class in &meta.classes {
+            let target = self.target(&class.title);
+
+            let Some(merged) = classes.get_mut(target) else {
+                classes.insert(
+                    target.to_string(),
+                    ClassMeta {
+                        title: target.to_string(),
+                        ..class.clone()
+                    },
+                );
+                continue;
+            };
+
+            merged.shape = match (merged.shape, class.shape) {
+                (lhs, rhs) if lhs == rhs => lhs,
+                (Shape::Rectangle, Shape::Polygon) | (Shape::Polygon, Shape::Rectangle) => {
+                    to_polygon.insert(target.to_string());
+                    Shape::Polygon
+                }
+                (lhs, rhs) => {
+                    return Err(Error::IncompatibleClassShapes {
+                        title: target.to_string(),
+                        lhs,
+                        rhs,
+                    })
+                }
+            };
+            merged.color = merged.color.or(class.color);
+            merged.id = merged.id.or(class.id);
+        }
+
+        let titles: HashMap<String, String> = meta
+            .classes
+            .iter()
+            .map(|class| (class.title.clone(), self.target(&class.title).to_string()))
+            .collect();
+        let ids: HashMap<usize, &str> = meta
+            .classes
+            .iter()
+            .filter_map(|class| Some((class.id?, self.target(&class.title))))
+            .collect();
+        let ids = ids
+            .into_iter()
+            .map(|(id, target)| (id, (target.to_string(), classes[target].id)))
+            .collect();
+
+        Ok(ClassRemap {
+            meta: ProjectMeta {
+                classes: classes.into_values().collect(),
+                ..meta.clone()
+            },
+            titles,
+            ids,
+            to_polygon,
+        })
+    }
+}
+
+/// The remapped project meta along with the rules to rewrite
+/// annotations accordingly.
+#[derive(Debug, Clone)]
+pub struct ClassRemap {
+    pub meta: ProjectMeta,
+    /// The target title of each source title.
+    titles: HashMap<String, String>,
+    /// The target title and id of each source class id.
+    ids: HashMap<usize, (String, Option<usize>)>,
+    /// The target titles whose rectangles are converted to polygons.
+    to_polygon: HashSet<String>,
+}
+
+impl ClassRemap {
+    /// Rewrite the class references of an annotation of any kind.
+    pub fn remap(&self, ann: &mut MediaAnnotation) {
+        match ann {
+            MediaAnnotation::Image(ann) => self.remap_image(ann),
+            MediaAnnotation::Video(ann) => self.remap_video(ann),
+            MediaAnnotation::PointCloud(ann) => self.remap_point_cloud(ann),
+            MediaAnnotation::PointCloudEpisode(ann) => self.remap_episode(ann),
+        }
+    }
+
+    /// Rewrite the class titles and ids of image objects.
+    pub fn remap_image(&self, ann: &mut ImageAnnotation) {
+        for obj in &mut ann.objects {
+            let (title, id) = match (&obj.class_title, obj.class_id) {
+                (Some(title), _) => {
+                    let title = self.target(title);
+                    let id = self.class_id(title);
+                    (Some(title.to_string()), id.or(obj.class_id))
+                }
+                (None, Some(id)) => match self.ids.get(&id) {
+                    Some((title, new_id)) => (Some(title.clone()), new_id.or(Some(id))),
+                    None => (None, Some(id)),
+                },
+                (None, None) => (None, None),
+            };
+
+            if let Some(title) = &title {
+                self.convert_geometry(title, &mut obj.geometry);
+            }
+            obj.class_title = title;
+            obj.class_id = id;
+        }
+    }
+
+    /// Rewrite the class titles of video objects.
+    pub fn remap_video(&self, ann: &mut VideoAnnotation) {
+        let mut converted = HashSet::new();
+        for obj in &mut ann.objects {
+            if let Some(title) = &mut obj.class_title {
+                *title = self.target(title).to_string();
+                if self.to_polygon.contains(title.as_str()) {
+                    converted.insert(obj.key.clone());
+                }
+            }
+        }
+
+        for figure in ann.frames.iter_mut().flat_map(|frame| &mut frame.figures) {
+            if converted.contains(&figure.object_key) {
+                rectangle_to_polygon(&mut figure.geometry);
+            }
+        }
+    }
+
+    /// Rewrite the class titles of point cloud objects.
+    pub fn remap_point_cloud(&self, ann: &mut PointCloudAnnotation) {
+        for obj in &mut ann.objects {
+            obj.class_title = self.target(&obj.class_title).to_string();
+        }
+    }
+
+    /// Rewrite the class titles of episode objects and figures.
+    pub fn remap_episode(&self, ann: &mut PointCloudEpisodeAnnotation) {
+        for obj in &mut ann.objects {
+            obj.class_title = self.target(&obj.class_title).to_string();
+        }
+        for figure in ann.frames.iter_mut().flat_map(|frame| &mut frame.figures) {
+            if let Some(title) = &mut figure.class_title {
+                *title = self.target(title).to_string();
+                self.convert_geometry(title, &mut figure.geometry);
+            }
+        }
+    }
+
+    fn target<'a>(&'a self, title: &'a str) -> &'a str {
+        self.titles.get(title).map(String::as_str).unwrap_or(title)
+    }
+
+    fn class_id(&self, title: &str) -> Option<usize> {
+        self.meta
+            .classes
+            .iter()
+            .find(|class| class.title == title)
+            .and_then(|class| class.id)
+    }
+
+    fn convert_geometry(&self, title: &str, geometry: &mut Geometry) {
+        if self.to_polygon.contains(title) {
+            rectangle_to_polygon(geometry);
+        }
+    }
+}
+
+fn rectangle_to_polygon(geometry: &mut Geometry) {
+    let Geometry::Rectangle(rect) = geometry else {
+        return;
+    };
+    let Some(bbox) = rect.bbox().ok().flatten() else {
+        return;
+    };
+    let exterior = [
+        (bbox.min_x, bbox.min_y),
+        (bbox.max_x, bbox.min_y),
+        (bbox.max_x, bbox.max_y),
+        (bbox.min_x, bbox.max_y),
+    ];
+
+    let mut points = rect.points.clone();
+    points.exterior = exterior
+        .into_iter()
+        .map(|(x, y)| (r64(x), r64(y)))
+        .collect();
+    *geometry = PolygonGeometry {
+        tags: rect.tags.clone(),
+        points,
+    }
+    .into();
+}
+
+impl Project {
+    /// Rename and merge classes, and rewrite the project meta and all
+    /// annotation files in place through the project storage.
+    ///
+    /// All annotations are loaded and remapped before any file is
+    /// written.
+    pub fn remap_classes(&mut self, mapping: &ClassMapping) -> Result<ClassRemap> {
+        let remap = mapping.remap_meta(&self.meta)?;
+
+        // Remap every annotation before writing any of them, so that a
+        // failure to load one leaves the project untouched.
+        let mut items = vec![];
+        for item in self.iter_annotations() {
+            let AnnotationItem {
+                dataset,
+                media_name,
+                mut annotation,
+            } = item?;
+            remap.remap(&mut annotation);
+            items.push((dataset.to_string(), media_name.to_string(), annotation));
+        }
+
+        for (dataset_name, media_name, annotation) in items {
+            let not_found = || Error::MediaNotFound {
+                dataset: dataset_name.clone(),
+                name: media_name.clone(),
+            };
+            let dataset = self.datasets.get_mut(&dataset_name).ok_or_else(not_found)?;
+            match (&mut dataset.kind, annotation) {
+                (
+                    DatasetKind::PointCloudEpisode(dataset),
+                    MediaAnnotation::PointCloudEpisode(ann),
+                ) => {
+                    dataset.annotation = ann;
+                    dataset.save_annotation()?;
+                }
+                (kind, annotation) => {
+                    let media = kind
+                        .as_classical()
+                        .and_then(|dataset| dataset.get_media(&media_name))
+                        .ok_or_else(not_found)?;
+                    media.write_ann(&annotation)?;
+                }
+            }
+        }
+
+        self.meta = remap.meta.clone();
+        self.save_meta()?;
+
+        Ok(remap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryStorage, Storage};
+    use std::sync::Arc;
+
+    fn class(id: usize, title: &str, shape: Shape) -> ClassMeta {
+        ClassMeta {
+            id: Some(id),
+            ..ClassMeta::new(title.to_string(), shape)
+        }
+    }
+
+    fn meta() -> ProjectMeta {
+        ProjectMeta {
+            classes: vec![
+                class(1, "car", Shape::Rectangle),
+                class(2, "truck", Shape::Polygon),
+                class(3, "person", Shape::Bitmap),
+            ],
+            tags: vec![],
+        }
+    }
+
+    fn mapping(pairs: &[(&str, &str)]) -> ClassMapping {
+        let mut mapping = ClassMapping::default();
+        for (source, target) in pairs {
+            mapping.insert(*source, *target);
+        }
+        mapping
+    }
+
+    const ANNOTATION: &str = r#"{
+        "name": "a.png",
+        "size": {"width": 10, "height": 10},
+        "tags": [],
+        "objects": [
+            {
+                "id": 1,
+                "classId": 1,
+                "classTitle": null,
+                "labelerLogin": null,
+                "createdAt": null,
+                "updatedAt": null,
+                "geometryType": "rectangle",
+                "tags": [],
+                "points": {"exterior": [[1, 2], [3, 4]], "interior": []}
+            },
+            {
+                "id": 2,
+                "classId": 2,
+                "classTitle": "truck",
+                "labelerLogin": null,
+                "createdAt": null,
+                "updatedAt": null,
+                "geometryType": "polygon",
+                "tags": [],
+                "points": {"exterior": [[0, 0], [5, 0], [0, 5]], "interior": []}
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn remap_meta_merges_classes() {
+        let mapping = mapping(&[("car", "vehicle"), ("truck", "vehicle")]);
+        let remap = mapping.remap_meta(&meta()).unwrap();
+
+        let classes: Vec<(&str, Shape, Option<usize>)> = remap
+            .meta
+            .classes
+            .iter()
+            .map(|class| (class.title.as_str(), class.shape, class.id))
+            .collect();
+        assert_eq!(
+            classes,
+            [
+                ("vehicle", Shape::Polygon, Some(1)),
+                ("person", Shape::Bitmap, Some(3))
+            ]
+        );
+    }
+
+    #[test]
+    fn remap_meta_rejects_incompatible_shapes() {
+        let mapping = mapping(&[("car", "thing"), ("person", "thing")]);
+        assert!(matches!(
+            mapping.remap_meta(&meta()),
+            Err(Error::IncompatibleClassShapes { title, .. }) if title == "thing"
+        ));
+    }
+
+    #[test]
+    fn remap_meta_rejects_chained_mappings() {
+        let chained = mapping(&[("car", "truck"), ("truck", "vehicle")]);
+        assert!(matches!(
+            chained.remap_meta(&meta()),
+            Err(Error::ChainedClassMapping { title, target, next })
+                if title == "car" && target == "truck" && next == "vehicle"
+        ));
+
+        // Mapping a target title to itself is not a chain.
+        let identity = mapping(&[("car", "truck"), ("truck", "truck")]);
+        assert!(identity.remap_meta(&meta()).is_ok());
+    }
+
+    #[test]
+    fn remap_image_objects() {
+        let mapping = mapping(&[("car", "vehicle"), ("truck", "vehicle")]);
+        let remap = mapping.remap_meta(&meta()).unwrap();
+
+        let mut ann: ImageAnnotation = serde_json::from_str(ANNOTATION).unwrap();
+        remap.remap_image(&mut ann);
+
+        for obj in &ann.objects {
+            assert_eq!(obj.class_title.as_deref(), Some("vehicle"));
+            assert_eq!(obj.class_id, Some(1));
+            assert!(matches!(obj.geometry, Geometry::Polygon(_)));
+        }
+        let Geometry::Polygon(polygon) = &ann.objects[0].geometry else {
+            unreachable!();
+        };
+        let exterior: Vec<(f64, f64)> = polygon
+            .points
+            .exterior
+            .iter()
+            .map(|(x, y)| (x.raw(), y.raw()))
+            .collect();
+        assert_eq!(exterior, [(1.0, 2.0), (3.0, 2.0), (3.0, 4.0), (1.0, 4.0)]);
+    }
+
+    #[test]
+    fn remap_classes_rewrites_the_project() {
+        let storage = Arc::new(MemoryStorage::new());
+        storage
+            .insert("meta.json", serde_json::to_vec(&meta()).unwrap())
+            .unwrap();
+        storage.insert("ds/img/a.png", []).unwrap();
+        storage.insert("ds/ann/a.png.json", ANNOTATION).unwrap();
+        let mut project =
+            Project::open_with(storage.clone() as Arc<dyn Storage>, Path::new("")).unwrap();
+
+        let mapping = mapping(&[("car", "vehicle"), ("truck", "vehicle")]);
+        let remap = project.remap_classes(&mapping).unwrap();
+        assert_eq!(project.meta, remap.meta);
+
+        let meta: ProjectMeta = serde_json::from_slice(&storage.get("meta.json").unwrap()).unwrap();
+        assert_eq!(meta, remap.meta);
+
+        let ann: ImageAnnotation =
+            serde_json::from_slice(&storage.get("ds/ann/a.png.json").unwrap()).unwrap();
+        let titles: Vec<Option<&str>> = ann
+            .objects
+            .iter()
+            .map(|obj| obj.class_title.as_deref())
+            .collect();
+        assert_eq!(titles, [Some("vehicle"), Some("vehicle")]);
+    }
+}
@@ -0,0 +1,435 @@
+use crate::{
+    utils::{copy_file, copy_tree, load_json, save_json, write_json},
+    DatasetKind, Error, FsStorage, Project, Result, Storage, ITEM_META_DIR,
+};
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, sync::Arc};
+use tracing::warn;
+
+/// The options for splitting a project.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitConfig {
+    /// The names and relative sizes of the splits. Ratios need not sum
+    /// up to 1.
+    pub ratios: IndexMap<String, f64>,
+    /// The seed of the random shuffle.
+    pub seed: u64,
+    /// Balance the classes across splits. Each media item is assigned
+    /// by its rarest class.
+    pub stratify: bool,
+}
+
+impl Default for SplitConfig {
+    fn default() -> Self {
+        Self {
+            ratios: [("train", 0.8), ("val", 0.1), ("test", 0.1)]
+                .into_iter()
+                .map(|(name, ratio)| (name.to_string(), ratio))
+                .collect(),
+            seed: 0,
+            stratify: false,
+        }
+    }
+}
+
+/// The split assigned to each media item, keyed by dataset and media
+/// names.
+///
+/// Videos and point cloud episodes are never divided. An episode is a
+/// single item keyed by its dataset name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SplitManifest {
+    pub datasets: IndexMap<String, IndexMap<String, String>>,
+}
+
+impl Project {
+    /// Partition the media items into splits.
+    ///
+    /// It fails if there are no splits or any ratio is not a finite
+    /// positive number.
+    pub fn split(&self, config: &SplitConfig) -> Result<SplitManifest> {
+        if config.ratios.is_empty() {
+            return Err(Error::NoSplits);
+        }
+        for (name, &ratio) in &config.ratios {
+            if !(ratio.is_finite() && ratio > 0.0) {
+                return Err(Error::InvalidSplitRatio {
+                    name: name.clone(),
+                    ratio,
+                });
+            }
+        }
+
+        // Collect the classes present in each media item.
+        let mut items: Vec<(&str, String, IndexSet<String>)> = vec![];
+        for item in self.iter_annotations() {
+            let item = item?;
+            let classes = item.annotation.object_classes().into_iter().flatten();
+            items.push((item.dataset, item.media_name.to_string(), classes.collect()));
+        }
+
+        let mut rng = SplitMix64::new(config.seed);
+        rng.shuffle(&mut items);
+
+        // Group the items by their rarest class when stratified.
+        let mut groups: IndexMap<Option<&str>, Vec<usize>> = IndexMap::new();
+        if config.stratify {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for (_, _, classes) in &items {
+                for class in classes {
+                    *counts.entry(class).or_default() += 1;
+                }
+            }
+            for (index, (_, _, classes)) in items.iter().enumerate() {
+                let rarest = classes
+                    .iter()
+                    .map(String::as_str)
+                    .min_by_key(|class| (counts[class], *class));
+                groups.entry(rarest).or_default().push(index);
+            }
+        } else {
+            groups.insert(None, (0..items.len()).collect());
+        }
+
+        let split_names: Vec<&String> = config.ratios.keys().collect();
+        let total_ratio: f64 = config.ratios.values().sum();
+        let ratios: Vec<f64> = config
+            .ratios
+            .values()
+            .map(|ratio| ratio / total_ratio)
+            .collect();
+
+        // Each item goes to the split lagging furthest behind its target
+        // size within the group, and then within all items.
+        let mut assignment = vec![0; items.len()];
+        let mut total_counts = vec![0; ratios.len()];
+        let mut num_assigned = 0;
+        for indices in groups.values() {
+            let mut counts = vec![0; ratios.len()];
+            for (nth, &index) in indices.iter().enumerate() {
+                let deficit = |split: usize| {
+                    let group = ratios[split] * (nth + 1) as f64 - counts[split] as f64;
+                    let total =
+                        ratios[split] * (num_assigned + 1) as f64 - total_counts[split] as f64;
+                    (group, total)
+                };
+                let split = (0..ratios.len())
+                    .max_by(|&lhs, &rhs| {
+                        let (lgroup, ltotal) = deficit(lhs);
+                        let (rgroup, rtotal) = deficit(rhs);
+                        lgroup
+                            .total_cmp(&rgroup)
+                            .then(ltotal.total_cmp(&rtotal))
+                            .then(rhs.cmp(&lhs))
+                    })
+                    .unwrap_or(0);
+                counts[split] += 1;
+                total_counts[split] += 1;
+                num_assigned += 1;
+                assignment[index] = split;
+            }
+        }
+
+        let mut manifest = SplitManifest::default();
+        for ((dataset_name, media_name, _), split) in items.into_iter().zip(assignment) {
+            manifest
+                .datasets
+                .entry(dataset_name.to_string())
+                .or_default()
+                .insert(media_name, split_names[split].to_string());
+        }
+        for items in manifest.datasets.values_mut() {
+            items.sort_unstable_keys();
+        }
+        manifest.datasets.sort_unstable_keys();
+
+        Ok(manifest)
+    }
+}
+
+impl SplitManifest {
+    /// Load the manifest from a JSON file.
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        load_json(path)
+    }
+
+    /// Save the manifest to a JSON file.
+    pub fn save<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        save_json(path, self)
+    }
+
+    /// Get the split of a media item.
+    pub fn get(&self, dataset_name: &str, media_name: &str) -> Option<&str> {
+        Some(self.datasets.get(dataset_name)?.get(media_name)?.as_str())
+    }
+
+    /// Write a new project with a dataset for each split.
+    ///
+    /// Images, videos and point clouds go to the dataset named after
+    /// their split, suffixed by the media kind if the project mixes
+    /// kinds. Media names colliding across source datasets are prefixed
+    /// by the source dataset name. Each episode becomes a dataset named
+    /// `{split}_{dataset}`. Nested dataset names are flattened with `_`.
+    /// The source project is read through its storage.
+    pub fn write_project<P>(&self, project: &Project, output_dir: P) -> Result<Project>
+    where
+        P: AsRef<Path>,
+    {
+        self.write_project_with(project, Arc::new(FsStorage), output_dir.as_ref())
+    }
+
+    /// Write a new project with a dataset for each split to a directory
+    /// of a storage. See [`Self::write_project`].
+    pub fn write_project_with(
+        &self,
+        project: &Project,
+        storage: Arc<dyn Storage>,
+        output_dir: &Path,
+    ) -> Result<Project> {
+        let source = &*project.storage;
+
+        let kinds: IndexSet<&str> = project
+            .datasets
+            .values()
+            .filter_map(|dataset| dataset.kind.as_classical())
+            .map(|dataset| dataset.media_kind().dir_name())
+            .collect();
+        let mut used_names: HashMap<String, IndexSet<String>> = HashMap::new();
+
+        for (dataset_name, items) in &self.datasets {
+            let Some(dataset) = project.datasets.get(dataset_name) else {
+                warn!("dataset '{dataset_name}' is missing in the project");
+                continue;
+            };
+            let src_dir = dataset.kind.dataset_dir();
+
+            if let DatasetKind::PointCloudEpisode(_) = &dataset.kind {
+                if let Some(split) = items.get(dataset_name) {
+                    let flat_name = dataset_name.replace('/', "_");
+                    copy_tree(
+                        source,
+                        src_dir,
+                        &*storage,
+                        &output_dir.join(format!("{split}_{flat_name}")),
+                    )?;
+                }
+                continue;
+            }
+            let Some(classical) = dataset.kind.as_classical() else {
+                continue;
+            };
+            let media_kind = classical.media_kind();
+            let media_dir = media_kind.dir_name();
+
+            for (media_name, split) in items {
+                let target_name = if kinds.len() > 1 {
+                    format!("{split}_{media_dir}")
+                } else {
+                    split.clone()
+                };
+                let used = used_names.entry(target_name.clone()).or_default();
+                let new_name = if used.contains(media_name) {
+                    let flat_name = dataset_name.replace('/', "_");
+                    format!("{flat_name}_{media_name}")
+                } else {
+                    media_name.clone()
+                };
+                used.insert(new_name.clone());
+
+                let dst_dir = output_dir.join(&target_name);
+                copy_file(
+                    source,
+                    &src_dir.join(media_dir).join(media_name),
+                    &*storage,
+                    &dst_dir.join(media_dir).join(&new_name),
+                )?;
+                copy_file(
+                    source,
+                    &src_dir.join("ann").join(format!("{media_name}.json")),
+                    &*storage,
+                    &dst_dir.join("ann").join(format!("{new_name}.json")),
+                )?;
+
+                for item_dir in [media_kind.info_dir_name(), ITEM_META_DIR] {
+                    let src = src_dir.join(item_dir).join(format!("{media_name}.json"));
+                    if source.exists(&src) {
+                        let dst = dst_dir.join(item_dir).join(format!("{new_name}.json"));
+                        copy_file(source, &src, &*storage, &dst)?;
+                    }
+                }
+
+                if let DatasetKind::PointCloud(_) = &dataset.kind {
+                    let related_dir =
+                        |name: &str| Path::new("related_images").join(name.replace('.', "_"));
+                    let src = src_dir.join(related_dir(media_name));
+                    if source.exists(&src) {
+                        copy_tree(
+                            source,
+                            &src,
+                            &*storage,
+                            &dst_dir.join(related_dir(&new_name)),
+                        )?;
+                    }
+                }
+            }
+        }
+
+        write_json(&*storage, &output_dir.join("meta.json"), &project.meta)?;
+
+        // Keep the ids of the keys that go along with the split media.
+        let mut split_project = Project::open_with(storage, output_dir)?;
+        if let Some(map) = &project.key_id_map {
+            split_project.key_id_map = Some(map.clone());
+            split_project.regenerate_key_id_map()?;
+            split_project.save_key_id_map()?;
+        }
+        Ok(split_project)
+    }
+}
+
+/// The SplitMix64 generator, which is small and reproducible across
+/// platforms.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Shuffle the slice by the Fisher-Yates algorithm.
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for index in (1..items.len()).rev() {
+            let other = (self.next_u64() % (index as u64 + 1)) as usize;
+            items.swap(index, other);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStorage;
+
+    fn project(num_images: usize) -> Project {
+        let storage = MemoryStorage::new();
+        storage
+            .insert("meta.json", r#"{"classes": [], "tags": []}"#)
+            .unwrap();
+        for index in 0..num_images {
+            let name = format!("{index:02}.png");
+            let ann = format!(
+                r#"{{"name": "{name}", "size": {{"width": 10, "height": 10}}, "tags": [], "objects": []}}"#
+            );
+            storage.insert(format!("ds/img/{name}"), []).unwrap();
+            storage.insert(format!("ds/ann/{name}.json"), ann).unwrap();
+        }
+        Project::open_with(Arc::new(storage), Path::new("")).unwrap()
+    }
+
+    #[test]
+    fn split_mix64_is_deterministic() {
+        let mut rng = SplitMix64::new(0);
+        let values: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+        assert_eq!(
+            values,
+            [0xe220a8397b1dcdaf, 0x6e789e6aa1b965f4, 0x06c45d188009454f]
+        );
+
+        let shuffle = |seed| {
+            let mut items: Vec<usize> = (0..10).collect();
+            SplitMix64::new(seed).shuffle(&mut items);
+            items
+        };
+        assert_eq!(shuffle(42), shuffle(42));
+        assert_ne!(shuffle(42), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn split_is_reproducible() {
+        let project = project(10);
+        let config = SplitConfig {
+            seed: 7,
+            ..SplitConfig::default()
+        };
+        let manifest = project.split(&config).unwrap();
+        assert_eq!(manifest, project.split(&config).unwrap());
+
+        let mut counts: IndexMap<&str, usize> = IndexMap::new();
+        for split in manifest.datasets["ds"].values() {
+            *counts.entry(split).or_default() += 1;
+        }
+        assert_eq!(counts.get("train"), Some(&8));
+        assert_eq!(counts.get("val"), Some(&1));
+        assert_eq!(counts.get("test"), Some(&1));
+    }
+
+    #[test]
+    fn split_rejects_invalid_ratios() {
+        let project = project(1);
+
+        let config = SplitConfig {
+            ratios: IndexMap::new(),
+            ..SplitConfig::default()
+        };
+        assert!(matches!(project.split(&config), Err(Error::NoSplits)));
+
+        for ratio in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let config = SplitConfig {
+                ratios: [("train".to_string(), ratio)].into_iter().collect(),
+                ..SplitConfig::default()
+            };
+            assert!(matches!(
+                project.split(&config),
+                Err(Error::InvalidSplitRatio { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn write_split_project_to_memory_storage() {
+        let project = project(10);
+        let manifest = project.split(&SplitConfig::default()).unwrap();
+
+        let storage = Arc::new(MemoryStorage::new());
+        let split_project = manifest
+            .write_project_with(&project, storage.clone(), Path::new("out"))
+            .unwrap();
+
+        let mut names: Vec<&String> = split_project.datasets.keys().collect();
+        names.sort_unstable();
+        assert_eq!(names, ["test", "train", "val"]);
+        for (dataset_name, items) in &manifest.datasets {
+            for (media_name, split) in items {
+                assert_eq!(dataset_name, "ds");
+                assert!(storage
+                    .get(Path::new("out").join(split).join("img").join(media_name))
+                    .is_some());
+            }
+        }
+
+        let num_images: usize = split_project
+            .iter_annotations()
+            .map(|item| item.map(|_| 1))
+            .sum::<Result<_>>()
+            .unwrap();
+        assert_eq!(num_images, 10);
+    }
+}
@@ -0,0 +1,409 @@
+use crate::{
+    Dataset, DatasetKind, Geometry, MediaAnnotation, Project, Result, Shape, Size, Tag, TagValue,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// The summary statistics of a project.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectStats {
+    /// The statistics over all datasets.
+    pub total: DatasetStats,
+    pub datasets: IndexMap<String, DatasetStats>,
+}
+
+/// The summary statistics of annotated media.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatasetStats {
+    pub num_images: usize,
+    pub num_videos: usize,
+    pub num_point_clouds: usize,
+    pub num_episodes: usize,
+    pub num_objects: usize,
+    /// The number of figures in videos, point clouds and episodes. Each
+    /// image object counts as one figure.
+    pub num_figures: usize,
+    /// The number of objects of each class.
+    pub classes: IndexMap<String, usize>,
+    /// The number of figures of each geometry type.
+    pub geometry_types: IndexMap<Shape, usize>,
+    pub tags: IndexMap<String, TagStats>,
+    /// The number of objects or figures created by each labeler.
+    pub labelers: IndexMap<String, usize>,
+    /// The distribution of the number of objects in each image, video,
+    /// point cloud or episode.
+    pub objects_per_item: Summary,
+    /// The distribution of the number of frames in videos and episodes.
+    pub frames_per_item: Summary,
+    pub object_sizes: ObjectSizeStats,
+    /// The number of bitmap figures whose mask cannot be decoded. They
+    /// are left out of the object sizes.
+    pub num_undecodable_figures: usize,
+}
+
+/// The occurrences of a tag on media, objects and figures.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagStats {
+    pub count: usize,
+    /// The number of occurrences of each value. Tags without value are
+    /// not listed.
+    pub values: IndexMap<String, usize>,
+}
+
+/// The sizes of image and video figures relative to the media size.
+///
+/// Only rectangles, polygons and bitmaps are measured.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObjectSizeStats {
+    /// The figure area over the media area.
+    pub relative_area: Summary,
+    /// The bounding box width over the media width.
+    pub relative_width: Summary,
+    /// The bounding box height over the media height.
+    pub relative_height: Summary,
+}
+
+/// The summary of a distribution of values.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+}
+
+impl Summary {
+    fn new(mut values: Vec<f64>) -> Self {
+        values.sort_by(f64::total_cmp);
+        let count = values.len();
+        let median = match count {
+            0 => None,
+            count if count % 2 == 1 => Some(values[count / 2]),
+            count => Some((values[count / 2 - 1] + values[count / 2]) / 2.0),
+        };
+
+        Self {
+            count,
+            min: values.first().copied(),
+            max: values.last().copied(),
+            mean: (count > 0).then(|| values.iter().sum::<f64>() / count as f64),
+            median,
+        }
+    }
+}
+
+impl Project {
+    /// Compute the summary statistics of all datasets.
+    ///
+    /// All annotation files are loaded, so this may take a while on
+    /// large projects.
+    pub fn stats(&self) -> Result<ProjectStats> {
+        let mut dataset_names: Vec<&String> = self.datasets.keys().collect();
+        dataset_names.sort_unstable();
+
+        let mut total = StatsBuilder::default();
+        let mut datasets = IndexMap::new();
+
+        for name in dataset_names {
+            let mut builder = StatsBuilder::default();
+            builder.add_dataset(&self.datasets[name])?;
+            total.merge(&builder);
+            datasets.insert(name.clone(), builder.finish());
+        }
+
+        Ok(ProjectStats {
+            total: total.finish(),
+            datasets,
+        })
+    }
+}
+
+impl Dataset {
+    /// Compute the summary statistics of the dataset.
+    pub fn stats(&self) -> Result<DatasetStats> {
+        let mut builder = StatsBuilder::default();
+        builder.add_dataset(self)?;
+        Ok(builder.finish())
+    }
+}
+
+/// Accumulates the raw values before they are summarized.
+#[derive(Debug, Clone, Default)]
+struct StatsBuilder {
+    stats: DatasetStats,
+    objects_per_item: Vec<f64>,
+    frames_per_item: Vec<f64>,
+    relative_area: Vec<f64>,
+    relative_width: Vec<f64>,
+    relative_height: Vec<f64>,
+}
+
+impl StatsBuilder {
+    fn add_dataset(&mut self, dataset: &Dataset) -> Result<()> {
+        for item in dataset.iter_annotations() {
+            match item?.annotation {
+                MediaAnnotation::Image(ann) => {
+                    self.stats.num_images += 1;
+                    self.add_tags(ann.tags.iter().flatten());
+                    self.objects_per_item.push(ann.objects.len() as f64);
+
+                    for obj in &ann.objects {
+                        self.add_object(obj.class_name(), obj.labeler_login.as_deref());
+                        self.add_figure(&obj.geometry, Some(&ann.size));
+                    }
+                }
+                MediaAnnotation::Video(ann) => {
+                    self.stats.num_videos += 1;
+                    self.add_tags(&ann.tags);
+                    self.objects_per_item.push(ann.objects.len() as f64);
+                    self.frames_per_item.push(ann.frames_count as f64);
+
+                    for obj in &ann.objects {
+                        self.add_object(obj.class_title.clone(), obj.labeler_login.as_deref());
+                        self.add_tags(obj.tags.iter().flatten());
+                    }
+                    for figure in ann.frames.iter().flat_map(|frame| &frame.figures) {
+                        self.add_figure(&figure.geometry, Some(&ann.size));
+                    }
+                }
+                MediaAnnotation::PointCloud(ann) => {
+                    self.stats.num_point_clouds += 1;
+                    self.add_tags(&ann.tags);
+                    self.objects_per_item.push(ann.objects.len() as f64);
+
+                    for obj in &ann.objects {
+                        self.add_object(Some(obj.class_title.clone()), None);
+                        self.add_tags(&obj.tags);
+                    }
+                    for figure in &ann.figures {
+                        self.stats.num_figures += 1;
+                        *self
+                            .stats
+                            .geometry_types
+                            .entry(figure.geometry_type)
+                            .or_default() += 1;
+                    }
+                }
+                MediaAnnotation::PointCloudEpisode(ann) => {
+                    // The annotation only lists the annotated frames.
+                    let num_frames = match &dataset.kind {
+                        DatasetKind::PointCloudEpisode(dataset) => dataset.frame_point_map.len(),
+                        _ => ann.frames.len(),
+                    };
+                    self.stats.num_episodes += 1;
+                    self.add_tags(&ann.tags);
+                    self.objects_per_item.push(ann.objects.len() as f64);
+                    self.frames_per_item.push(num_frames as f64);
+
+                    for obj in &ann.objects {
+                        self.add_object(Some(obj.class_title.clone()), None);
+                        self.add_tags(&obj.tags);
+                    }
+                    for figure in ann.frames.iter().flat_map(|frame| &frame.figures) {
+                        if let Some(login) = &figure.labeler_login {
+                            *self.stats.labelers.entry(login.clone()).or_default() += 1;
+                        }
+                        self.add_figure(&figure.geometry, None);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_object(&mut self, class: Option<String>, labeler_login: Option<&str>) {
+        self.stats.num_objects += 1;
+        if let Some(class) = class {
+            *self.stats.classes.entry(class).or_default() += 1;
+        }
+        if let Some(login) = labeler_login {
+            *self.stats.labelers.entry(login.to_string()).or_default() += 1;
+        }
+    }
+
+    fn add_figure(&mut self, geometry: &Geometry, size: Option<&Size>) {
+        self.stats.num_figures += 1;
+        *self
+            .stats
+            .geometry_types
+            .entry(geometry.shape())
+            .or_default() += 1;
+        self.add_tags(geometry.tags().into_iter().flatten());
+
+        let Some(size) = size else {
+            return;
+        };
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        if !matches!(
+            geometry,
+            Geometry::Rectangle(_) | Geometry::Polygon(_) | Geometry::Bitmap(_)
+        ) {
+            return;
+        }
+
+        let measure = || -> Result<_> {
+            let geometry = geometry.as_2d()?;
+            Ok((geometry.area()?, geometry.bbox()?))
+        };
+        let (area, bbox) = match measure() {
+            Ok(measures) => measures,
+            Err(error) => {
+                warn!("skip the size of an undecodable figure: {error}");
+                self.stats.num_undecodable_figures += 1;
+                return;
+            }
+        };
+
+        let (width, height) = (size.width as f64, size.height as f64);
+        self.relative_area.push(area / (width * height));
+        if let Some(bbox) = bbox {
+            self.relative_width.push(bbox.width() / width);
+            self.relative_height.push(bbox.height() / height);
+        }
+    }
+
+    fn add_tags<'a, I>(&mut self, tags: I)
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        for tag in tags {
+            let stats = self.stats.tags.entry(tag.name.clone()).or_default();
+            stats.count += 1;
+
+            let value = match &tag.value {
+                Some(TagValue::Number(value)) => value.to_string(),
+                Some(TagValue::Float(value)) => value.to_string(),
+                Some(TagValue::Text(value) | TagValue::OneOf(value)) => value.clone(),
+                None => continue,
+            };
+            *stats.values.entry(value).or_default() += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        let stats = &mut self.stats;
+        let other_stats = &other.stats;
+        stats.num_images += other_stats.num_images;
+        stats.num_videos += other_stats.num_videos;
+        stats.num_point_clouds += other_stats.num_point_clouds;
+        stats.num_episodes += other_stats.num_episodes;
+        stats.num_objects += other_stats.num_objects;
+        stats.num_figures += other_stats.num_figures;
+        stats.num_undecodable_figures += other_stats.num_undecodable_figures;
+
+        for (class, count) in &other_stats.classes {
+            *stats.classes.entry(class.clone()).or_default() += count;
+        }
+        for (shape, count) in &other_stats.geometry_types {
+            *stats.geometry_types.entry(*shape).or_default() += count;
+        }
+        for (login, count) in &other_stats.labelers {
+            *stats.labelers.entry(login.clone()).or_default() += count;
+        }
+        for (name, tag) in &other_stats.tags {
+            let entry = stats.tags.entry(name.clone()).or_default();
+            entry.count += tag.count;
+            for (value, count) in &tag.values {
+                *entry.values.entry(value.clone()).or_default() += count;
+            }
+        }
+
+        self.objects_per_item.extend(&other.objects_per_item);
+        self.frames_per_item.extend(&other.frames_per_item);
+        self.relative_area.extend(&other.relative_area);
+        self.relative_width.extend(&other.relative_width);
+        self.relative_height.extend(&other.relative_height);
+    }
+
+    fn finish(self) -> DatasetStats {
+        DatasetStats {
+            objects_per_item: Summary::new(self.objects_per_item),
+            frames_per_item: Summary::new(self.frames_per_item),
+            object_sizes: ObjectSizeStats {
+                relative_area: Summary::new(self.relative_area),
+                relative_width: Summary::new(self.relative_width),
+                relative_height: Summary::new(self.relative_height),
+            },
+            ..self.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bitmap, BitmapGeometry, Points, RectangleGeometry};
+    use noisy_float::types::r64;
+
+    const SIZE: Size = Size {
+        width: 100,
+        height: 50,
+    };
+
+    fn rectangle(x1: f64, y1: f64, x2: f64, y2: f64) -> Geometry {
+        Geometry::Rectangle(RectangleGeometry {
+            tags: Some(vec![Tag::new("quality".to_string(), "good".to_string())]),
+            points: Points {
+                exterior: vec![(r64(x1), r64(y1)), (r64(x2), r64(y2))],
+                interior: vec![],
+            },
+        })
+    }
+
+    #[test]
+    fn summary_of_values() {
+        let summary = Summary::new(vec![4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(summary.count, 4);
+        assert_eq!((summary.min, summary.max), (Some(1.0), Some(4.0)));
+        assert_eq!((summary.mean, summary.median), (Some(2.5), Some(2.5)));
+
+        assert_eq!(Summary::new(vec![]), Summary::default());
+    }
+
+    #[test]
+    fn figure_sizes_are_relative_to_the_media() {
+        let mut builder = StatsBuilder::default();
+        builder.add_figure(&rectangle(0.0, 0.0, 50.0, 10.0), Some(&SIZE));
+        builder.add_figure(&rectangle(0.0, 0.0, 10.0, 10.0), None);
+        let stats = builder.finish();
+
+        assert_eq!(stats.num_figures, 2);
+        assert_eq!(stats.geometry_types[&Shape::Rectangle], 2);
+        assert_eq!(stats.tags["quality"].values["good"], 2);
+        assert_eq!(stats.object_sizes.relative_area.count, 1);
+        assert_eq!(stats.object_sizes.relative_area.mean, Some(0.1));
+        assert_eq!(stats.object_sizes.relative_width.mean, Some(0.5));
+        assert_eq!(stats.object_sizes.relative_height.mean, Some(0.2));
+    }
+
+    #[test]
+    fn undecodable_bitmaps_are_counted() {
+        let bitmap = Geometry::Bitmap(BitmapGeometry {
+            tags: None,
+            bitmap: Bitmap {
+                data_encoded: "not a bitmap".to_string(),
+                origin: [0, 0],
+            },
+            shape: None,
+        });
+
+        let mut builder = StatsBuilder::default();
+        builder.add_figure(&bitmap, Some(&SIZE));
+        builder.add_figure(&rectangle(0.0, 0.0, 50.0, 10.0), Some(&SIZE));
+
+        let mut total = StatsBuilder::default();
+        total.merge(&builder);
+        total.merge(&builder);
+        let stats = total.finish();
+
+        assert_eq!(stats.num_figures, 4);
+        assert_eq!(stats.num_undecodable_figures, 2);
+        assert_eq!(stats.geometry_types[&Shape::Bitmap], 2);
+        assert_eq!(stats.object_sizes.relative_area.count, 2);
+    }
+}
@@ -0,0 +1,99 @@
+mod memory;
+mod path_index;
+#[cfg(feature = "tar")]
+mod tar_archive;
+#[cfg(feature = "zip")]
+mod zip_archive;
+
+pub use memory::MemoryStorage;
+#[cfg(feature = "tar")]
+pub use tar_archive::TarStorage;
+#[cfg(feature = "zip")]
+pub use zip_archive::ZipStorage;
+
+use crate::{Error, Result};
+use itertools::Itertools;
+use std::{
+    fmt::Debug,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// A virtual file system from which projects are read and written.
+///
+/// Paths are interpreted by the storage. The file system storage takes
+/// them as is, while archive and memory storages take paths relative
+/// to their root.
+pub trait Storage: Debug + Send + Sync {
+    /// List the entries of a directory in sorted order.
+    fn list_dir(&self, dir: &Path) -> Result<Vec<DirEntry>>;
+
+    /// Open a file for reading.
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send + '_>>;
+
+    /// Open a file for writing, creating the parent directories and
+    /// truncating the existing content. The content is only guaranteed
+    /// to be stored once the writer is flushed. Storages are read-only
+    /// by default.
+    fn open_write(&self, path: &Path) -> Result<Box<dyn Write + Send + '_>> {
+        Err(Error::ReadOnlyStorage(path.to_path_buf()))
+    }
+
+    /// Check whether a file or directory exists.
+    fn exists(&self, path: &Path) -> bool;
+}
+
+/// An entry listed by [`Storage::list_dir`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// The storage backed by the local file system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsStorage;
+
+impl Storage for FsStorage {
+    fn list_dir(&self, dir: &Path) -> Result<Vec<DirEntry>> {
+        let entries = fs::read_dir(dir).map_err(|error| Error::read_dir_error(dir, error))?;
+
+        let mut entries: Vec<_> = entries
+            .map(|entry| -> Result<_> {
+                let entry = entry.map_err(|error| Error::read_dir_error(dir, error))?;
+                let path = entry.path();
+                let name = entry
+                    .file_name()
+                    .to_str()
+                    .ok_or_else(|| Error::expect_utf8_file_name(&path))?
+                    .to_string();
+
+                // Symbolic links are followed.
+                Ok(DirEntry {
+                    name,
+                    is_dir: path.is_dir(),
+                })
+            })
+            .try_collect()?;
+        entries.sort_unstable();
+        Ok(entries)
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send + '_>> {
+        let file = File::open(path).map_err(|error| Error::open_file_error(path, error))?;
+        Ok(Box::new(BufReader::new(file)))
+    }
+
+    fn open_write(&self, path: &Path) -> Result<Box<dyn Write + Send + '_>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| Error::create_dir_error(parent, error))?;
+        }
+        let file = File::create(path).map_err(|error| Error::write_file_error(path, error))?;
+        Ok(Box::new(BufWriter::new(file)))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
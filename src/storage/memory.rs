@@ -0,0 +1,187 @@
+use super::{path_index::PathIndex, DirEntry, Storage};
+use crate::{Error, Result};
+use std::{
+    collections::HashMap,
+    io::{self, Cursor, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+/// The storage keeping files in memory, which is handy to build and
+/// test projects without touching the disk.
+///
+/// Directories exist implicitly as the ancestors of files.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    inner: RwLock<MemoryFiles>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryFiles {
+    index: PathIndex,
+    files: HashMap<PathBuf, Arc<[u8]>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a file, replacing the existing content. It fails if the
+    /// path escapes the storage root.
+    pub fn insert<P, B>(&self, path: P, bytes: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: Into<Vec<u8>>,
+    {
+        let path = PathIndex::normalize(path.as_ref())?;
+        self.store(path, bytes.into());
+        Ok(())
+    }
+
+    /// Get the content of a file.
+    pub fn get<P>(&self, path: P) -> Option<Vec<u8>>
+    where
+        P: AsRef<Path>,
+    {
+        let inner = self.inner.read().unwrap();
+        let bytes = inner
+            .files
+            .get(&PathIndex::normalize(path.as_ref()).ok()?)?;
+        Some(bytes.to_vec())
+    }
+
+    fn store(&self, path: PathBuf, bytes: Vec<u8>) {
+        let mut inner = self.inner.write().unwrap();
+        inner.index.insert(&path, false);
+        inner.files.insert(path, bytes.into());
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn list_dir(&self, dir: &Path) -> Result<Vec<DirEntry>> {
+        self.inner
+            .read()
+            .unwrap()
+            .index
+            .list_dir(Path::new(""), dir)
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send + '_>> {
+        let inner = self.inner.read().unwrap();
+        let bytes = inner
+            .files
+            .get(&PathIndex::normalize(path)?)
+            .ok_or_else(|| Error::open_file_error(path, io::ErrorKind::NotFound.into()))?;
+        Ok(Box::new(Cursor::new(bytes.clone())))
+    }
+
+    fn open_write(&self, path: &Path) -> Result<Box<dyn Write + Send + '_>> {
+        Ok(Box::new(MemoryWriter {
+            storage: self,
+            path: PathIndex::normalize(path)?,
+            buffer: vec![],
+        }))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.read().unwrap().index.exists(path)
+    }
+}
+
+/// Buffers the written content, which is stored on flush. Dropping the
+/// writer without a flush discards the content, like a failed write.
+struct MemoryWriter<'a> {
+    storage: &'a MemoryStorage,
+    path: PathBuf,
+    buffer: Vec<u8>,
+}
+
+impl Write for MemoryWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.storage.store(self.path.clone(), self.buffer.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{utils::read_json, DatasetKind, ImageAnnotation, Project};
+
+    #[test]
+    fn writes_are_stored_on_flush() {
+        let storage = MemoryStorage::new();
+        let path = Path::new("ds/ann/a.png.json");
+
+        let mut writer = storage.open_write(path).unwrap();
+        writer.write_all(b"abc").unwrap();
+        assert!(!storage.exists(path));
+        writer.flush().unwrap();
+        assert_eq!(storage.get(path).as_deref(), Some(&b"abc"[..]));
+
+        writer.write_all(b"def").unwrap();
+        drop(writer);
+        assert_eq!(storage.get(path).as_deref(), Some(&b"abc"[..]));
+
+        let mut writer = storage.open_write(path).unwrap();
+        writer.write_all(b"ghi").unwrap();
+        drop(writer);
+        assert_eq!(storage.get(path).as_deref(), Some(&b"abc"[..]));
+
+        let names: Vec<String> = storage
+            .list_dir(Path::new("ds"))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["ann"]);
+    }
+
+    #[test]
+    fn reject_paths_escaping_the_root() {
+        let storage = MemoryStorage::new();
+        assert!(matches!(
+            storage.insert("../a.json", []),
+            Err(Error::PathEscapesRoot(_))
+        ));
+        assert!(storage.open_write(Path::new("../a.json")).is_err());
+    }
+
+    #[test]
+    fn write_ann_round_trip() {
+        let storage = Arc::new(MemoryStorage::new());
+        storage
+            .insert("meta.json", r#"{"classes": [], "tags": []}"#)
+            .unwrap();
+        storage.insert("ds/img/a.png", []).unwrap();
+        storage
+            .insert(
+                "ds/ann/a.png.json",
+                r#"{"name": "a.png", "size": {"width": 10, "height": 10}, "tags": [], "objects": []}"#,
+            )
+            .unwrap();
+
+        let project = Project::open_with(storage.clone(), Path::new("")).unwrap();
+        let DatasetKind::Image(dataset) = &project.datasets["ds"].kind else {
+            panic!("expect an image dataset");
+        };
+        let image = dataset.get_image("a.png").unwrap();
+        let mut ann = image.ann().unwrap();
+        ann.description = Some("updated".to_string());
+        image.write_ann(&ann).unwrap();
+
+        let stored: ImageAnnotation = read_json(&*storage, Path::new("ds/ann/a.png.json")).unwrap();
+        assert_eq!(stored, ann);
+
+        let project = Project::open_with(storage, Path::new("")).unwrap();
+        let DatasetKind::Image(dataset) = &project.datasets["ds"].kind else {
+            panic!("expect an image dataset");
+        };
+        assert_eq!(dataset.get_image("a.png").unwrap().ann().unwrap(), ann);
+    }
+}
@@ -0,0 +1,109 @@
+use super::DirEntry;
+use crate::{Error, Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+/// The directory tree of a virtual storage.
+#[derive(Debug, Default)]
+pub(super) struct PathIndex {
+    children: HashMap<PathBuf, BTreeMap<String, bool>>,
+}
+
+impl PathIndex {
+    /// Normalize a virtual path by dropping `.` and root components
+    /// and resolving `..` against the previous component. It fails if
+    /// the path escapes the root.
+    pub(super) fn normalize(path: &Path) -> Result<PathBuf> {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => normalized.push(name),
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return Err(Error::PathEscapesRoot(path.to_path_buf()));
+                    }
+                }
+                Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            }
+        }
+        Ok(normalized)
+    }
+
+    /// Register a file or directory along with its ancestors. The path
+    /// is expected to be normalized.
+    pub(super) fn insert(&mut self, path: &Path, is_dir: bool) {
+        let mut path = path.to_path_buf();
+        let mut is_dir = is_dir;
+
+        while let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            let name = name.to_string();
+            path.pop();
+            let siblings = self.children.entry(path.clone()).or_default();
+            let entry = siblings.entry(name).or_insert(is_dir);
+            *entry |= is_dir;
+            is_dir = true;
+        }
+        self.children.entry(path).or_default();
+    }
+
+    pub(super) fn list_dir(&self, root: &Path, dir: &Path) -> Result<Vec<DirEntry>> {
+        let children = self
+            .children
+            .get(&Self::normalize(dir)?)
+            .ok_or_else(|| Error::read_dir_error(root.join(dir), io::ErrorKind::NotFound.into()))?;
+        Ok(children
+            .iter()
+            .map(|(name, &is_dir)| DirEntry {
+                name: name.clone(),
+                is_dir,
+            })
+            .collect())
+    }
+
+    pub(super) fn exists(&self, path: &Path) -> bool {
+        let Ok(path) = Self::normalize(path) else {
+            return false;
+        };
+        if self.children.contains_key(&path) {
+            return true;
+        }
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return false;
+        };
+        let Some(name) = name.to_str() else {
+            return false;
+        };
+        self.children
+            .get(parent)
+            .is_some_and(|siblings| siblings.contains_key(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_resolves_parent_components() {
+        let normalize = |path: &str| PathIndex::normalize(Path::new(path)).unwrap();
+        assert_eq!(normalize("/ds/./img/x.png"), Path::new("ds/img/x.png"));
+        assert_eq!(
+            normalize("ds/img/../ann/x.json"),
+            Path::new("ds/ann/x.json")
+        );
+        assert_eq!(normalize("ds/.."), Path::new(""));
+    }
+
+    #[test]
+    fn normalize_rejects_escaping_paths() {
+        for path in ["..", "ds/../../x", "/../x"] {
+            assert!(matches!(
+                PathIndex::normalize(Path::new(path)),
+                Err(Error::PathEscapesRoot(_))
+            ));
+        }
+    }
+}
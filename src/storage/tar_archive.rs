@@ -0,0 +1,125 @@
+use super::{path_index::PathIndex, DirEntry, Storage};
+use crate::{Error, Result};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// The storage reading an uncompressed tar archive.
+///
+/// The archive is indexed once when opened. Each read seeks to the file
+/// content within the archive.
+#[derive(Debug)]
+pub struct TarStorage {
+    archive_path: PathBuf,
+    index: PathIndex,
+    /// The offset and size of each file.
+    files: HashMap<PathBuf, (u64, u64)>,
+}
+
+impl TarStorage {
+    /// Index a tar archive.
+    pub fn open<P>(archive_path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let archive_path = archive_path.as_ref();
+        let read_error = |error| Error::read_archive_error(archive_path, error);
+
+        let file = File::open(archive_path)
+            .map_err(|error| Error::open_file_error(archive_path, error))?;
+        let mut archive = tar::Archive::new(BufReader::new(file));
+
+        let mut index = PathIndex::default();
+        let mut files = HashMap::new();
+
+        for entry in archive.entries_with_seek().map_err(read_error)? {
+            let entry = entry.map_err(read_error)?;
+            let path = entry.path().map_err(read_error)?;
+            // Skip entries escaping the archive root.
+            let Ok(path) = PathIndex::normalize(&path) else {
+                continue;
+            };
+            let entry_type = entry.header().entry_type();
+
+            if entry_type.is_dir() {
+                index.insert(&path, true);
+            } else if entry_type.is_file() {
+                index.insert(&path, false);
+                files.insert(path, (entry.raw_file_position(), entry.size()));
+            }
+        }
+
+        Ok(Self {
+            archive_path: archive_path.to_path_buf(),
+            index,
+            files,
+        })
+    }
+}
+
+impl Storage for TarStorage {
+    fn list_dir(&self, dir: &Path) -> Result<Vec<DirEntry>> {
+        self.index.list_dir(&self.archive_path, dir)
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send + '_>> {
+        let open_error = |error| Error::open_file_error(self.archive_path.join(path), error);
+
+        let &(offset, size) = self
+            .files
+            .get(&PathIndex::normalize(path)?)
+            .ok_or_else(|| open_error(io::ErrorKind::NotFound.into()))?;
+        let mut file = File::open(&self.archive_path).map_err(open_error)?;
+        file.seek(SeekFrom::Start(offset)).map_err(open_error)?;
+        Ok(Box::new(BufReader::new(file.take(size))))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.index.exists(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DatasetKind, Project};
+    use std::sync::Arc;
+
+    #[test]
+    fn open_project_in_tar() {
+        let archive_path =
+            std::env::temp_dir().join(format!("supervisely-tar-{}.tar", std::process::id()));
+        let mut builder = tar::Builder::new(File::create(&archive_path).unwrap());
+        let files = [
+            ("project/meta.json", r#"{"classes": [], "tags": []}"#),
+            ("project/ds/img/a.png", ""),
+            (
+                "project/ds/ann/a.png.json",
+                r#"{"name": "a.png", "size": {"width": 10, "height": 10}, "tags": [], "objects": []}"#,
+            ),
+        ];
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap();
+
+        let result = TarStorage::open(&archive_path).and_then(|storage| {
+            let project = Project::open_with(Arc::new(storage), Path::new("project"))?;
+            let DatasetKind::Image(dataset) = &project.datasets["ds"].kind else {
+                panic!("expect an image dataset");
+            };
+            dataset.get_image("a.png").unwrap().ann()
+        });
+        std::fs::remove_file(&archive_path).unwrap();
+        assert_eq!(result.unwrap().size.width, 10);
+    }
+}
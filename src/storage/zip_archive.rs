@@ -0,0 +1,135 @@
+use super::{path_index::PathIndex, DirEntry, Storage};
+use crate::{Error, Result};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Cursor, Read},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use zip::ZipArchive;
+
+/// The largest buffer reserved ahead of decompressing a file, so that a
+/// forged size in the archive cannot exhaust the memory.
+const MAX_PREALLOCATION: u64 = 1 << 20;
+
+/// The storage reading a zip archive.
+///
+/// Reads are serialized over the archive and each file is decompressed
+/// into memory.
+#[derive(Debug)]
+pub struct ZipStorage {
+    archive_path: PathBuf,
+    index: PathIndex,
+    /// The index of each file within the archive.
+    files: HashMap<PathBuf, usize>,
+    archive: Mutex<ZipArchive<BufReader<File>>>,
+}
+
+impl ZipStorage {
+    /// Index a zip archive.
+    pub fn open<P>(archive_path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let archive_path = archive_path.as_ref();
+        let read_error = |error: zip::result::ZipError| {
+            Error::read_archive_error(archive_path, io::Error::from(error))
+        };
+
+        let file = File::open(archive_path)
+            .map_err(|error| Error::open_file_error(archive_path, error))?;
+        let mut archive = ZipArchive::new(BufReader::new(file)).map_err(read_error)?;
+
+        let mut index = PathIndex::default();
+        let mut files = HashMap::new();
+
+        for nth in 0..archive.len() {
+            let file = archive.by_index_raw(nth).map_err(read_error)?;
+            // Skip entries escaping the archive root.
+            let Some(path) = file.enclosed_name() else {
+                continue;
+            };
+            let path = PathIndex::normalize(&path)?;
+
+            index.insert(&path, file.is_dir());
+            if file.is_file() {
+                files.insert(path, nth);
+            }
+        }
+
+        Ok(Self {
+            archive_path: archive_path.to_path_buf(),
+            index,
+            files,
+            archive: Mutex::new(archive),
+        })
+    }
+}
+
+impl Storage for ZipStorage {
+    fn list_dir(&self, dir: &Path) -> Result<Vec<DirEntry>> {
+        self.index.list_dir(&self.archive_path, dir)
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send + '_>> {
+        let open_error = |error| Error::open_file_error(self.archive_path.join(path), error);
+
+        let &nth = self
+            .files
+            .get(&PathIndex::normalize(path)?)
+            .ok_or_else(|| open_error(io::ErrorKind::NotFound.into()))?;
+
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = archive
+            .by_index(nth)
+            .map_err(|error| open_error(error.into()))?;
+        let mut bytes = Vec::with_capacity(file.size().min(MAX_PREALLOCATION) as usize);
+        file.read_to_end(&mut bytes).map_err(open_error)?;
+        Ok(Box::new(Cursor::new(bytes)))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.index.exists(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DatasetKind, Project};
+    use std::{io::Write, sync::Arc};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    #[test]
+    fn open_project_in_zip() {
+        let archive_path =
+            std::env::temp_dir().join(format!("supervisely-zip-{}.zip", std::process::id()));
+        let mut writer = ZipWriter::new(File::create(&archive_path).unwrap());
+        let files = [
+            ("project/meta.json", r#"{"classes": [], "tags": []}"#),
+            ("project/ds/img/a.png", ""),
+            (
+                "project/ds/ann/a.png.json",
+                r#"{"name": "a.png", "size": {"width": 10, "height": 10}, "tags": [], "objects": []}"#,
+            ),
+        ];
+        for (path, content) in files {
+            writer
+                .start_file(path, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let storage = ZipStorage::open(&archive_path);
+        std::fs::remove_file(&archive_path).unwrap();
+        let project = Project::open_with(Arc::new(storage.unwrap()), Path::new("project")).unwrap();
+
+        let DatasetKind::Image(dataset) = &project.datasets["ds"].kind else {
+            panic!("expect an image dataset");
+        };
+        let ann = dataset.get_image("a.png").unwrap().ann().unwrap();
+        assert_eq!(ann.size.width, 10);
+    }
+}
@@ -1,3 +1,4 @@
+use noisy_float::types::R64;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -5,6 +6,9 @@ pub struct Tag {
     pub name: String,
     pub value: Option<TagValue>,
     pub id: Option<usize>,
+    /// The key of the tag in video and point cloud annotations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
     #[serde(rename = "tagId")]
     pub tag_id: Option<usize>,
     #[serde(rename = "labelerLogin")]
@@ -24,6 +28,7 @@ impl Tag {
             name,
             value: Some(value.into()),
             id: None,
+            key: None,
             tag_id: None,
             labeler_login: None,
             created_at: Some("2024-01-01T00:00:00.000Z".to_string()),
@@ -36,16 +41,40 @@ impl Tag {
 #[serde(untagged)]
 pub enum TagValue {
     Number(isize),
+    Float(R64),
     Text(String),
     OneOf(String),
 }
 
+impl TagValue {
+    /// Interpret the value as a number. Text values are parsed.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            TagValue::Number(value) => Some(*value as f64),
+            TagValue::Float(value) => Some(value.raw()),
+            TagValue::Text(text) | TagValue::OneOf(text) => text.trim().parse().ok(),
+        }
+    }
+}
+
 impl From<String> for TagValue {
     fn from(value: String) -> Self {
         Self::Text(value)
     }
 }
 
+impl From<&str> for TagValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<R64> for TagValue {
+    fn from(value: R64) -> Self {
+        Self::Float(value)
+    }
+}
+
 impl From<isize> for TagValue {
     fn from(value: isize) -> Self {
         Self::Number(value)
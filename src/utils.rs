@@ -1,6 +1,13 @@
-use crate::{Error, Result};
-use serde::Deserialize;
-use std::{fs::File, io::BufReader, path::Path};
+use crate::{Error, Result, Storage};
+use indexmap::IndexSet;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+#[cfg(feature = "async")]
+use std::{path::PathBuf, sync::Arc};
 
 pub fn load_json<T, P>(path: P) -> Result<T>
 where
@@ -8,9 +15,173 @@ where
     P: AsRef<Path>,
 {
     let path = path.as_ref();
-    let file = File::open(path).map_err(|error| Error::open_file_error(path, error))?;
-    let reader = BufReader::new(file);
-    let value = serde_json::from_reader(reader)
-        .map_err(|error| Error::parse_json_file_error(path, error))?;
-    Ok(value)
+    let bytes = fs::read(path).map_err(|error| Error::open_file_error(path, error))?;
+    parse_json(path, &bytes)
+}
+
+/// The async counterpart of [`load_json`].
+#[cfg(feature = "async")]
+pub async fn load_json_async<T, P>(path: P) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let bytes = tokio::fs::read(path)
+        .await
+        .map_err(|error| Error::open_file_error(path, error))?;
+    parse_json(path, &bytes)
+}
+
+/// Parse the JSON content read from the file at `path`.
+pub fn parse_json<T, P>(path: P, bytes: &[u8]) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+    P: AsRef<Path>,
+{
+    serde_json::from_slice(bytes).map_err(|error| Error::parse_json_file_error(path, error))
+}
+
+/// Load a JSON file from a storage.
+pub fn read_json<T>(storage: &dyn Storage, path: &Path) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut bytes = vec![];
+    storage
+        .open_read(path)?
+        .read_to_end(&mut bytes)
+        .map_err(|error| Error::open_file_error(path, error))?;
+    parse_json(path, &bytes)
+}
+
+/// The async counterpart of [`read_json`]. The storage is read on a
+/// blocking thread, whose panic is propagated and whose cancellation
+/// is reported as a failure to open the file.
+#[cfg(feature = "async")]
+pub async fn read_json_async<T>(storage: Arc<dyn Storage>, path: PathBuf) -> Result<T>
+where
+    T: for<'de> Deserialize<'de> + Send + 'static,
+{
+    let file = path.clone();
+    let result = tokio::task::spawn_blocking(move || read_json(&*storage, &file)).await;
+    match result {
+        Ok(result) => result,
+        Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+        Err(error) => Err(Error::open_file_error(&path, io::Error::other(error))),
+    }
+}
+
+/// Load a JSON file from a storage if the file exists.
+pub fn read_json_if_exists<T>(storage: &dyn Storage, path: &Path) -> Result<Option<T>>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    if !storage.exists(path) {
+        return Ok(None);
+    }
+    read_json(storage, path).map(Some)
+}
+
+/// Save a value to a JSON file in a storage.
+pub fn write_json<T>(storage: &dyn Storage, path: &Path, value: &T) -> Result<()>
+where
+    T: Serialize + ?Sized,
+{
+    let mut writer = storage.open_write(path)?;
+    serde_json::to_writer_pretty(&mut writer, value)
+        .map_err(|error| Error::write_json_file_error(path, error))?;
+    writer
+        .flush()
+        .map_err(|error| Error::write_file_error(path, error))?;
+    Ok(())
+}
+
+/// List the file names in a storage directory in sorted order.
+pub fn list_file_names(storage: &dyn Storage, dir: &Path) -> Result<IndexSet<String>> {
+    Ok(storage
+        .list_dir(dir)?
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect())
+}
+
+/// The async counterpart of [`list_file_names`].
+#[cfg(feature = "async")]
+pub async fn list_file_names_async<P>(dir: P) -> Result<IndexSet<String>>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .map_err(|error| Error::read_dir_error(dir, error))?;
+
+    let mut names = IndexSet::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|error| Error::read_dir_error(dir, error))?
+    {
+        let name = entry
+            .file_name()
+            .to_str()
+            .ok_or_else(|| Error::expect_utf8_file_name(entry.path()))?
+            .to_string();
+        names.insert(name);
+    }
+    names.sort_unstable();
+    Ok(names)
+}
+
+pub fn save_json<T, P>(path: P, value: &T) -> Result<()>
+where
+    T: Serialize + ?Sized,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let file = File::create(path).map_err(|error| Error::write_file_error(path, error))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, value)
+        .map_err(|error| Error::write_json_file_error(path, error))?;
+    writer
+        .flush()
+        .map_err(|error| Error::write_file_error(path, error))?;
+    Ok(())
+}
+
+/// Copy a directory recursively from one storage to another.
+pub fn copy_tree(
+    from_storage: &dyn Storage,
+    from: &Path,
+    to_storage: &dyn Storage,
+    to: &Path,
+) -> Result<()> {
+    for entry in from_storage.list_dir(from)? {
+        let src = from.join(&entry.name);
+        let dst = to.join(&entry.name);
+
+        if entry.is_dir {
+            copy_tree(from_storage, &src, to_storage, &dst)?;
+        } else {
+            copy_file(from_storage, &src, to_storage, &dst)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy a file from one storage to another.
+pub fn copy_file(
+    from_storage: &dyn Storage,
+    from: &Path,
+    to_storage: &dyn Storage,
+    to: &Path,
+) -> Result<()> {
+    let mut reader = from_storage.open_read(from)?;
+    let mut writer = to_storage.open_write(to)?;
+    io::copy(&mut reader, &mut writer)
+        .and_then(|_| writer.flush())
+        .map_err(|error| Error::copy_file_error(from, to, error))?;
+    Ok(())
 }